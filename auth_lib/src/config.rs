@@ -0,0 +1,138 @@
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Key material for one algorithm: a shared secret for HMAC, or PEM encoded keys otherwise.
+pub enum KeyMaterial<'a> {
+    Secret(&'a [u8]),
+    Pem(&'a [u8]),
+}
+
+/// Settings used to issue and validate access tokens.
+///
+/// Tokens are signed with the active key and carry its `kid` in the header. Any key
+/// registered with `with_verification_key` is still accepted, so keys can be rotated
+/// without invalidating live sessions.
+pub struct AuthConfig {
+    pub issuer: String,
+    pub audience: String,
+    pub expiry_secs: u64,
+    pub leeway_secs: u64,
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    verification_keys: HashMap<String, (Algorithm, DecodingKey)>,
+}
+
+impl AuthConfig {
+    /// Creates a config signing with `private_key`. For HMAC both keys are the same secret.
+    pub fn new(
+        kid: &str,
+        algorithm: Algorithm,
+        private_key: KeyMaterial,
+        public_key: KeyMaterial,
+    ) -> Result<Self, String> {
+        let encoding_key = encoding_key(algorithm, private_key)?;
+        let decoding_key = decoding_key(algorithm, public_key)?;
+
+        Ok(Self {
+            issuer: "todo-app".to_string(),
+            audience: "todo-app".to_string(),
+            expiry_secs: 3600,
+            leeway_secs: 60,
+            kid: kid.to_string(),
+            algorithm,
+            encoding_key,
+            verification_keys: HashMap::from([(kid.to_string(), (algorithm, decoding_key))]),
+        })
+    }
+
+    /// Shorthand for an HS256 config with a shared secret.
+    pub fn from_secret(kid: &str, secret: &str) -> Result<Self, String> {
+        let secret = secret.as_bytes();
+        Self::new(kid, Algorithm::HS256, KeyMaterial::Secret(secret), KeyMaterial::Secret(secret))
+    }
+
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = issuer.to_string();
+        self
+    }
+
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audience = audience.to_string();
+        self
+    }
+
+    pub fn expiry_secs(mut self, expiry_secs: u64) -> Self {
+        self.expiry_secs = expiry_secs;
+        self
+    }
+
+    pub fn leeway_secs(mut self, leeway_secs: u64) -> Self {
+        self.leeway_secs = leeway_secs;
+        self
+    }
+
+    /// Accepts tokens signed by a retired key that is no longer used for signing.
+    pub fn with_verification_key(mut self, kid: &str, algorithm: Algorithm, key: KeyMaterial) -> Result<Self, String> {
+        if kid == self.kid {
+            return Err(format!("kid {kid} is already the signing key"));
+        }
+        let key = decoding_key(algorithm, key)?;
+        self.verification_keys.insert(kid.to_string(), (algorithm, key));
+        Ok(self)
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub(crate) fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub(crate) fn verification_key(&self, kid: &str) -> Option<&(Algorithm, DecodingKey)> {
+        self.verification_keys.get(kid)
+    }
+}
+
+/// Parses an algorithm name such as `HS256`, `RS256` or `EdDSA`.
+pub fn parse_algorithm(name: &str) -> Result<Algorithm, String> {
+    Algorithm::from_str(name).map_err(|e| format!("Invalid JWT algorithm {name}: {e}"))
+}
+
+fn encoding_key(algorithm: Algorithm, key: KeyMaterial) -> Result<EncodingKey, String> {
+    let key = match (algorithm, key) {
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, KeyMaterial::Secret(secret)) => {
+            Ok(EncodingKey::from_secret(secret))
+        }
+        (Algorithm::ES256 | Algorithm::ES384, KeyMaterial::Pem(pem)) => EncodingKey::from_ec_pem(pem),
+        (Algorithm::EdDSA, KeyMaterial::Pem(pem)) => EncodingKey::from_ed_pem(pem),
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, KeyMaterial::Pem(_)) => {
+            return Err(format!("{algorithm:?} needs a secret, not a PEM key"));
+        }
+        (_, KeyMaterial::Pem(pem)) => EncodingKey::from_rsa_pem(pem),
+        (_, KeyMaterial::Secret(_)) => return Err(format!("{algorithm:?} needs a PEM key, not a secret")),
+    };
+    key.map_err(|e| e.to_string())
+}
+
+fn decoding_key(algorithm: Algorithm, key: KeyMaterial) -> Result<DecodingKey, String> {
+    let key = match (algorithm, key) {
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, KeyMaterial::Secret(secret)) => {
+            Ok(DecodingKey::from_secret(secret))
+        }
+        (Algorithm::ES256 | Algorithm::ES384, KeyMaterial::Pem(pem)) => DecodingKey::from_ec_pem(pem),
+        (Algorithm::EdDSA, KeyMaterial::Pem(pem)) => DecodingKey::from_ed_pem(pem),
+        (Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512, KeyMaterial::Pem(_)) => {
+            return Err(format!("{algorithm:?} needs a secret, not a PEM key"));
+        }
+        (_, KeyMaterial::Pem(pem)) => DecodingKey::from_rsa_pem(pem),
+        (_, KeyMaterial::Secret(_)) => return Err(format!("{algorithm:?} needs a PEM key, not a secret")),
+    };
+    key.map_err(|e| e.to_string())
+}
@@ -1,37 +1,66 @@
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
+use jsonwebtoken::{encode, decode, decode_header, Header, Validation};
 use serde::{Serialize, Deserialize};
 use std::time::{SystemTime, UNIX_EPOCH};
-use rand_core::OsRng;
+use rand_core::{OsRng, RngCore};
 use argon2::{Argon2, password_hash::{PasswordHasher, SaltString, PasswordVerifier, PasswordHash}};
+use sha2::{Digest, Sha256};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 
-#[derive(Debug, Serialize, Deserialize)]
+mod config;
+
+pub use config::{parse_algorithm, AuthConfig, KeyMaterial};
+pub use jsonwebtoken::Algorithm;
+
+/// Lifetime of a refresh token: 30 days.
+pub const REFRESH_TOKEN_TTL_SECS: u64 = 30 * 24 * 3600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,  // Subject (Username)
-    exp: usize,   // Expiration timestamp
+    pub exp: usize,   // Expiration timestamp
+    pub iat: usize,   // Issued-at timestamp
+    pub iss: String,  // Issuer
+    pub aud: String,  // Audience
+    pub jti: String,  // Token id, checked against the revocation list
 }
 
-/// Generates a JWT token based on username
-pub fn generate_token(username: &str, secret: &str) -> Result<String, String> {
-    let expiration = SystemTime::now()
+/// Generates a JWT token based on username, signed with the active key of `config`
+pub fn generate_token(username: &str, config: &AuthConfig) -> Result<String, String> {
+    let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| e.to_string())?
-        .as_secs() + 3600; // 1-hour expiry
+        .as_secs();
 
     let claims = Claims {
         sub: username.to_owned(),
-        exp: expiration as usize,
+        exp: (now + config.expiry_secs) as usize,
+        iat: now as usize,
+        iss: config.issuer.clone(),
+        aud: config.audience.clone(),
+        jti: hex(&random_bytes::<16>()),
     };
 
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref())).map_err(|e| e.to_string())
+    let mut header = Header::new(config.algorithm());
+    header.kid = Some(config.kid().to_string());
+
+    encode(&header, &claims, config.encoding_key()).map_err(|e| e.to_string())
 }
 
-/// Validates a JWT token
-pub fn validate_token(token: &str, secret: &str) -> Result<Claims, String> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    ).map_err(|e| e.to_string())?;
+/// Validates a JWT token against the key named by its `kid`, enforcing `iss` and `aud`.
+/// Tokens without a `kid` are checked against the active key.
+pub fn validate_token(token: &str, config: &AuthConfig) -> Result<Claims, String> {
+    let header = decode_header(token).map_err(|e| e.to_string())?;
+    let kid = header.kid.as_deref().unwrap_or(config.kid());
+    let (algorithm, key) = config.verification_key(kid)
+        .ok_or_else(|| format!("Unknown signing key {kid}"))?;
+
+    let mut validation = Validation::new(*algorithm);
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.audience]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    validation.leeway = config.leeway_secs;
+
+    let token_data = decode::<Claims>(token, key, &validation).map_err(|e| e.to_string())?;
     Ok(token_data.claims)
 }
 
@@ -50,8 +79,64 @@ pub fn hash_password(password: &str) -> Result<String, String> {
 pub fn verify_password(password: &str, stored_hash: &str) -> bool {
     let parsed_hash = PasswordHash::new(stored_hash);
     match parsed_hash {
-        Err(_) => return false,
+        Err(_) => false,
         Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()
     }
 }
 
+/// Generates an opaque refresh token. Only its hash should be persisted.
+pub fn generate_refresh_token() -> String {
+    URL_SAFE_NO_PAD.encode(random_bytes::<32>())
+}
+
+/// Hash a refresh token for storage and lookup
+pub fn hash_refresh_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::EncodingKey;
+
+    fn token_with_kid(kid: Option<&str>, secret: &str, config: &AuthConfig) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as usize;
+        let claims = Claims {
+            sub: "alice".to_string(),
+            exp: now + 60,
+            iat: now,
+            iss: config.issuer.clone(),
+            aud: config.audience.clone(),
+            jti: "1".to_string(),
+        };
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = kid.map(str::to_string);
+        encode(&header, &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn tokens_without_a_kid_use_the_active_key() {
+        let config = AuthConfig::from_secret("current", "secret").unwrap();
+        let claims = validate_token(&token_with_kid(None, "secret", &config), &config).unwrap();
+        assert_eq!(claims.sub, "alice");
+
+        assert!(validate_token(&token_with_kid(None, "other-secret", &config), &config).is_err());
+    }
+
+    #[test]
+    fn tokens_with_an_unknown_kid_are_rejected() {
+        let config = AuthConfig::from_secret("current", "secret").unwrap();
+        let token = token_with_kid(Some("retired"), "secret", &config);
+        assert_eq!(validate_token(&token, &config).unwrap_err(), "Unknown signing key retired");
+    }
+}
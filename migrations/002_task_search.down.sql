@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_tracking_search;
+DROP INDEX IF EXISTS idx_tasks_search;
@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS idx_tasks_search ON tasks
+    USING GIN (to_tsvector('english', title || ' ' || description));
+
+CREATE INDEX IF NOT EXISTS idx_tracking_search ON tracking
+    USING GIN (to_tsvector('english', status));
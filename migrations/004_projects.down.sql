@@ -0,0 +1,5 @@
+DROP FUNCTION IF EXISTS task_role(uuid, VARCHAR, VARCHAR);
+DROP FUNCTION IF EXISTS project_role(uuid, VARCHAR);
+ALTER TABLE tasks DROP COLUMN IF EXISTS project_uuid;
+DROP TABLE IF EXISTS project_members;
+DROP TABLE IF EXISTS projects;
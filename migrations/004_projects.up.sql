@@ -0,0 +1,41 @@
+CREATE TABLE IF NOT EXISTS projects (
+    project_uuid uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (created_by) REFERENCES users(username)
+);
+
+CREATE TABLE IF NOT EXISTS project_members (
+    project_uuid uuid NOT NULL,
+    user_username VARCHAR(255) NOT NULL,
+    role VARCHAR(32) NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (project_uuid, user_username),
+    FOREIGN KEY (project_uuid) REFERENCES projects(project_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (user_username) REFERENCES users(username)
+);
+
+CREATE INDEX idx_project_members_user_username ON project_members(user_username);
+
+-- Tasks without a project are personal and only visible to their creator.
+ALTER TABLE tasks ADD COLUMN project_uuid uuid REFERENCES projects(project_uuid);
+
+CREATE INDEX idx_tasks_project_uuid ON tasks(project_uuid);
+
+-- Role of a user in a project, NULL when not a member.
+CREATE OR REPLACE FUNCTION project_role(p_project_uuid uuid, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT role FROM project_members
+    WHERE project_uuid = p_project_uuid AND user_username = p_username
+$$ LANGUAGE SQL STABLE;
+
+-- Role of a user on a task: 'owner' of a personal task, the member role for project tasks.
+CREATE OR REPLACE FUNCTION task_role(p_project_uuid uuid, p_task_owner VARCHAR, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN p_project_uuid IS NULL THEN
+            CASE WHEN p_task_owner = p_username THEN 'owner' END
+        ELSE project_role(p_project_uuid, p_username)
+    END
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,14 @@
+DROP FUNCTION IF EXISTS task_role(tasks, VARCHAR);
+
+CREATE OR REPLACE FUNCTION task_role(p_project_uuid uuid, p_task_owner VARCHAR, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN p_project_uuid IS NULL THEN
+            CASE WHEN p_task_owner = p_username THEN 'owner' END
+        ELSE project_role(p_project_uuid, p_username)
+    END
+$$ LANGUAGE SQL STABLE;
+
+DROP INDEX IF EXISTS idx_tasks_assignee;
+
+ALTER TABLE tasks DROP COLUMN IF EXISTS assignee;
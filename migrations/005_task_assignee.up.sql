@@ -0,0 +1,19 @@
+ALTER TABLE tasks ADD COLUMN assignee VARCHAR(255) REFERENCES users(username);
+
+CREATE INDEX idx_tasks_assignee ON tasks(assignee);
+
+DROP FUNCTION task_role(uuid, VARCHAR, VARCHAR);
+
+-- Role of a user on a task: 'owner' of a personal task and 'editor' when it is assigned to them,
+-- the member role for project tasks. Takes the whole row so new columns don't change the signature.
+CREATE OR REPLACE FUNCTION task_role(t tasks, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN t.project_uuid IS NULL THEN
+            CASE
+                WHEN t.user_username = p_username THEN 'owner'
+                WHEN t.assignee = p_username THEN 'editor'
+            END
+        ELSE project_role(t.project_uuid, p_username)
+    END
+$$ LANGUAGE SQL STABLE;
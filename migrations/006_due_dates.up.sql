@@ -0,0 +1,16 @@
+ALTER TABLE tasks ADD COLUMN due_at TIMESTAMPTZ;
+-- Seconds before due_at at which a reminder is sent.
+ALTER TABLE tasks ADD COLUMN remind_offsets INTEGER[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_tasks_due_at ON tasks(due_at) WHERE due_at IS NOT NULL;
+
+-- Notifications already sent, so every reminder fires once per due date.
+CREATE TABLE IF NOT EXISTS task_notifications (
+    task_uuid uuid NOT NULL,
+    due_at TIMESTAMPTZ NOT NULL,
+    kind VARCHAR(32) NOT NULL CHECK (kind IN ('reminder', 'overdue')),
+    offset_secs INTEGER NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (task_uuid, due_at, kind, offset_secs),
+    FOREIGN KEY (task_uuid) REFERENCES tasks(task_uuid) ON DELETE CASCADE
+);
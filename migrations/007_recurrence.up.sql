@@ -0,0 +1,8 @@
+-- RRULE of a recurring task, e.g. FREQ=WEEKLY;BYDAY=MO.
+ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+-- First task of the series; NULL on the first task itself.
+ALTER TABLE tasks ADD COLUMN series_uuid uuid;
+ALTER TABLE tasks ADD COLUMN occurrence INTEGER NOT NULL DEFAULT 1;
+
+-- Completing an occurrence twice must not generate the next one twice.
+CREATE UNIQUE INDEX idx_tasks_series_occurrence ON tasks(series_uuid, occurrence) WHERE series_uuid IS NOT NULL;
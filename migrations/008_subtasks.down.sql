@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_tasks_parent_task_uuid;
+
+ALTER TABLE tasks DROP COLUMN IF EXISTS subtask_position;
+ALTER TABLE tasks DROP COLUMN IF EXISTS parent_task_uuid;
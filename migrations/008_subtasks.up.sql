@@ -0,0 +1,5 @@
+ALTER TABLE tasks ADD COLUMN parent_task_uuid uuid REFERENCES tasks(task_uuid);
+-- Order among the subtasks of the same parent.
+ALTER TABLE tasks ADD COLUMN subtask_position INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_tasks_parent_task_uuid ON tasks(parent_task_uuid, subtask_position);
@@ -0,0 +1,3 @@
+DROP FUNCTION IF EXISTS task_blocked(uuid);
+
+DROP TABLE IF EXISTS task_dependencies;
@@ -0,0 +1,24 @@
+-- task_uuid is blocked by blocker_uuid until the blocker is done.
+CREATE TABLE IF NOT EXISTS task_dependencies (
+    task_uuid uuid NOT NULL,
+    blocker_uuid uuid NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (task_uuid, blocker_uuid),
+    CHECK (task_uuid <> blocker_uuid),
+    FOREIGN KEY (task_uuid) REFERENCES tasks(task_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (blocker_uuid) REFERENCES tasks(task_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(username)
+);
+
+CREATE INDEX idx_task_dependencies_blocker_uuid ON task_dependencies(blocker_uuid);
+
+-- Whether the task has a blocker that is not done yet.
+CREATE OR REPLACE FUNCTION task_blocked(p_task_uuid uuid)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS(
+        SELECT 1 FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = p_task_uuid AND b.status <> 'done'
+    )
+$$ LANGUAGE SQL STABLE;
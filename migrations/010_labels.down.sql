@@ -0,0 +1,5 @@
+DROP FUNCTION IF EXISTS task_label_list(uuid);
+
+DROP TABLE IF EXISTS task_labels;
+
+DROP TABLE IF EXISTS labels;
@@ -0,0 +1,42 @@
+-- Labels belong either to a user (personal tasks) or to a project (its tasks).
+CREATE TABLE IF NOT EXISTS labels (
+    label_uuid uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    color VARCHAR(7),
+    user_username VARCHAR(255),
+    project_uuid uuid,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((user_username IS NULL) <> (project_uuid IS NULL)),
+    FOREIGN KEY (user_username) REFERENCES users(username),
+    FOREIGN KEY (project_uuid) REFERENCES projects(project_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(username)
+);
+
+CREATE UNIQUE INDEX idx_labels_user_name ON labels(user_username, lower(name)) WHERE user_username IS NOT NULL;
+CREATE UNIQUE INDEX idx_labels_project_name ON labels(project_uuid, lower(name)) WHERE project_uuid IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS task_labels (
+    task_uuid uuid NOT NULL,
+    label_uuid uuid NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (task_uuid, label_uuid),
+    FOREIGN KEY (task_uuid) REFERENCES tasks(task_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (label_uuid) REFERENCES labels(label_uuid) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_task_labels_label_uuid ON task_labels(label_uuid);
+
+-- Labels of a task as a JSON array, in the shape of LabelDetail.
+CREATE OR REPLACE FUNCTION task_label_list(p_task_uuid uuid)
+RETURNS JSONB AS $$
+    SELECT COALESCE(jsonb_agg(jsonb_build_object(
+        'label_uuid', l.label_uuid,
+        'name', l.name,
+        'color', l.color,
+        'project_uuid', l.project_uuid
+    ) ORDER BY lower(l.name)), '[]'::jsonb)
+    FROM task_labels tl
+    JOIN labels l ON l.label_uuid = tl.label_uuid
+    WHERE tl.task_uuid = p_task_uuid
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,9 @@
+DROP FUNCTION IF EXISTS priority_rank(VARCHAR);
+
+DROP INDEX IF EXISTS idx_tasks_board_status_position;
+
+DROP FUNCTION IF EXISTS task_board(uuid, VARCHAR);
+
+ALTER TABLE tasks DROP COLUMN IF EXISTS position;
+
+ALTER TABLE tasks DROP COLUMN IF EXISTS priority;
@@ -0,0 +1,29 @@
+ALTER TABLE tasks ADD COLUMN priority VARCHAR(16) NOT NULL DEFAULT 'medium'
+    CHECK (priority IN ('low', 'medium', 'high', 'urgent'));
+
+-- Board a task is shown on: its project, or the personal tasks of its creator.
+CREATE OR REPLACE FUNCTION task_board(p_project_uuid uuid, p_owner VARCHAR)
+RETURNS TEXT AS $$
+    SELECT COALESCE('project:' || p_project_uuid::text, 'user:' || p_owner)
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Order of a task within its status column on its board. Moves place a task halfway between its
+-- new neighbours, so only the moved task is written.
+ALTER TABLE tasks ADD COLUMN position DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+UPDATE tasks SET position = ordered.rn * 1024
+FROM (
+    SELECT task_uuid, row_number() OVER (
+        PARTITION BY task_board(project_uuid, user_username), status ORDER BY created_at, task_uuid
+    ) AS rn
+    FROM tasks
+) ordered
+WHERE tasks.task_uuid = ordered.task_uuid;
+
+CREATE INDEX idx_tasks_board_status_position ON tasks(task_board(project_uuid, user_username), status, position);
+
+-- Sort key of a priority, from low to urgent.
+CREATE OR REPLACE FUNCTION priority_rank(p_priority VARCHAR)
+RETURNS INTEGER AS $$
+    SELECT CASE p_priority WHEN 'low' THEN 0 WHEN 'medium' THEN 1 WHEN 'high' THEN 2 WHEN 'urgent' THEN 3 END
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,22 @@
+CREATE OR REPLACE FUNCTION task_blocked(p_task_uuid uuid)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS(
+        SELECT 1 FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = p_task_uuid AND b.status <> 'done'
+    )
+$$ LANGUAGE SQL STABLE;
+
+DROP FUNCTION IF EXISTS initial_status(uuid, VARCHAR);
+
+DROP FUNCTION IF EXISTS workflow_allows(uuid, VARCHAR, VARCHAR, VARCHAR);
+
+DROP FUNCTION IF EXISTS status_category(uuid, VARCHAR, VARCHAR);
+
+DROP FUNCTION IF EXISTS workflow_of(uuid, VARCHAR);
+
+DROP TABLE IF EXISTS workflow_transitions;
+
+DROP TABLE IF EXISTS workflow_statuses;
+
+DROP TABLE IF EXISTS workflows;
@@ -0,0 +1,90 @@
+-- Custom workflows for a user's personal tasks or a project's tasks. Scopes without one use the
+-- built-in todo/in_progress/done workflow.
+CREATE TABLE IF NOT EXISTS workflows (
+    workflow_uuid uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_username VARCHAR(255) UNIQUE,
+    project_uuid uuid UNIQUE,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((user_username IS NULL) <> (project_uuid IS NULL)),
+    FOREIGN KEY (user_username) REFERENCES users(username),
+    FOREIGN KEY (project_uuid) REFERENCES projects(project_uuid) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(username)
+);
+
+CREATE TABLE IF NOT EXISTS workflow_statuses (
+    workflow_uuid uuid NOT NULL,
+    key VARCHAR(64) NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    category VARCHAR(16) NOT NULL CHECK (category IN ('todo', 'active', 'done')),
+    position INTEGER NOT NULL,
+    PRIMARY KEY (workflow_uuid, key),
+    FOREIGN KEY (workflow_uuid) REFERENCES workflows(workflow_uuid) ON DELETE CASCADE
+);
+
+-- Allowed status changes. A workflow without transitions allows every change.
+CREATE TABLE IF NOT EXISTS workflow_transitions (
+    workflow_uuid uuid NOT NULL,
+    from_key VARCHAR(64) NOT NULL,
+    to_key VARCHAR(64) NOT NULL,
+    PRIMARY KEY (workflow_uuid, from_key, to_key),
+    FOREIGN KEY (workflow_uuid, from_key) REFERENCES workflow_statuses(workflow_uuid, key) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_uuid, to_key) REFERENCES workflow_statuses(workflow_uuid, key) ON DELETE CASCADE
+);
+
+-- Workflow of a project task, or of a personal task of p_username. NULL for the built-in one.
+CREATE OR REPLACE FUNCTION workflow_of(p_project_uuid uuid, p_username VARCHAR)
+RETURNS uuid AS $$
+    SELECT workflow_uuid FROM workflows
+    WHERE CASE WHEN p_project_uuid IS NULL THEN user_username = p_username ELSE project_uuid = p_project_uuid END
+$$ LANGUAGE SQL STABLE;
+
+-- Category (todo, active or done) of a status in the workflow of a task.
+CREATE OR REPLACE FUNCTION status_category(p_project_uuid uuid, p_username VARCHAR, p_status VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE WHEN w.workflow_uuid IS NULL THEN
+        CASE p_status WHEN 'todo' THEN 'todo' WHEN 'in_progress' THEN 'active' WHEN 'done' THEN 'done' END
+    ELSE
+        (SELECT category FROM workflow_statuses WHERE workflow_uuid = w.workflow_uuid AND key = p_status)
+    END
+    FROM (SELECT workflow_of(p_project_uuid, p_username) AS workflow_uuid) w
+$$ LANGUAGE SQL STABLE;
+
+-- Whether a task may change from p_from to p_to; p_from is NULL for new tasks.
+CREATE OR REPLACE FUNCTION workflow_allows(p_project_uuid uuid, p_username VARCHAR, p_from VARCHAR, p_to VARCHAR)
+RETURNS BOOLEAN AS $$
+    SELECT p_from IS NOT DISTINCT FROM p_to OR (
+        status_category(p_project_uuid, p_username, p_to) IS NOT NULL
+        AND (
+            p_from IS NULL
+            OR w.workflow_uuid IS NULL
+            OR NOT EXISTS(SELECT 1 FROM workflow_transitions WHERE workflow_uuid = w.workflow_uuid)
+            OR EXISTS(
+                SELECT 1 FROM workflow_transitions
+                WHERE workflow_uuid = w.workflow_uuid AND from_key = p_from AND to_key = p_to
+            )
+        )
+    )
+    FROM (SELECT workflow_of(p_project_uuid, p_username) AS workflow_uuid) w
+$$ LANGUAGE SQL STABLE;
+
+-- First status of category todo, given to new occurrences of recurring tasks.
+CREATE OR REPLACE FUNCTION initial_status(p_project_uuid uuid, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT COALESCE(
+        (SELECT key FROM workflow_statuses
+         WHERE workflow_uuid = workflow_of(p_project_uuid, p_username) AND category = 'todo'
+         ORDER BY position LIMIT 1),
+        'todo'
+    )
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION task_blocked(p_task_uuid uuid)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS(
+        SELECT 1 FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = p_task_uuid
+          AND status_category(b.project_uuid, b.user_username, b.status) IS DISTINCT FROM 'done'
+    )
+$$ LANGUAGE SQL STABLE;
@@ -0,0 +1,17 @@
+DROP INDEX IF EXISTS idx_tracking_search;
+
+DROP FUNCTION IF EXISTS tracking_text(TEXT, JSONB);
+
+DROP INDEX IF EXISTS idx_tracking_event_type;
+
+UPDATE tracking SET message = concat_ws(' ', event_type, changes::text) WHERE message IS NULL;
+
+ALTER TABLE tracking DROP COLUMN IF EXISTS changes;
+ALTER TABLE tracking DROP COLUMN IF EXISTS actor;
+ALTER TABLE tracking DROP COLUMN IF EXISTS event_type;
+
+ALTER TABLE tracking ALTER COLUMN message SET NOT NULL;
+ALTER TABLE tracking RENAME COLUMN message TO status;
+
+CREATE INDEX IF NOT EXISTS idx_tracking_search ON tracking
+    USING GIN (to_tsvector('english', status));
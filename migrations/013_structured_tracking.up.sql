@@ -0,0 +1,30 @@
+-- Task history as typed events: what happened, who did it and the value of each changed field
+-- before and after. Entries written before keep their free text in `message` as `legacy` events.
+DROP INDEX IF EXISTS idx_tracking_search;
+
+ALTER TABLE tracking RENAME COLUMN status TO message;
+ALTER TABLE tracking ALTER COLUMN message DROP NOT NULL;
+
+ALTER TABLE tracking ADD COLUMN event_type VARCHAR(32) NOT NULL DEFAULT 'legacy'
+    CHECK (event_type IN (
+        'created', 'updated', 'status_changed', 'assigned', 'moved', 'parent_changed',
+        'dependency_added', 'dependency_removed', 'label_added', 'label_removed',
+        'occurrence_scheduled', 'legacy'
+    ));
+ALTER TABLE tracking ALTER COLUMN event_type DROP DEFAULT;
+
+ALTER TABLE tracking ADD COLUMN actor VARCHAR(255) REFERENCES users(username);
+
+-- Array of {"field", "before", "after"} objects.
+ALTER TABLE tracking ADD COLUMN changes JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX idx_tracking_event_type ON tracking(task_task_uuid, event_type);
+
+-- Searchable text of a history entry: its legacy message and the new values of the changed fields.
+CREATE OR REPLACE FUNCTION tracking_text(p_message TEXT, p_changes JSONB)
+RETURNS TEXT AS $$
+    SELECT concat_ws(' ', p_message, (SELECT string_agg(c->>'after', ' ') FROM jsonb_array_elements(p_changes) c))
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX idx_tracking_search ON tracking
+    USING GIN (to_tsvector('english', tracking_text(message, changes)));
@@ -0,0 +1,38 @@
+-- Tasks still in the trash become regular tasks again.
+DELETE FROM tracking WHERE event_type IN ('deleted', 'restored');
+
+ALTER TABLE tracking DROP CONSTRAINT tracking_event_type_check;
+ALTER TABLE tracking ADD CONSTRAINT tracking_event_type_check
+    CHECK (event_type IN (
+        'created', 'updated', 'status_changed', 'assigned', 'moved', 'parent_changed',
+        'dependency_added', 'dependency_removed', 'label_added', 'label_removed',
+        'occurrence_scheduled', 'legacy'
+    ));
+
+CREATE OR REPLACE FUNCTION task_blocked(p_task_uuid uuid)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS(
+        SELECT 1 FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = p_task_uuid
+          AND status_category(b.project_uuid, b.user_username, b.status) IS DISTINCT FROM 'done'
+    )
+$$ LANGUAGE SQL STABLE;
+
+CREATE OR REPLACE FUNCTION task_role(t tasks, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN t.project_uuid IS NULL THEN
+            CASE
+                WHEN t.user_username = p_username THEN 'owner'
+                WHEN t.assignee = p_username THEN 'editor'
+            END
+        ELSE project_role(t.project_uuid, p_username)
+    END
+$$ LANGUAGE SQL STABLE;
+
+DROP FUNCTION IF EXISTS task_member_role(tasks, VARCHAR);
+
+DROP INDEX IF EXISTS idx_tasks_deleted_at;
+
+ALTER TABLE tasks DROP COLUMN IF EXISTS deleted_at;
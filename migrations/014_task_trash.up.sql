@@ -0,0 +1,43 @@
+-- Deleted tasks go to the trash first: they keep their subtasks, dependencies, labels and history
+-- until they are restored or purged once the retention period is over.
+ALTER TABLE tasks ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_tasks_deleted_at ON tasks(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Role of a user on a task whether or not it is in the trash, to list and restore trashed tasks.
+CREATE OR REPLACE FUNCTION task_member_role(t tasks, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN t.project_uuid IS NULL THEN
+            CASE
+                WHEN t.user_username = p_username THEN 'owner'
+                WHEN t.assignee = p_username THEN 'editor'
+            END
+        ELSE project_role(t.project_uuid, p_username)
+    END
+$$ LANGUAGE SQL STABLE;
+
+-- Trashed tasks have no role, so every query filtering on task_role leaves them out.
+CREATE OR REPLACE FUNCTION task_role(t tasks, p_username VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT CASE WHEN t.deleted_at IS NULL THEN task_member_role(t, p_username) END
+$$ LANGUAGE SQL STABLE;
+
+-- Trashed blockers no longer block.
+CREATE OR REPLACE FUNCTION task_blocked(p_task_uuid uuid)
+RETURNS BOOLEAN AS $$
+    SELECT EXISTS(
+        SELECT 1 FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = p_task_uuid AND b.deleted_at IS NULL
+          AND status_category(b.project_uuid, b.user_username, b.status) IS DISTINCT FROM 'done'
+    )
+$$ LANGUAGE SQL STABLE;
+
+ALTER TABLE tracking DROP CONSTRAINT tracking_event_type_check;
+ALTER TABLE tracking ADD CONSTRAINT tracking_event_type_check
+    CHECK (event_type IN (
+        'created', 'updated', 'status_changed', 'assigned', 'moved', 'parent_changed',
+        'dependency_added', 'dependency_removed', 'label_added', 'label_removed',
+        'occurrence_scheduled', 'deleted', 'restored', 'legacy'
+    ));
@@ -1,3 +1,4 @@
+use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use axum::{
     routing::{get, post, delete, patch},
@@ -9,47 +10,181 @@ use std::sync::Arc;
 use crate::persistence::{
     tasks_dbo::{TasksDbo, TasksDboImpl},
     users_dbo::{UsersDbo, UsersDboImpl},
-    tracking_dbo::{TrackingDbo, TrackingDboImpl}
+    tracking_dbo::{TrackingDbo, TrackingDboImpl},
+    tokens_dbo::{TokensDbo, TokensDboImpl},
+    projects_dbo::{ProjectsDbo, ProjectsDboImpl},
+    labels_dbo::{LabelsDbo, LabelsDboImpl},
+    workflows_dbo::{WorkflowsDbo, WorkflowsDboImpl},
+    memory::{
+        MemoryStore,
+        tasks_dbo::MemoryTasksDbo,
+        users_dbo::MemoryUsersDbo,
+        tracking_dbo::MemoryTrackingDbo,
+        tokens_dbo::MemoryTokensDbo,
+        projects_dbo::MemoryProjectsDbo,
+        labels_dbo::MemoryLabelsDbo,
+        workflows_dbo::MemoryWorkflowsDbo
+    }
 };
 use crate::handlers::*;
 use crate::auth::auth;
 use crate::logging::logging_middleware;
+use crate::graphql::{build_schema, graphql_handler, graphql_ws_handler, graphiql, TodoSchema};
+use crate::events::TaskEvents;
+use crate::services::tasks::TaskService;
+use crate::config::{auth_config_from_env, storage_from_env, Storage};
+use auth_lib::AuthConfig;
 
 #[derive(Clone)]
 pub struct AppState {
     pub tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
     pub users_dbo: Arc<dyn UsersDbo + Send + Sync>,
     pub tracking_dbo: Arc<dyn TrackingDbo + Send + Sync>,
+    pub tokens_dbo: Arc<dyn TokensDbo + Send + Sync>,
+    pub projects_dbo: Arc<dyn ProjectsDbo + Send + Sync>,
+    pub labels_dbo: Arc<dyn LabelsDbo + Send + Sync>,
+    pub workflows_dbo: Arc<dyn WorkflowsDbo + Send + Sync>,
+    pub auth_config: Arc<AuthConfig>,
+    pub task_events: TaskEvents,
+    pub task_service: TaskService,
+    pub schema: TodoSchema,
 }
 
-pub async fn prepare_app() -> IntoMakeService<Router>{
-  let pool = PgPoolOptions::new()
-      .max_connections(5) // Set max connection pool size
-      .connect(&std::env::var("DATABASE_URL").expect("Invalid database URL"))
-      .await.expect("Unable to create postgres connection pool");
+impl AppState {
+    /// Wires the DBOs together with the event bus, the task service and the GraphQL schema built on them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+        users_dbo: Arc<dyn UsersDbo + Send + Sync>,
+        tracking_dbo: Arc<dyn TrackingDbo + Send + Sync>,
+        tokens_dbo: Arc<dyn TokensDbo + Send + Sync>,
+        projects_dbo: Arc<dyn ProjectsDbo + Send + Sync>,
+        labels_dbo: Arc<dyn LabelsDbo + Send + Sync>,
+        workflows_dbo: Arc<dyn WorkflowsDbo + Send + Sync>,
+        auth_config: Arc<AuthConfig>,
+    ) -> AppState {
+        let task_events = TaskEvents::new();
+        let task_service = TaskService::new(
+            tasks_dbo.clone(),
+            tracking_dbo.clone(),
+            labels_dbo.clone(),
+            task_events.clone()
+        );
+        let schema = build_schema(
+            tasks_dbo.clone(),
+            users_dbo.clone(),
+            tracking_dbo.clone(),
+            tokens_dbo.clone(),
+            projects_dbo.clone(),
+            labels_dbo.clone(),
+            workflows_dbo.clone(),
+            auth_config.clone(),
+            task_events.clone(),
+            task_service.clone()
+        );
 
-  let tasks_dbo = Arc::new(TasksDboImpl::new(pool.clone()));
-  let users_dbo = Arc::new(UsersDboImpl::new(pool.clone()));
-  let tracking_dbo = Arc::new(TrackingDboImpl::new(pool));
+        AppState {
+            tasks_dbo,
+            users_dbo,
+            tracking_dbo,
+            tokens_dbo,
+            projects_dbo,
+            labels_dbo,
+            workflows_dbo,
+            auth_config,
+            task_events,
+            task_service,
+            schema
+        }
+    }
 
-  let app_state = AppState {
-      tasks_dbo,
-      users_dbo,
-      tracking_dbo
+    /// State backed by the PostgreSQL database behind `pool`.
+    pub fn postgres(pool: PgPool, auth_config: Arc<AuthConfig>) -> AppState {
+        AppState::new(
+            Arc::new(TasksDboImpl::new(pool.clone())),
+            Arc::new(UsersDboImpl::new(pool.clone())),
+            Arc::new(TrackingDboImpl::new(pool.clone())),
+            Arc::new(TokensDboImpl::new(pool.clone())),
+            Arc::new(ProjectsDboImpl::new(pool.clone())),
+            Arc::new(LabelsDboImpl::new(pool.clone())),
+            Arc::new(WorkflowsDboImpl::new(pool)),
+            auth_config
+        )
+    }
+
+    /// State backed by a fresh in-memory store, for `STORAGE=memory` and tests.
+    pub fn in_memory(auth_config: Arc<AuthConfig>) -> AppState {
+        let store = MemoryStore::new();
+        AppState::new(
+            Arc::new(MemoryTasksDbo::new(store.clone())),
+            Arc::new(MemoryUsersDbo::new(store.clone())),
+            Arc::new(MemoryTrackingDbo::new(store.clone())),
+            Arc::new(MemoryTokensDbo::new(store.clone())),
+            Arc::new(MemoryProjectsDbo::new(store.clone())),
+            Arc::new(MemoryLabelsDbo::new(store.clone())),
+            Arc::new(MemoryWorkflowsDbo::new(store)),
+            auth_config
+        )
+    }
+}
+
+/// Builds the router together with the state it serves, so `main` can share the DBOs with background jobs.
+/// `STORAGE` picks between PostgreSQL and the in-memory store.
+pub async fn prepare_app() -> (IntoMakeService<Router>, AppState) {
+  let auth_config = Arc::new(auth_config_from_env().expect("Invalid JWT configuration"));
+
+  let app_state = match storage_from_env().expect("Invalid storage configuration") {
+      Storage::Postgres => {
+          let pool = PgPoolOptions::new()
+              .max_connections(5) // Set max connection pool size
+              .connect(&std::env::var("DATABASE_URL").expect("Invalid database URL"))
+              .await.expect("Unable to create postgres connection pool");
+          AppState::postgres(pool, auth_config)
+      }
+      Storage::Memory => AppState::in_memory(auth_config),
   };
 
-  let app = Router::new()
+  (build_router(app_state.clone()).into_make_service(), app_state)
+}
+
+/// Routes of the app served with `app_state`; tests call it with a state of their own.
+pub fn build_router(app_state: AppState) -> Router {
+  Router::new()
       .route("/", get(get_all))
-      .route("/:id", get(get_task))
+      .route("/search", get(search))
+      .route("/assigned", get(get_assigned))
+      .route("/trash", get(get_trash))
+      .route("/{id}", get(get_task))
+      .route("/{id}/occurrences", get(get_occurrences))
+      .route("/{id}/history", get(get_history))
+      .route("/{id}/restore", post(restore_task))
       .route("/", post(add_task))
       .route("/", patch(update_task))
       .route("/update-status", patch(update_status))
+      .route("/{id}/assign", patch(assign_task))
+      .route("/{id}/subtasks", post(add_subtask))
+      .route("/{id}/parent", patch(set_parent))
+      .route("/{id}/move", patch(move_task))
+      .route("/{id}/dependencies", post(add_dependency))
+      .route("/{id}/dependencies/{blocker_id}", delete(remove_dependency))
+      .route("/{id}/labels", post(attach_label))
+      .route("/{id}/labels/{label_id}", delete(detach_label))
       .route("/", delete(delete_task))
-      .route_layer(middleware::from_fn(auth))
+      .route("/logout", post(logout))
+      .route("/labels", get(get_labels).post(add_label))
+      .route("/labels/{id}", patch(update_label).delete(delete_label))
+      .route("/workflow", get(get_workflow).put(set_workflow).delete(reset_workflow))
+      .route("/projects", get(get_projects).post(add_project))
+      .route("/projects/{id}/tasks", get(get_project_tasks))
+      .route("/projects/{id}/members", get(get_members).post(add_member))
+      .route("/projects/{id}/members/{username}", patch(update_member).delete(remove_member))
+      .route("/projects/{id}/workflow", get(get_project_workflow).put(set_project_workflow).delete(reset_project_workflow))
+      .route_layer(middleware::from_fn_with_state(app_state.clone(), auth))
       .route("/register", post(register_user))
       .route("/login", post(login))
+      .route("/token/refresh", post(refresh_token))
+      .route("/graphql", get(graphiql).post(graphql_handler))
+      .route("/graphql/ws", get(graphql_ws_handler))
       .layer(middleware::from_fn(logging_middleware))
-      .with_state(app_state);
-  
-  app.into_make_service()
+      .with_state(app_state)
 }
\ No newline at end of file
@@ -1,35 +1,50 @@
 use axum:: {
-  extract::Request, http::{HeaderMap, StatusCode}, middleware::Next, response::{IntoResponse, Response}
+  extract::{FromRequestParts, Request, State}, http::request::Parts, middleware::Next, response::{IntoResponse, Response}
 };
-use auth_lib::validate_token;
+use auth_lib::Claims;
+use crate::app::AppState;
+use crate::handlers::utils::{read_token, validate_session};
+use crate::models::DBError;
+
+/// The caller of an authenticated route, decoded once by the `auth` middleware.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+  pub username: String,
+  pub claims: Claims,
+}
 
 pub async fn auth(
-  headers: HeaderMap,
-  request: Request,
+  State(AppState { tokens_dbo, auth_config, .. }): State<AppState>,
+  mut request: Request,
   next: Next,
 ) -> Response {
-  let token = headers.get("auth_token");
-  
-  if let Some(val) = token {
-
-    let token_str = match val.to_str() {
-      Ok(value) => value,
-      Err(e) => {
-        return (StatusCode::UNAUTHORIZED, format!("auth_token is invalid: {e}")).into_response();
-      }
-    };
-    let is_verified = validate_token(token_str, "1234");
-    
-    match is_verified {
-      Ok(_) => (),
-      Err(e) => {
-        return (StatusCode::UNAUTHORIZED, format!("auth_token is invalid: {e}")).into_response();
-      }
+  let token_str = match read_token(request.headers()) {
+    Ok(value) => value,
+    Err(e) => {
+      return e.into_response();
+    }
+  };
+  let claims = match validate_session(token_str, tokens_dbo.as_ref(), &auth_config).await {
+    Ok(claims) => claims,
+    Err(e) => {
+      return e.into_response();
     }
+  };
+
+  request.extensions_mut().insert(AuthUser {
+    username: claims.sub.clone(),
+    claims,
+  });
+
+  next.run(request).await
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+  type Rejection = DBError;
 
-    let response = next.run(request).await;
-    response
-  } else {
-    (StatusCode::UNAUTHORIZED, "auth_token does not exist").into_response()
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    parts.extensions.get::<AuthUser>()
+      .cloned()
+      .ok_or_else(|| DBError::UnAuthorized("auth_token does not exist".to_string()))
   }
-}
\ No newline at end of file
+}
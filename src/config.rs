@@ -0,0 +1,86 @@
+use auth_lib::{parse_algorithm, Algorithm, AuthConfig, KeyMaterial};
+use std::env;
+
+/// Builds the JWT configuration from the environment.
+///
+/// - `JWT_ALGORITHM`: `HS256` (default), `RS256`, `EdDSA`, ...
+/// - `JWT_SECRET` for HMAC algorithms, or `JWT_PRIVATE_KEY_FILE` and `JWT_PUBLIC_KEY_FILE` (PEM) otherwise
+/// - `JWT_KID`: id of the signing key, `default` if unset
+/// - `JWT_ISSUER`, `JWT_AUDIENCE`, `JWT_EXPIRY_SECS`, `JWT_LEEWAY_SECS`
+/// - `JWT_RETIRED_KEYS`: comma separated `kid:ALGORITHM:secret-or-public-key-file` entries that
+///   are still accepted for verification while their tokens expire
+pub fn auth_config_from_env() -> Result<AuthConfig, String> {
+    let algorithm = parse_algorithm(&env_or("JWT_ALGORITHM", "HS256"))?;
+    let kid = env_or("JWT_KID", "default");
+
+    let mut config = if is_hmac(algorithm) {
+        let secret = env::var("JWT_SECRET").map_err(|_| "JWT_SECRET is not set".to_string())?;
+        let secret = secret.as_bytes();
+        AuthConfig::new(&kid, algorithm, KeyMaterial::Secret(secret), KeyMaterial::Secret(secret))?
+    } else {
+        let private_key = read_file_var("JWT_PRIVATE_KEY_FILE")?;
+        let public_key = read_file_var("JWT_PUBLIC_KEY_FILE")?;
+        AuthConfig::new(&kid, algorithm, KeyMaterial::Pem(&private_key), KeyMaterial::Pem(&public_key))?
+    };
+
+    config = config
+        .issuer(&env_or("JWT_ISSUER", "todo-app"))
+        .audience(&env_or("JWT_AUDIENCE", "todo-app"))
+        .expiry_secs(parse_var("JWT_EXPIRY_SECS", 3600)?)
+        .leeway_secs(parse_var("JWT_LEEWAY_SECS", 60)?);
+
+    for entry in env_or("JWT_RETIRED_KEYS", "").split(',').filter(|e| !e.trim().is_empty()) {
+        let mut parts = entry.trim().splitn(3, ':');
+        let (Some(kid), Some(algorithm), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Invalid JWT_RETIRED_KEYS entry {entry}, expected kid:ALGORITHM:key"));
+        };
+        let algorithm = parse_algorithm(algorithm)?;
+
+        config = if is_hmac(algorithm) {
+            config.with_verification_key(kid, algorithm, KeyMaterial::Secret(value.as_bytes()))?
+        } else {
+            let public_key = std::fs::read(value).map_err(|e| format!("Unable to read {value}: {e}"))?;
+            config.with_verification_key(kid, algorithm, KeyMaterial::Pem(&public_key))?
+        };
+    }
+
+    Ok(config)
+}
+
+fn is_hmac(algorithm: Algorithm) -> bool {
+    matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+fn parse_var(name: &str, default: u64) -> Result<u64, String> {
+    match env::var(name) {
+        Ok(value) => value.parse().map_err(|e| format!("Invalid {name}: {e}")),
+        Err(_) => Ok(default),
+    }
+}
+
+fn read_file_var(name: &str) -> Result<Vec<u8>, String> {
+    let path = env::var(name).map_err(|_| format!("{name} is not set"))?;
+    std::fs::read(&path).map_err(|e| format!("Unable to read {name} {path}: {e}"))
+}
+
+/// Where the app keeps its data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Storage {
+    /// The database at `DATABASE_URL`.
+    Postgres,
+    /// In process memory, lost on restart. Meant for local runs and tests.
+    Memory,
+}
+
+/// Reads the storage from `STORAGE`: `postgres` (default) or `memory`.
+pub fn storage_from_env() -> Result<Storage, String> {
+    match env_or("STORAGE", "postgres").to_lowercase().as_str() {
+        "postgres" => Ok(Storage::Postgres),
+        "memory" => Ok(Storage::Memory),
+        other => Err(format!("Invalid STORAGE {other}, expected postgres or memory")),
+    }
+}
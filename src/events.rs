@@ -0,0 +1,55 @@
+use std::sync::Arc;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use crate::models::TaskEvent;
+use crate::persistence::projects_dbo::ProjectsDbo;
+
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Fan-out of task changes to GraphQL subscribers.
+#[derive(Clone)]
+pub struct TaskEvents {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl TaskEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            sender,
+        }
+    }
+
+    pub fn publish(&self, event: TaskEvent) {
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// Stream of events for tasks visible to `user`: personal tasks they created or are assigned to,
+    /// and tasks of projects they are a member of at the time of the event. Events missed by a lagging subscriber are dropped.
+    pub fn subscribe(
+        &self,
+        user: String,
+        projects_dbo: Arc<dyn ProjectsDbo + Send + Sync>,
+    ) -> impl Stream<Item = TaskEvent> {
+        BroadcastStream::new(self.sender.subscribe()).filter_map(move |event| {
+            let user = user.clone();
+            let projects_dbo = projects_dbo.clone();
+            async move {
+                let event = event.ok()?;
+                let visible = match &event.task.project_uuid {
+                    Some(project_uuid) => matches!(projects_dbo.get_role(project_uuid, user).await, Ok(Some(_))),
+                    None => event.task.created_by == user || event.task.assignee.as_ref() == Some(&user),
+                };
+                visible.then_some(event)
+            }
+        })
+    }
+}
+
+impl Default for TaskEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
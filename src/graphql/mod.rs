@@ -0,0 +1,146 @@
+use async_graphql::{
+    http::{GraphiQLSource, ALL_WEBSOCKET_PROTOCOLS},
+    Context, Data, Schema
+};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::{
+    extract::{State as AxumState, WebSocketUpgrade},
+    http::HeaderMap,
+    response::{Html, IntoResponse},
+};
+use std::sync::Arc;
+use auth_lib::AuthConfig;
+use crate::app::AppState;
+use crate::events::TaskEvents;
+use crate::services::tasks::TaskService;
+use crate::auth::AuthUser;
+use crate::handlers::utils::{read_token, validate_session};
+use crate::persistence::{
+    tasks_dbo::TasksDbo,
+    users_dbo::UsersDbo,
+    tracking_dbo::TrackingDbo,
+    tokens_dbo::TokensDbo,
+    projects_dbo::ProjectsDbo,
+    labels_dbo::LabelsDbo,
+    workflows_dbo::WorkflowsDbo
+};
+
+mod query;
+mod mutation;
+mod subscription;
+
+pub use query::QueryRoot;
+pub use mutation::MutationRoot;
+pub use subscription::SubscriptionRoot;
+
+pub type TodoSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_schema(
+    tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+    users_dbo: Arc<dyn UsersDbo + Send + Sync>,
+    tracking_dbo: Arc<dyn TrackingDbo + Send + Sync>,
+    tokens_dbo: Arc<dyn TokensDbo + Send + Sync>,
+    projects_dbo: Arc<dyn ProjectsDbo + Send + Sync>,
+    labels_dbo: Arc<dyn LabelsDbo + Send + Sync>,
+    workflows_dbo: Arc<dyn WorkflowsDbo + Send + Sync>,
+    auth_config: Arc<AuthConfig>,
+    task_events: TaskEvents,
+    task_service: TaskService,
+) -> TodoSchema {
+    Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+        .data(tasks_dbo)
+        .data(users_dbo)
+        .data(tracking_dbo)
+        .data(tokens_dbo)
+        .data(projects_dbo)
+        .data(labels_dbo)
+        .data(workflows_dbo)
+        .data(auth_config)
+        .data(task_events)
+        .data(task_service)
+        .finish()
+}
+
+/// Returns the authenticated username, or an error for resolvers that need a valid `auth_token`.
+fn current_user(ctx: &Context<'_>) -> async_graphql::Result<String> {
+    ctx.data_opt::<AuthUser>()
+        .map(|user| user.username.clone())
+        .ok_or_else(|| "auth_token does not exist or is invalid".into())
+}
+
+/// Resolves the caller from the request headers, honouring the revocation list.
+async fn header_user(
+    headers: &HeaderMap,
+    tokens_dbo: &(dyn TokensDbo + Send + Sync),
+    auth_config: &AuthConfig,
+) -> Option<AuthUser> {
+    let token = read_token(headers).ok()?;
+    let claims = validate_session(token, tokens_dbo, auth_config).await.ok()?;
+    Some(AuthUser { username: claims.sub.clone(), claims })
+}
+
+pub async fn graphql_handler(
+    headers: HeaderMap,
+    AxumState(AppState { schema, tokens_dbo, auth_config, .. }): AxumState<AppState>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let mut req = req.into_inner();
+
+    // register and login are reachable without a token, so the request is not
+    // rejected here; resolvers call `current_user` when they need one.
+    if let Some(user) = header_user(&headers, tokens_dbo.as_ref(), &auth_config).await {
+        req = req.data(user);
+    }
+
+    schema.execute(req).await.into()
+}
+
+/// Websocket endpoint for subscriptions. Browsers cannot set headers on a websocket, so
+/// the token may also be sent as `{"auth_token": "..."}` in the `connection_init` payload.
+pub async fn graphql_ws_handler(
+    headers: HeaderMap,
+    AxumState(AppState { schema, tokens_dbo, auth_config, .. }): AxumState<AppState>,
+    protocol: GraphQLProtocol,
+    websocket: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let header_user = header_user(&headers, tokens_dbo.as_ref(), &auth_config).await;
+
+    websocket
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .on_connection_init(move |payload| {
+                    on_connection_init(payload, header_user, tokens_dbo, auth_config)
+                })
+                .serve()
+        })
+}
+
+async fn on_connection_init(
+    payload: serde_json::Value,
+    header_user: Option<AuthUser>,
+    tokens_dbo: Arc<dyn TokensDbo + Send + Sync>,
+    auth_config: Arc<AuthConfig>,
+) -> async_graphql::Result<Data> {
+    let user = match payload.get("auth_token").and_then(|token| token.as_str()) {
+        Some(token) => {
+            let claims = validate_session(token, tokens_dbo.as_ref(), &auth_config).await?;
+            AuthUser { username: claims.sub.clone(), claims }
+        }
+        None => header_user.ok_or("auth_token does not exist")?,
+    };
+
+    let mut data = Data::default();
+    data.insert(user);
+    Ok(data)
+}
+
+pub async fn graphiql() -> impl IntoResponse {
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/graphql")
+            .subscription_endpoint("/graphql/ws")
+            .finish()
+    )
+}
@@ -0,0 +1,186 @@
+use async_graphql::{Context, Object, Result};
+use std::sync::Arc;
+use validator::Validate;
+use auth_lib::{hash_password, verify_password, AuthConfig};
+use crate::handlers::utils::{invalid_login, issue_tokens};
+use crate::models::*;
+use crate::persistence::{
+    users_dbo::UsersDbo,
+    tokens_dbo::TokensDbo,
+    projects_dbo::ProjectsDbo,
+    labels_dbo::LabelsDbo,
+    workflows_dbo::WorkflowsDbo
+};
+use crate::services::tasks::TaskService;
+use super::current_user;
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn register(&self, ctx: &Context<'_>, user: User) -> Result<UserToken> {
+        user.validate().map_err(DBError::from)?;
+        let users_dbo = ctx.data::<Arc<dyn UsersDbo + Send + Sync>>()?;
+        let tokens_dbo = ctx.data::<Arc<dyn TokensDbo + Send + Sync>>()?;
+        let auth_config = ctx.data::<Arc<AuthConfig>>()?;
+        let user = User {
+            username: user.username,
+            password: hash_password(&user.password)?,
+            email: user.email
+        };
+        let user = users_dbo.create_user(user).await?;
+
+        Ok(issue_tokens(tokens_dbo.as_ref(), auth_config, &user.username).await?)
+    }
+
+    async fn login(&self, ctx: &Context<'_>, user: LoginReq) -> Result<UserToken> {
+        user.validate().map_err(DBError::from)?;
+        let users_dbo = ctx.data::<Arc<dyn UsersDbo + Send + Sync>>()?;
+        let tokens_dbo = ctx.data::<Arc<dyn TokensDbo + Send + Sync>>()?;
+        let auth_config = ctx.data::<Arc<AuthConfig>>()?;
+        let user_stored = users_dbo.get_user(user.username).await.map_err(|e| match e {
+            DBError::NotFound(_) => invalid_login(),
+            e => e,
+        })?;
+
+        if !verify_password(&user.password, &user_stored.password) {
+            return Err(invalid_login().into());
+        }
+
+        Ok(issue_tokens(tokens_dbo.as_ref(), auth_config, &user_stored.username).await?)
+    }
+
+    async fn create_project(&self, ctx: &Context<'_>, project: Project) -> Result<ProjectDetail> {
+        project.validate().map_err(DBError::from)?;
+        let user_name = current_user(ctx)?;
+        let projects_dbo = ctx.data::<Arc<dyn ProjectsDbo + Send + Sync>>()?;
+        Ok(projects_dbo.create_project(project, user_name).await?)
+    }
+
+    async fn add_project_member(&self, ctx: &Context<'_>, project_uuid: String, member: ProjectMemberReq) -> Result<ProjectMember> {
+        member.validate().map_err(DBError::from)?;
+        let user_name = current_user(ctx)?;
+        let projects_dbo = ctx.data::<Arc<dyn ProjectsDbo + Send + Sync>>()?;
+        Ok(projects_dbo.add_member(&project_uuid, member.username, member.role, user_name).await?)
+    }
+
+    async fn create_label(&self, ctx: &Context<'_>, label: Label) -> Result<LabelDetail> {
+        let user_name = current_user(ctx)?;
+        let labels_dbo = ctx.data::<Arc<dyn LabelsDbo + Send + Sync>>()?;
+        Ok(labels_dbo.create_label(label, user_name).await?)
+    }
+
+    /// Replaces the workflow of a project, or of the personal tasks of the authenticated user without `project_uuid`.
+    async fn set_workflow(&self, ctx: &Context<'_>, project_uuid: Option<String>, workflow: Workflow) -> Result<WorkflowDetail> {
+        let user_name = current_user(ctx)?;
+        let workflows_dbo = ctx.data::<Arc<dyn WorkflowsDbo + Send + Sync>>()?;
+        Ok(workflows_dbo.set_workflow(project_uuid, workflow, user_name).await?)
+    }
+
+    async fn reset_workflow(&self, ctx: &Context<'_>, project_uuid: Option<String>) -> Result<WorkflowDetail> {
+        let user_name = current_user(ctx)?;
+        let workflows_dbo = ctx.data::<Arc<dyn WorkflowsDbo + Send + Sync>>()?;
+        Ok(workflows_dbo.reset_workflow(project_uuid, user_name).await?)
+    }
+
+    async fn add_task(&self, ctx: &Context<'_>, task: Task) -> Result<TaskDetail> {
+        task.validate().map_err(DBError::from)?;
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.create_task(task, user_name).await?)
+    }
+
+    /// Changes the fields set in `task`, provided the task is still at `task.version`. `null` clears
+    /// the due date, recurrence and assignee.
+    async fn update_task(&self, ctx: &Context<'_>, task: TaskUpdateReq) -> Result<TaskDetail> {
+        task.validate().map_err(DBError::from)?;
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        let version = task.version.ok_or_else(|| DBError::InvalidInput("version is required".to_string()))?;
+        Ok(task_service.update_task(task, Some(version), user_name).await?)
+    }
+
+    /// Changes the status, provided the task is still at `version`.
+    async fn update_status(&self, ctx: &Context<'_>, task_uuid: String, status: TaskStatus, version: i32) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.update_status(task_uuid, status, Some(version), user_name).await?)
+    }
+
+    /// Assigns the task to `assignee`, or unassigns it when omitted.
+    async fn assign_task(&self, ctx: &Context<'_>, task_uuid: String, assignee: Option<String>) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.assign_task(&task_uuid, assignee, user_name).await?)
+    }
+
+    /// Moves the task under another parent, or to the top level when `parent_task_uuid` is omitted.
+    async fn set_task_parent(
+        &self,
+        ctx: &Context<'_>,
+        task_uuid: String,
+        parent_task_uuid: Option<String>,
+        position: Option<i32>
+    ) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.set_parent(&task_uuid, parent_task_uuid, position, user_name).await?)
+    }
+
+    /// Moves the task to the `status` column, after `after_task_uuid` and/or before `before_task_uuid`,
+    /// or last without either.
+    async fn move_task(
+        &self,
+        ctx: &Context<'_>,
+        task_uuid: String,
+        status: TaskStatus,
+        after_task_uuid: Option<String>,
+        before_task_uuid: Option<String>
+    ) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.move_task(&task_uuid, status, after_task_uuid, before_task_uuid, user_name).await?)
+    }
+
+    /// Marks the task as blocked by `blocker_uuid`.
+    async fn add_task_dependency(&self, ctx: &Context<'_>, task_uuid: String, blocker_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.add_dependency(&task_uuid, &blocker_uuid, user_name).await?)
+    }
+
+    async fn remove_task_dependency(&self, ctx: &Context<'_>, task_uuid: String, blocker_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.remove_dependency(&task_uuid, &blocker_uuid, user_name).await?)
+    }
+
+    async fn attach_label(&self, ctx: &Context<'_>, task_uuid: String, label_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.attach_label(&task_uuid, &label_uuid, user_name).await?)
+    }
+
+    async fn detach_label(&self, ctx: &Context<'_>, task_uuid: String, label_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.detach_label(&task_uuid, &label_uuid, user_name).await?)
+    }
+
+    /// Deletes the task together with its tracking history. Subtasks are deleted too with `cascade`,
+    /// otherwise a task that has subtasks is kept.
+    /// Moves the task to the trash, where it stays until restored or purged.
+    async fn delete_task(&self, ctx: &Context<'_>, task_uuid: String, #[graphql(default)] cascade: bool) -> Result<bool> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        task_service.delete_task(task_uuid, cascade, user_name).await?;
+        Ok(true)
+    }
+
+    /// Takes the task out of the trash, together with the subtasks deleted along with it.
+    async fn restore_task(&self, ctx: &Context<'_>, task_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let task_service = ctx.data::<TaskService>()?;
+        Ok(task_service.restore_task(&task_uuid, user_name).await?)
+    }
+}
@@ -0,0 +1,127 @@
+use async_graphql::{ComplexObject, Context, Object, Result};
+use std::sync::Arc;
+use crate::models::{LabelDetail, ProjectDetail, TaskDetail, TaskListQuery, TaskProgress, TrackingDetail, TrackingEventType, UserDetail, WorkflowDetail};
+use crate::persistence::{
+    tasks_dbo::TasksDbo,
+    users_dbo::UsersDbo,
+    tracking_dbo::TrackingDbo,
+    projects_dbo::ProjectsDbo,
+    labels_dbo::LabelsDbo,
+    workflows_dbo::WorkflowsDbo
+};
+use super::current_user;
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// All tasks visible to the authenticated user, including tasks of shared projects.
+    async fn tasks(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.get_all_tasks(user_name).await?)
+    }
+
+    async fn task(&self, ctx: &Context<'_>, task_uuid: String) -> Result<TaskDetail> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.get_task(&task_uuid, user_name).await?)
+    }
+
+    /// Tasks assigned to the authenticated user.
+    async fn assigned_tasks(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        let query = TaskListQuery {
+            assignee: Some(user_name.clone()),
+            ..Default::default()
+        };
+        Ok(tasks_dbo.list_tasks(query, user_name).await?.tasks)
+    }
+
+    /// Tasks in the trash, most recently deleted first.
+    async fn trash(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.list_trash(user_name).await?)
+    }
+
+    /// Projects the authenticated user is a member of.
+    async fn projects(&self, ctx: &Context<'_>) -> Result<Vec<ProjectDetail>> {
+        let user_name = current_user(ctx)?;
+        let projects_dbo = ctx.data::<Arc<dyn ProjectsDbo + Send + Sync>>()?;
+        Ok(projects_dbo.list_projects(user_name).await?)
+    }
+
+    /// Labels of a project, or the personal labels of the authenticated user without `project_uuid`.
+    async fn labels(&self, ctx: &Context<'_>, project_uuid: Option<String>) -> Result<Vec<LabelDetail>> {
+        let user_name = current_user(ctx)?;
+        let labels_dbo = ctx.data::<Arc<dyn LabelsDbo + Send + Sync>>()?;
+        Ok(labels_dbo.list_labels(project_uuid, user_name).await?)
+    }
+
+    /// Workflow of a project, or of the personal tasks of the authenticated user without `project_uuid`.
+    async fn workflow(&self, ctx: &Context<'_>, project_uuid: Option<String>) -> Result<WorkflowDetail> {
+        let user_name = current_user(ctx)?;
+        let workflows_dbo = ctx.data::<Arc<dyn WorkflowsDbo + Send + Sync>>()?;
+        Ok(workflows_dbo.get_workflow(project_uuid, user_name).await?)
+    }
+
+    /// The authenticated user.
+    async fn me(&self, ctx: &Context<'_>) -> Result<UserDetail> {
+        let user_name = current_user(ctx)?;
+        let users_dbo = ctx.data::<Arc<dyn UsersDbo + Send + Sync>>()?;
+        Ok(users_dbo.get_user(user_name).await?)
+    }
+}
+
+#[ComplexObject]
+impl TaskDetail {
+    /// Tracking history of the task, so a task and its history can be fetched in one round trip.
+    async fn tracking(&self, ctx: &Context<'_>, event_type: Option<TrackingEventType>) -> Result<Vec<TrackingDetail>> {
+        let tracking_dbo = ctx.data::<Arc<dyn TrackingDbo + Send + Sync>>()?;
+        Ok(tracking_dbo.get_tracking(self.task_uuid.clone(), event_type).await?)
+    }
+
+    /// Direct subtasks, in order.
+    async fn subtasks(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        let descendants = tasks_dbo.get_subtree(&self.task_uuid, user_name).await?;
+        Ok(descendants.into_iter()
+            .filter(|task| task.parent_task_uuid.as_deref() == Some(self.task_uuid.as_str()))
+            .collect())
+    }
+
+    /// Completion of all subtasks below this task, `null` without subtasks.
+    async fn progress(&self, ctx: &Context<'_>) -> Result<Option<TaskProgress>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        let descendants = tasks_dbo.get_subtree(&self.task_uuid, user_name).await?;
+        Ok(TaskProgress::from_subtasks(&descendants))
+    }
+
+    /// Tasks this task is blocked by.
+    async fn blockers(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.get_blockers(&self.task_uuid, user_name).await?)
+    }
+
+    /// Tasks blocked by this task.
+    async fn dependents(&self, ctx: &Context<'_>) -> Result<Vec<TaskDetail>> {
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.get_dependents(&self.task_uuid, user_name).await?)
+    }
+
+    /// Due dates of the next occurrences of a recurring task, empty for other tasks.
+    async fn next_occurrences(&self, ctx: &Context<'_>, #[graphql(default = 5)] count: usize) -> Result<Vec<String>> {
+        if self.recurrence.is_none() {
+            return Ok(Vec::new());
+        }
+        let user_name = current_user(ctx)?;
+        let tasks_dbo = ctx.data::<Arc<dyn TasksDbo + Send + Sync>>()?;
+        Ok(tasks_dbo.preview_occurrences(&self.task_uuid, count.min(100), user_name).await?)
+    }
+}
@@ -0,0 +1,20 @@
+use async_graphql::{Context, Result, Subscription};
+use tokio_stream::Stream;
+use crate::events::TaskEvents;
+use crate::models::TaskEvent;
+use crate::persistence::projects_dbo::ProjectsDbo;
+use std::sync::Arc;
+use super::current_user;
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// Emits whenever a task visible to the authenticated user is created, updated or deleted.
+    async fn task_changes(&self, ctx: &Context<'_>) -> Result<impl Stream<Item = TaskEvent>> {
+        let user_name = current_user(ctx)?;
+        let task_events = ctx.data::<TaskEvents>()?;
+        let projects_dbo = ctx.data::<Arc<dyn ProjectsDbo + Send + Sync>>()?.clone();
+        Ok(task_events.subscribe(user_name, projects_dbo))
+    }
+}
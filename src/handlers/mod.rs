@@ -1,169 +1,524 @@
 use axum::{
     extract::State as AxumState,
     extract::Path,
+    extract::Query,
     response::IntoResponse,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     Json as JsonAxum,
-    http::HeaderMap,
 };
 use crate::models::*;
 use crate::app::AppState;
-use auth_lib::{generate_token, hash_password, verify_password};
+use crate::auth::AuthUser;
+use crate::logging::current_request_id;
+use auth_lib::{hash_password, hash_refresh_token, verify_password};
 
-mod utils;
+pub mod utils;
 
-use utils::validate_user;
+use utils::{etag, invalid_login, issue_tokens, ExpectedVersion, ValidatedJson};
 
+/// Errors are sent as `{"error": {"code", "message", "request_id"}}`, plus `fields` when fields of
+/// the body failed validation. Internal errors only show up in the logs, under the request id the
+/// client gets back.
 impl IntoResponse for DBError {
     fn into_response(self) -> axum::response::Response {
-        match self {
-            DBError::InvalidInput(msg) => {
-                (StatusCode::BAD_REQUEST, msg).into_response()
-            }
-            DBError::UnAuthorized(msg) => {
-                (StatusCode::UNAUTHORIZED, msg).into_response()
-            }
+        let status = match self {
+            DBError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            DBError::UnAuthorized(_) => StatusCode::UNAUTHORIZED,
+            DBError::Forbidden(_) => StatusCode::FORBIDDEN,
+            DBError::NotFound(_) => StatusCode::NOT_FOUND,
+            DBError::Conflict(_) | DBError::VersionConflict(_) => StatusCode::CONFLICT,
+            DBError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            DBError::Validation(_) | DBError::InvalidFields(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            DBError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let request_id = current_request_id();
+        let message = match &self {
             DBError::Other(msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, msg).into_response()
+                println!("[{}] Internal error: {msg}", request_id.as_deref().unwrap_or("-"));
+                "Internal server error".to_string()
+            }
+            e => e.to_string(),
+        };
+
+        let code = self.code().to_string();
+        let fields = match self {
+            DBError::InvalidFields(fields) => fields,
+            _ => Vec::new(),
+        };
+        let body = ErrorResponse {
+            error: ErrorBody {
+                code,
+                message,
+                request_id,
+                fields,
             }
-        }
+        };
+        (status, JsonAxum(body)).into_response()
     }
 }
 
 pub async fn register_user(
-    AxumState(AppState { users_dbo, .. }): AxumState<AppState>,
-    JsonAxum(user): JsonAxum<User>
+    AxumState(AppState { users_dbo, tokens_dbo, auth_config, .. }): AxumState<AppState>,
+    ValidatedJson(user): ValidatedJson<User>
 ) -> Result<impl IntoResponse, DBError> {
     let user = User {
         username: user.username,
-        password: hash_password(&user.password).map_err(|e| DBError::Other(e))?,
+        password: hash_password(&user.password).map_err(DBError::Other)?,
         email: user.email
     };
     let user = users_dbo.create_user(user).await?;
 
-    let token: String  = generate_token(&user.username, "1234").map_err(|e| DBError::Other(e))?;
+    let tokens = issue_tokens(tokens_dbo.as_ref(), &auth_config, &user.username).await?;
 
-    if token == "" {
+    if tokens.token.is_empty() {
         return Err(DBError::Other("Failed to generate token".to_string()));
     }
 
-    Ok(JsonAxum(UserToken { token }))
+    Ok(JsonAxum(tokens))
 }
 
 pub async fn login(
-    AxumState(AppState { users_dbo, .. }): AxumState<AppState>,
-    JsonAxum(user): JsonAxum<LoginReq>
+    AxumState(AppState { users_dbo, tokens_dbo, auth_config, .. }): AxumState<AppState>,
+    ValidatedJson(user): ValidatedJson<LoginReq>
 ) -> Result<impl IntoResponse, DBError>{
     println!("step1");
-    let user_stored = users_dbo.get_user(user.username).await?;
+    // Unknown users and wrong passwords get the same answer, so usernames cannot be probed.
+    let user_stored = users_dbo.get_user(user.username).await.map_err(|e| match e {
+        DBError::NotFound(_) => invalid_login(),
+        e => e,
+    })?;
     println!("step2");
     let is_verified = verify_password(&user.password, &user_stored.password);
     println!("step3");
     if is_verified {
         println!("step4");
-        let token: String = generate_token(&user_stored.username, "1234").map_err(|e| DBError::Other(e))?;
-        return Ok(JsonAxum(UserToken { token }));
+        let tokens = issue_tokens(tokens_dbo.as_ref(), &auth_config, &user_stored.username).await?;
+        Ok(JsonAxum(tokens))
     } else {
         println!("step5");
-        return Err(DBError::InvalidInput("Invalid password".to_string()));
+        Err(invalid_login())
     }
 }
 
-// TODO: update to User-based fetching.
+/// Exchanges a refresh token for a new access/refresh token pair. The old refresh token is revoked.
+pub async fn refresh_token(
+    AxumState(AppState { tokens_dbo, auth_config, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<RefreshTokenReq>
+) -> Result<impl IntoResponse, DBError> {
+    let user_name = tokens_dbo.consume_refresh_token(hash_refresh_token(&req.refresh_token)).await?;
+    let tokens = issue_tokens(tokens_dbo.as_ref(), &auth_config, &user_name).await?;
+
+    Ok(JsonAxum(tokens))
+}
+
+/// Revokes the current access token and, when given, the refresh token.
+pub async fn logout(
+    AuthUser { claims, .. }: AuthUser,
+    AxumState(AppState { tokens_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<LogoutReq>
+) -> Result<impl IntoResponse, DBError> {
+    tokens_dbo.revoke_access_token(claims.jti, claims.exp as i64).await?;
+    if let Some(refresh_token) = req.refresh_token {
+        tokens_dbo.revoke_refresh_token(hash_refresh_token(&refresh_token), claims.sub).await?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn get_all(
-    headers: HeaderMap,
-    AxumState(AppState { tasks_dbo , ..}): AxumState<AppState>,
+    AuthUser { username: user_name, .. }: AuthUser,
+    Query(query): Query<TaskListQuery>,
+    AxumState(AppState { tasks_dbo, .. }): AxumState<AppState>,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
-    let user_name = validate_user(&headers)?;
-    tasks_dbo.get_all_tasks(user_name).await.map(JsonAxum)
+    tasks_dbo.list_tasks(query, user_name).await.map(JsonAxum)
+}
+
+pub async fn search(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Query(query): Query<SearchQuery>,
+    AxumState(AppState { tasks_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let hits = tasks_dbo.search_tasks(&query.q, query.limit, user_name).await?;
+    Ok(JsonAxum(hits))
 }
 
 pub async fn get_task(
-    headers: HeaderMap,
+    AuthUser { username: user_name, .. }: AuthUser,
     Path(id): Path<String>,
     AxumState(AppState { tasks_dbo, tracking_dbo, .. }): AxumState<AppState>
 ) -> Result<impl IntoResponse, DBError> {
     println!("ID: {}", id);
-    let user_name = validate_user(&headers)?;
-    let task = tasks_dbo.get_task(&id, user_name).await?;
-    let tracking = tracking_dbo.get_tracking(id).await?;
-
-    if tracking.len() == 0 {
-        return Ok(JsonAxum(TaskDetailResponse {
-            task,
-            tracking: None
-        }));
-    } else {
-        let resp = TaskDetailResponse {
-            task,
-            tracking: Some(tracking)
-        };
+    let task = tasks_dbo.get_task(&id, user_name.clone()).await?;
+    let tracking = tracking_dbo.get_tracking(id.clone(), None).await?;
+    let descendants = tasks_dbo.get_subtree(&id, user_name.clone()).await?;
+    let blockers = tasks_dbo.get_blockers(&id, user_name.clone()).await?;
+    let dependents = tasks_dbo.get_dependents(&id, user_name).await?;
 
-    Ok(JsonAxum(resp))
-    }
+    let etag = etag(&task);
+    let resp = TaskDetailResponse {
+        task,
+        tracking: (!tracking.is_empty()).then_some(tracking),
+        progress: TaskProgress::from_subtasks(&descendants),
+        subtasks: SubtaskNode::tree(&id, &descendants),
+        blockers,
+        dependents,
+    };
+
+    Ok((etag, JsonAxum(resp)))
 }
 
 pub async fn add_task(
-    headers: HeaderMap,
-    AxumState(AppState { tasks_dbo, tracking_dbo , ..}): AxumState<AppState>,
-    JsonAxum(task): JsonAxum<Task>
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(task): ValidatedJson<Task>
 ) -> Result<impl IntoResponse, DBError> {
-    let user_name = validate_user(&headers)?;
-    let task = tasks_dbo.create_task(task, user_name).await?;
+    let task = task_service.create_task(task, user_name).await?;
+    Ok(JsonAxum(task))
+}
 
-    #[allow(unused)]
-    tracking_dbo.create_tracking(Tracking {
-            task_uuid: task.task_uuid.clone(),
-            status: format!("Task Created with status {}", task.status.to_string())
-    }).await;
+/// Creates a task as the last subtask of `id`.
+pub async fn add_subtask(
+    auth_user: AuthUser,
+    Path(id): Path<String>,
+    state: AxumState<AppState>,
+    ValidatedJson(task): ValidatedJson<Task>
+) -> Result<impl IntoResponse, DBError> {
+    let task = Task {
+        parent_task_uuid: Some(id),
+        ..task
+    };
+    add_task(auth_user, state, ValidatedJson(task)).await
+}
 
+/// Moves the task under another parent, or to the top level, and reorders its new siblings.
+pub async fn set_parent(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<TaskParentReq>
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.set_parent(&id, req.parent_task_uuid, req.position, user_name).await?;
     Ok(JsonAxum(task))
 }
 
+/// Applies a JSON Merge Patch to the task if it is still at the version given in `If-Match` or the body.
+/// Patches that change nothing leave no trace in the history.
 pub async fn update_task(
+    AuthUser { username: user_name, .. }: AuthUser,
     headers: HeaderMap,
-    AxumState(AppState { tasks_dbo, tracking_dbo, .. }): AxumState<AppState>,
-    JsonAxum(task): JsonAxum<TaskUpdateReq>
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(task): ValidatedJson<TaskUpdateReq>
 ) -> Result<impl IntoResponse, DBError>{
-    let user_name = validate_user(&headers)?;
-    let task = tasks_dbo.update_task(task, user_name).await?;
+    let expected = ExpectedVersion::from_request(&headers, task.version)?;
+    let task = task_service.update_task(task, expected.version, user_name).await
+        .map_err(|e| expected.map_err(e))?;
+    Ok((etag(&task), JsonAxum(task)))
+}
 
-    #[allow(unused)]
-    tracking_dbo.create_tracking(Tracking {
-        task_uuid: task.task_uuid.clone(),
-        status: format!("Task Updated with title: {}, description: {}", task.title.to_string(), task.description.to_string())
-    }).await;
+/// Changes the status if the task is still at the version given in `If-Match` or the body.
+pub async fn update_status(
+    AuthUser { username: user_name, .. }: AuthUser,
+    headers: HeaderMap,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(task): ValidatedJson<TaskStatusReq>
+) -> Result<impl IntoResponse, DBError> {
+    let expected = ExpectedVersion::from_request(&headers, task.version)?;
+    let task = task_service.update_status(task.task_uuid, task.status, expected.version, user_name).await
+        .map_err(|e| expected.map_err(e))?;
+    Ok((etag(&task), JsonAxum(task)))
+}
+
+/// Marks the task as blocked by another task.
+pub async fn add_dependency(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<TaskDependencyReq>
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.add_dependency(&id, &req.blocker_uuid, user_name).await?;
+    Ok((StatusCode::CREATED, JsonAxum(task)))
+}
 
+pub async fn remove_dependency(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path((id, blocker_id)): Path<(String, String)>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.remove_dependency(&id, &blocker_id, user_name).await?;
     Ok(JsonAxum(task))
 }
 
-pub async fn update_status(
-    headers: HeaderMap,
-    AxumState(AppState { tasks_dbo, tracking_dbo, .. }): AxumState<AppState>,
-    JsonAxum(task): JsonAxum<TaskStatusReq>
+/// Attaches a label to the task and records it in the task history.
+pub async fn attach_label(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<TaskLabelReq>
 ) -> Result<impl IntoResponse, DBError> {
-    let user_name = validate_user(&headers)?;
-    let task = tasks_dbo.update_task_status(task.status, task.task_uuid, user_name).await?;
+    let task = task_service.attach_label(&id, &req.label_uuid, user_name).await?;
+    Ok(JsonAxum(task))
+}
 
-    #[allow(unused)]
-    tracking_dbo.create_tracking(Tracking {
-            task_uuid: task.task_uuid.clone(),
-            status: format!("Task Updated with status {}", task.status.to_string())
-    }).await;
+pub async fn detach_label(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path((id, label_id)): Path<(String, String)>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.detach_label(&id, &label_id, user_name).await?;
+    Ok(JsonAxum(task))
+}
 
+/// Moves the task to another status column and/or position on the board.
+pub async fn move_task(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<TaskMoveReq>
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.move_task(&id, req.status, req.after_task_uuid, req.before_task_uuid, user_name).await?;
     Ok(JsonAxum(task))
 }
 
-pub async fn delete_task(
-    headers: HeaderMap,
-    AxumState(AppState { tasks_dbo, tracking_dbo , ..}): AxumState<AppState>,
-    JsonAxum(task): JsonAxum<TaskId>
+const DEFAULT_OCCURRENCES: usize = 5;
+const MAX_OCCURRENCES: usize = 100;
+
+/// Previews the due dates of the next occurrences of a recurring task.
+pub async fn get_occurrences(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<OccurrencesQuery>,
+    AxumState(AppState { tasks_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let count = query.count.unwrap_or(DEFAULT_OCCURRENCES).min(MAX_OCCURRENCES);
+    let occurrences = tasks_dbo.preview_occurrences(&id, count, user_name).await?;
+    Ok(JsonAxum(occurrences))
+}
+
+/// History of a task, oldest first, optionally limited to one `event_type`.
+pub async fn get_history(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    AxumState(AppState { tasks_dbo, tracking_dbo, .. }): AxumState<AppState>,
 ) -> Result<impl IntoResponse, DBError> {
-    let user_name = validate_user(&headers)?;
-    #[allow(unused)]
-    tasks_dbo.delete_task(task.task_uuid.clone(), user_name).await;
-    #[allow(unused)]
-    tracking_dbo.delete_tracking(task.task_uuid).await;
+    tasks_dbo.get_task(&id, user_name).await?;
+    let history = tracking_dbo.get_tracking(id, query.event_type).await?;
+    Ok(JsonAxum(history))
+}
 
+/// Assigns the task to another user, or unassigns it, and records who made the change.
+pub async fn assign_task(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<TaskAssignReq>
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.assign_task(&id, req.assignee, user_name).await?;
+    Ok(JsonAxum(task))
+}
+
+/// Tasks assigned to the caller, with the same filters and pagination as `GET /`.
+pub async fn get_assigned(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Query(query): Query<TaskListQuery>,
+    AxumState(AppState { tasks_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let query = TaskListQuery {
+        assignee: Some(user_name.clone()),
+        ..query
+    };
+    let page = tasks_dbo.list_tasks(query, user_name).await?;
+    Ok(JsonAxum(page))
+}
+
+/// Moves the task to the trash, where it stays until restored or purged.
+pub async fn delete_task(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+    ValidatedJson(task): ValidatedJson<TaskId>
+) -> Result<impl IntoResponse, DBError> {
+    task_service.delete_task(task.task_uuid, task.cascade, user_name).await?;
     Ok(())
 }
+
+/// Tasks in the trash, most recently deleted first.
+pub async fn get_trash(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { tasks_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let tasks = tasks_dbo.list_trash(user_name).await?;
+    Ok(JsonAxum(tasks))
+}
+
+/// Takes the task out of the trash, together with the subtasks deleted along with it.
+pub async fn restore_task(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { task_service, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let task = task_service.restore_task(&id, user_name).await?;
+    Ok(JsonAxum(task))
+}
+
+pub async fn add_label(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { labels_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(label): ValidatedJson<Label>
+) -> Result<impl IntoResponse, DBError> {
+    let label = labels_dbo.create_label(label, user_name).await?;
+    Ok((StatusCode::CREATED, JsonAxum(label)))
+}
+
+pub async fn get_labels(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Query(query): Query<LabelListQuery>,
+    AxumState(AppState { labels_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let labels = labels_dbo.list_labels(query.project_uuid, user_name).await?;
+    Ok(JsonAxum(labels))
+}
+
+pub async fn update_label(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { labels_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<LabelUpdateReq>
+) -> Result<impl IntoResponse, DBError> {
+    let label = labels_dbo.update_label(&id, req, user_name).await?;
+    Ok(JsonAxum(label))
+}
+
+pub async fn delete_label(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { labels_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    labels_dbo.delete_label(&id, user_name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn add_project(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(project): ValidatedJson<Project>
+) -> Result<impl IntoResponse, DBError> {
+    let project = projects_dbo.create_project(project, user_name).await?;
+    Ok((StatusCode::CREATED, JsonAxum(project)))
+}
+
+pub async fn get_projects(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let projects = projects_dbo.list_projects(user_name).await?;
+    Ok(JsonAxum(projects))
+}
+
+pub async fn get_project_tasks(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    Query(query): Query<TaskListQuery>,
+    AxumState(AppState { tasks_dbo, projects_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    if projects_dbo.get_role(&id, user_name.clone()).await?.is_none() {
+        return Err(DBError::NotFound(format!("Project {id} not found")));
+    }
+
+    let query = TaskListQuery {
+        project_uuid: Some(id),
+        ..query
+    };
+    let page = tasks_dbo.list_tasks(query, user_name).await?;
+    Ok(JsonAxum(page))
+}
+
+pub async fn get_members(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let members = projects_dbo.list_members(&id, user_name).await?;
+    Ok(JsonAxum(members))
+}
+
+pub async fn add_member(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(member): ValidatedJson<ProjectMemberReq>
+) -> Result<impl IntoResponse, DBError> {
+    let member = projects_dbo.add_member(&id, member.username, member.role, user_name).await?;
+    Ok((StatusCode::CREATED, JsonAxum(member)))
+}
+
+pub async fn update_member(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path((id, username)): Path<(String, String)>,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(req): ValidatedJson<ProjectRoleReq>
+) -> Result<impl IntoResponse, DBError> {
+    let member = projects_dbo.update_role(&id, username, req.role, user_name).await?;
+    Ok(JsonAxum(member))
+}
+
+pub async fn remove_member(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path((id, username)): Path<(String, String)>,
+    AxumState(AppState { projects_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    projects_dbo.remove_member(&id, username, user_name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.get_workflow(None, user_name).await?;
+    Ok(JsonAxum(workflow))
+}
+
+pub async fn set_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(workflow): ValidatedJson<Workflow>
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.set_workflow(None, workflow, user_name).await?;
+    Ok(JsonAxum(workflow))
+}
+
+pub async fn reset_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.reset_workflow(None, user_name).await?;
+    Ok(JsonAxum(workflow))
+}
+
+pub async fn get_project_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.get_workflow(Some(id), user_name).await?;
+    Ok(JsonAxum(workflow))
+}
+
+pub async fn set_project_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+    ValidatedJson(workflow): ValidatedJson<Workflow>
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.set_workflow(Some(id), workflow, user_name).await?;
+    Ok(JsonAxum(workflow))
+}
+
+pub async fn reset_project_workflow(
+    AuthUser { username: user_name, .. }: AuthUser,
+    Path(id): Path<String>,
+    AxumState(AppState { workflows_dbo, .. }): AxumState<AppState>,
+) -> Result<impl IntoResponse, DBError> {
+    let workflow = workflows_dbo.reset_workflow(Some(id), user_name).await?;
+    Ok(JsonAxum(workflow))
+}
@@ -1,21 +1,132 @@
-use auth_lib::validate_token;
-use crate::models::DBError;
-use axum::http::HeaderMap;
+use auth_lib::{
+  generate_refresh_token, generate_token, hash_refresh_token, validate_token, AuthConfig, Claims, REFRESH_TOKEN_TTL_SECS
+};
+use crate::models::{DBError, TaskDetail, UserToken};
+use crate::persistence::tokens_dbo::TokensDbo;
+use axum::{
+  extract::{rejection::JsonRejection, FromRequest, Request},
+  http::{header::{AUTHORIZATION, ETAG, IF_MATCH}, HeaderMap, HeaderName},
+  Json,
+};
+use serde::de::DeserializeOwned;
+use validator::Validate;
 
-pub fn get_user_from_token(token: &str) -> Result<String, String> {
-    let claims = validate_token(token, "1234")?;
-    Ok(claims.sub)
+/// Reads the access token from `Authorization: Bearer <token>`, falling back to the legacy `auth_token` header.
+pub fn read_token(headers: &HeaderMap) -> Result<&str, DBError> {
+  if let Some(value) = headers.get(AUTHORIZATION) {
+    let value = value.to_str().map_err(|e| DBError::UnAuthorized(format!("Invalid token: {e}")))?;
+    return value.strip_prefix("Bearer ")
+      .map(str::trim)
+      .ok_or_else(|| DBError::UnAuthorized("Authorization header must use the Bearer scheme".to_string()));
+  }
+
+  headers.get("auth_token")
+    .ok_or_else(|| DBError::UnAuthorized("auth_token does not exist".to_string()))?
+    .to_str()
+    .map_err(|e| DBError::UnAuthorized(format!("Invalid token: {e}")))
+}
+
+/// Validates the token and checks it against the revocation list.
+pub async fn validate_session(
+  token: &str,
+  tokens_dbo: &(dyn TokensDbo + Send + Sync),
+  auth_config: &AuthConfig
+) -> Result<Claims, DBError> {
+  let claims = validate_token(token, auth_config)
+    .map_err(|e| DBError::UnAuthorized(format!("auth_token is invalid: {e}")))?;
+
+  if tokens_dbo.is_access_token_revoked(claims.jti.clone()).await? {
+    return Err(DBError::UnAuthorized("auth_token has been revoked".to_string()));
+  }
+
+  Ok(claims)
+}
+
+/// `ETag` header carrying the version of the task.
+pub fn etag(task: &TaskDetail) -> [(HeaderName, String); 1] {
+  [(ETAG, format!("\"{}\"", task.version))]
+}
+
+/// JSON body checked against the `#[validate]` rules of `T`. Broken rules are answered with a 422
+/// listing each field; bodies that do not parse get the error envelope as well.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+  T: DeserializeOwned + Validate,
+  S: Send + Sync,
+{
+  type Rejection = DBError;
+
+  async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+    let Json(value) = Json::<T>::from_request(req, state).await.map_err(|rejection| match rejection {
+      JsonRejection::JsonDataError(e) => DBError::Validation(e.body_text()),
+      rejection => DBError::InvalidInput(rejection.body_text()),
+    })?;
+    value.validate()?;
+    Ok(ValidatedJson(value))
+  }
+}
+
+/// Version of the task a change is based on, from `If-Match` or else the `version` of the request.
+pub struct ExpectedVersion {
+  /// `None` for `If-Match: *`, which accepts any version.
+  pub version: Option<i32>,
+  if_match: bool,
+}
+
+impl ExpectedVersion {
+  pub fn from_request(headers: &HeaderMap, version: Option<i32>) -> Result<ExpectedVersion, DBError> {
+    let Some(value) = headers.get(IF_MATCH) else {
+      let version = version.ok_or_else(|| DBError::InvalidInput(
+        "Send the task version in If-Match or the version field".to_string()
+      ))?;
+      return Ok(ExpectedVersion { version: Some(version), if_match: false });
+    };
+
+    let value = value.to_str().map_err(|e| DBError::InvalidInput(format!("Invalid If-Match: {e}")))?.trim();
+    if value == "*" {
+      return Ok(ExpectedVersion { version: None, if_match: true });
+    }
+    let if_match = value.trim_start_matches("W/").trim_matches('"').parse()
+      .map_err(|_| DBError::InvalidInput(format!("Invalid If-Match {value}, expected an ETag of the task")))?;
+    if version.is_some_and(|version| version != if_match) {
+      return Err(DBError::InvalidInput("If-Match and version name different versions".to_string()));
+    }
+    Ok(ExpectedVersion { version: Some(if_match), if_match: true })
+  }
+
+  /// Reports a mismatch with an `If-Match` version as a failed precondition. Other conflicts, such
+  /// as a blocked task, stay conflicts.
+  pub fn map_err(&self, e: DBError) -> DBError {
+    match e {
+      DBError::VersionConflict(msg) if self.if_match => DBError::PreconditionFailed(msg),
+      e => e,
+    }
+  }
+}
+
+/// Answer to a login with an unknown username or a wrong password, the same for both.
+pub fn invalid_login() -> DBError {
+  DBError::UnAuthorized("Invalid username or password".to_string())
 }
 
-pub fn validate_user(headers: &HeaderMap) -> Result<String, DBError> {
-  let token = headers.get("auth_token")
-      .ok_or_else(|| DBError::UnAuthorized("Error reading header auth_token".to_string()))?;
+/// Issues an access token together with a new refresh token for `user_name`.
+pub async fn issue_tokens(
+  tokens_dbo: &(dyn TokensDbo + Send + Sync),
+  auth_config: &AuthConfig,
+  user_name: &str
+) -> Result<UserToken, DBError> {
+  let token = generate_token(user_name, auth_config).map_err(DBError::Other)?;
+  let refresh_token = generate_refresh_token();
 
-  let user_name = get_user_from_token(
-      token.to_str().map_err(|e| DBError::UnAuthorized(format!("Invalid token: {e}")))?
-  ).map_err(|e| DBError::UnAuthorized(e))?;
+  tokens_dbo.create_refresh_token(
+      hash_refresh_token(&refresh_token),
+      user_name.to_string(),
+      REFRESH_TOKEN_TTL_SECS as i64
+  ).await?;
 
-  Ok(user_name)
+  Ok(UserToken { token, refresh_token })
 }
 
 // pub fn validate_user_token(headers: &HeaderMap, user_name: &str) -> Result<(), DBError> {
@@ -0,0 +1,15 @@
+//! The todo app as a library: `main` serves it, and integration tests build the router around a
+//! state of their own with `app::build_router`.
+pub mod app;
+pub mod models;
+pub mod persistence;
+pub mod handlers;
+pub mod services;
+pub mod auth;
+pub mod logging;
+pub mod graphql;
+pub mod events;
+pub mod config;
+pub mod reminders;
+pub mod trash;
+pub mod recurrence;
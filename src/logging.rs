@@ -1,14 +1,39 @@
 use axum:: {
   middleware::Next,
   extract::Request,
+  http::{HeaderName, HeaderValue},
 };
+use sqlx::types::Uuid;
 
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
 
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request being handled, for error bodies and log lines.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Logs the request and tags it with an id: the `x-request-id` sent by the client or proxy, or a new one.
+/// The id is echoed in the `x-request-id` response header.
 pub async fn logging_middleware(
     req: Request,
     next: Next,
 ) -> axum::response::Response {
-    println!("[{}] Request made to: {}", req.method(), req.uri());
-    let response = next.run(req).await;
+    let request_id = req.headers().get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+    println!("[{}] [{}] Request made to: {}", request_id, req.method(), req.uri());
+    let mut response = REQUEST_ID.scope(request_id.clone(), next.run(req)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
     response
-}
\ No newline at end of file
+}
@@ -1,14 +1,10 @@
 use axum::{self};
 use dotenvy::dotenv;
 
-mod app;
-mod models;
-mod persistence;
-mod handlers;
-mod auth;
-mod logging;
-
-use app::prepare_app;
+use std::sync::Arc;
+use todo_app::app::prepare_app;
+use todo_app::reminders::{reminder_interval_from_env, spawn_reminder_job, LogNotifier};
+use todo_app::trash::{spawn_trash_purge_job, trash_purge_interval_from_env, trash_retention_from_env};
 
 #[tokio::main]
 async fn main() {
@@ -18,7 +14,14 @@ async fn main() {
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
 
-    let app = prepare_app().await;
+    let (app, app_state) = prepare_app().await;
+
+    let reminder_interval = reminder_interval_from_env().expect("Invalid reminder configuration");
+    spawn_reminder_job(app_state.tasks_dbo.clone(), Arc::new(LogNotifier), reminder_interval);
+
+    let trash_retention = trash_retention_from_env().expect("Invalid trash configuration");
+    let trash_purge_interval = trash_purge_interval_from_env().expect("Invalid trash configuration");
+    spawn_trash_purge_job(app_state.tasks_dbo, trash_retention, trash_purge_interval);
 
     axum::serve(listener, app).await.unwrap();
 }
\ No newline at end of file
@@ -1,116 +1,1031 @@
 use serde::{Deserialize, Serialize};
+use async_graphql::{InputValueError, InputValueResult, MaybeUndefined, Scalar, ScalarType, Value, SimpleObject, InputObject, Enum};
+use std::fmt;
+use std::str::FromStr;
+use validator::{Validate, ValidationError, ValidationErrors};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum TaskStatus {
+const MAX_STATUS_KEY_LENGTH: usize = 64;
+
+/// Key of a status in the task's workflow, such as `review`. The built-in `todo`, `in_progress`
+/// and `done` keep their former names: `Todo`, `InProgress` and `Done` in JSON, `TODO`,
+/// `IN_PROGRESS` and `DONE` in GraphQL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TaskStatus(String);
+
+impl TaskStatus {
+    pub fn todo() -> TaskStatus {
+        TaskStatus("todo".to_string())
+    }
+
+    pub fn in_progress() -> TaskStatus {
+        TaskStatus("in_progress".to_string())
+    }
+
+    pub fn done() -> TaskStatus {
+        TaskStatus("done".to_string())
+    }
+
+    pub fn key(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = DBError;
+
+    /// Parses a key (lowercase letters, digits and `_`) or one of the built-in names.
+    fn from_str(s: &str) -> Result<TaskStatus, DBError> {
+        let key = match s {
+            "Todo" | "TODO" => "todo",
+            "InProgress" | "IN_PROGRESS" => "in_progress",
+            "Done" | "DONE" => "done",
+            _ => s,
+        };
+        let valid = key.len() <= MAX_STATUS_KEY_LENGTH
+            && key.starts_with(|c: char| c.is_ascii_lowercase())
+            && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(DBError::Validation(format!("Invalid status: {}", s)));
+        }
+        Ok(TaskStatus(key.to_string()))
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<String> for TaskStatus {
+    type Error = DBError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        TaskStatus::from_str(&value)
+    }
+}
+
+impl From<TaskStatus> for String {
+    fn from(status: TaskStatus) -> String {
+        match status.key() {
+            "todo" => "Todo".to_string(),
+            "in_progress" => "InProgress".to_string(),
+            "done" => "Done".to_string(),
+            _ => status.0,
+        }
+    }
+}
+
+#[Scalar(name = "TaskStatus")]
+impl ScalarType for TaskStatus {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        let parsed = match &value {
+            Value::String(s) => TaskStatus::from_str(s),
+            Value::Enum(name) => TaskStatus::from_str(name.as_str()),
+            _ => return Err(InputValueError::expected_type(value)),
+        };
+        parsed.map_err(|e| InputValueError::custom(e.to_string()))
+    }
+
+    fn to_value(&self) -> Value {
+        let name = match self.key() {
+            "todo" => "TODO",
+            "in_progress" => "IN_PROGRESS",
+            "done" => "DONE",
+            key => key,
+        };
+        Value::String(name.to_string())
+    }
+}
+
+/// What a status means for the task, whatever its workflow calls it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusCategory {
     Todo,
-    InProgress,
+    Active,
     Done,
 }
 
-impl TaskStatus {
-    pub fn from_str(s: &str) -> Result<TaskStatus, DBError> {
+impl FromStr for StatusCategory {
+    type Err = DBError;
+
+    fn from_str(s: &str) -> Result<StatusCategory, DBError> {
         match s {
-            "todo" => Ok(TaskStatus::Todo),
-            "in_progress" => Ok(TaskStatus::InProgress),
-            "done" => Ok(TaskStatus::Done),
-            _ => Err(DBError::Other(format!("Invalid status: {}", s))),
+            "todo" => Ok(StatusCategory::Todo),
+            "active" => Ok(StatusCategory::Active),
+            "done" => Ok(StatusCategory::Done),
+            _ => Err(DBError::Other(format!("Invalid status category: {}", s))),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for StatusCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            TaskStatus::Todo => "todo".to_string(),
-            TaskStatus::InProgress => "in_progress".to_string(),
-            TaskStatus::Done => "done".to_string(),
+            StatusCategory::Todo => write!(f, "todo"),
+            StatusCategory::Active => write!(f, "active"),
+            StatusCategory::Done => write!(f, "done"),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Urgent,
+}
+
+impl TaskPriority {
+    /// Sort key, matching `priority_rank` in the database.
+    pub fn rank(self) -> i32 {
+        self as i32
+    }
+}
+
+impl FromStr for TaskPriority {
+    type Err = DBError;
+
+    fn from_str(s: &str) -> Result<TaskPriority, DBError> {
+        match s {
+            "low" => Ok(TaskPriority::Low),
+            "medium" => Ok(TaskPriority::Medium),
+            "high" => Ok(TaskPriority::High),
+            "urgent" => Ok(TaskPriority::Urgent),
+            _ => Err(DBError::Other(format!("Invalid priority: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for TaskPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskPriority::Low => write!(f, "low"),
+            TaskPriority::Medium => write!(f, "medium"),
+            TaskPriority::High => write!(f, "high"),
+            TaskPriority::Urgent => write!(f, "urgent"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, InputObject, Validate)]
+#[graphql(name = "TaskInput")]
 pub struct Task {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
     pub title: String,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
     pub description: String,
-    pub status: TaskStatus
+    pub status: TaskStatus,
+    #[serde(default)]
+    #[graphql(default)]
+    pub priority: TaskPriority,
+    /// Project the task belongs to; personal task when absent.
+    pub project_uuid: Option<String>,
+    /// RFC 3339 due date.
+    pub due_at: Option<String>,
+    /// Seconds before `due_at` at which reminders are sent.
+    #[serde(default)]
+    #[graphql(default)]
+    pub remind_offsets: Vec<i32>,
+    /// RRULE subset such as `FREQ=WEEKLY;BYDAY=MO` or `monthly`.
+    pub recurrence: Option<String>,
+    /// Creates the task as the last subtask of this task. Subtasks always belong to the parent's project.
+    pub parent_task_uuid: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A JSON Merge Patch of the task: fields that are absent keep their value. `null` clears
+/// `due_at`, `recurrence` and `assignee`, and is the same as absent for the other fields.
+#[derive(Serialize, Deserialize, Debug, InputObject, Validate)]
+#[graphql(name = "TaskUpdateInput")]
 pub struct TaskUpdateReq {
     pub task_uuid: String,
-    pub title: String,
-    pub description: String,
-    pub status: TaskStatus,
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub title: Option<String>,
+    #[validate(length(max = 255, message = "must be at most 255 characters"))]
+    pub description: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub priority: Option<TaskPriority>,
+    /// RFC 3339 due date.
+    #[serde(default, skip_serializing_if = "MaybeUndefined::is_undefined")]
+    pub due_at: MaybeUndefined<String>,
+    pub remind_offsets: Option<Vec<i32>>,
+    #[serde(default, skip_serializing_if = "MaybeUndefined::is_undefined")]
+    pub recurrence: MaybeUndefined<String>,
+    /// Project tasks can only be assigned to project members.
+    #[serde(default, skip_serializing_if = "MaybeUndefined::is_undefined")]
+    pub assignee: MaybeUndefined<String>,
+    /// Version of the task the change is based on, required unless `If-Match` is sent.
+    pub version: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
 pub struct TaskStatusReq {
     pub task_uuid: String,
     pub status: TaskStatus,
+    /// Version of the task the change is based on, required unless `If-Match` is sent.
+    pub version: Option<i32>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Query parameters of `GET /{id}/occurrences`.
+#[derive(Debug, Deserialize)]
+pub struct OccurrencesQuery {
+    pub count: Option<usize>,
+}
+
+/// Body of `PATCH /{id}/assign`; a `null` assignee unassigns the task.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct TaskAssignReq {
+    pub assignee: Option<String>,
+}
+
+/// Body of `PATCH /{id}/parent`: moves the task under another parent (`null` for top level)
+/// at `position` among its subtasks, or last when absent.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct TaskParentReq {
+    pub parent_task_uuid: Option<String>,
+    pub position: Option<i32>,
+}
+
+/// Body of `PATCH /{id}/move`: moves the task to the `status` column, right after
+/// `after_task_uuid` and/or right before `before_task_uuid`, or last without either.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct TaskMoveReq {
+    pub status: TaskStatus,
+    pub after_task_uuid: Option<String>,
+    pub before_task_uuid: Option<String>,
+}
+
+/// Body of `POST /{id}/dependencies`: the task `id` is blocked by `blocker_uuid`.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct TaskDependencyReq {
+    pub blocker_uuid: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
 pub struct TaskId {
     pub task_uuid: String,
+    /// Also delete the subtasks; deleting a task that has subtasks is rejected otherwise.
+    #[serde(default)]
+    pub cascade: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, SimpleObject)]
+#[graphql(complex)]
 pub struct TaskDetail {
     pub task_uuid: String,
     pub title: String,
     pub description: String,
     pub status: TaskStatus,
-    pub user_name: String,
+    pub status_category: StatusCategory,
+    pub priority: TaskPriority,
+    /// Order within the status column, ascending.
+    pub position: f64,
+    pub created_by: String,
+    pub assignee: Option<String>,
     pub created_at: String,
+    pub project_uuid: Option<String>,
+    pub due_at: Option<String>,
+    pub remind_offsets: Vec<i32>,
+    /// Past its due date and not done yet.
+    pub overdue: bool,
+    pub recurrence: Option<String>,
+    /// First task of the recurring series this task belongs to.
+    pub series_uuid: Option<String>,
+    /// Position in the series, starting at 1.
+    pub occurrence: i32,
+    pub parent_task_uuid: Option<String>,
+    /// Order among the subtasks of the same parent, starting at 0.
+    pub subtask_position: i32,
+    /// Blocked by at least one task that is not done yet.
+    pub blocked: bool,
+    pub labels: Vec<LabelDetail>,
+    /// When the task was moved to the trash, `None` for tasks not in the trash.
+    pub deleted_at: Option<String>,
+    /// Incremented on every change of the task, sent as its `ETag`.
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSortField {
+    #[default]
+    CreatedAt,
+    Title,
+    Status,
+    Priority,
+    Position,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+/// Query parameters of `GET /`. Timestamps are RFC 3339, `q` matches title or description.
+#[derive(Debug, Default, Deserialize)]
+pub struct TaskListQuery {
+    pub project_uuid: Option<String>,
+    pub assignee: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub status_category: Option<StatusCategory>,
+    /// `true` for tasks past their due date that are not done, `false` for all others.
+    pub overdue: Option<bool>,
+    /// `true` for tasks waiting on unfinished blockers, `false` for all others.
+    pub blocked: Option<bool>,
+    /// Comma separated label names, e.g. `tags=bug,urgent`.
+    pub tags: Option<String>,
+    /// Whether tasks need `any` (default) or `all` of the `tags`.
+    #[serde(default)]
+    pub tags_mode: TagMatch,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub q: Option<String>,
+    #[serde(default)]
+    pub sort_by: TaskSortField,
+    #[serde(default)]
+    pub order: SortDirection,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct TaskPage {
+    pub tasks: Vec<TaskDetail>,
+    pub next_cursor: Option<String>,
+    pub total: i64,
+}
+
+/// Query parameters of `GET /search`. `q` uses web search syntax (quotes, `or`, `-`).
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+/// A search match with `<mark>`-highlighted snippets.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TaskSearchHit {
+    pub task: TaskDetail,
+    pub rank: f32,
+    pub title_highlight: String,
+    pub description_highlight: String,
+    /// Best matching tracking entry, when the match came from the task history.
+    pub tracking_highlight: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, InputObject, Validate)]
+#[graphql(name = "UserInput")]
 pub struct User {
+    #[validate(
+        length(min = 3, max = 64, message = "must be between 3 and 64 characters"),
+        custom(function = "validate_username")
+    )]
     pub username: String,
+    #[validate(custom(function = "validate_password"))]
     pub password: String,
+    #[validate(
+        email(message = "must be a valid email address"),
+        length(max = 255, message = "must be at most 255 characters")
+    )]
     pub email: String
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+fn validation_error(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError::new(code).with_message(message.into())
+}
+
+/// Usernames end up in URLs and log lines, so they are kept to letters, digits, `_`, `-` and `.`.
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return Err(validation_error("charset", "may only contain letters, digits, '_', '-' and '.'"));
+    }
+    Ok(())
+}
+
+/// At least 8 characters, with a letter and a digit. The upper bound keeps hashing cheap.
+fn validate_password(password: &str) -> Result<(), ValidationError> {
+    let length = password.chars().count();
+    if length < MIN_PASSWORD_LENGTH {
+        return Err(validation_error("length", "must be at least 8 characters"));
+    }
+    if length > MAX_PASSWORD_LENGTH {
+        return Err(validation_error("length", "must be at most 128 characters"));
+    }
+    if !password.chars().any(char::is_alphabetic) || !password.chars().any(|c| c.is_ascii_digit()) {
+        return Err(validation_error("strength", "must contain a letter and a digit"));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, SimpleObject)]
+#[graphql(name = "User")]
 pub struct UserDetail {
     pub username: String,
+    #[graphql(skip)]
     pub password: String,
     pub email: String,
     pub created_at: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, SimpleObject)]
 pub struct UserToken {
     pub token: String,
+    pub refresh_token: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Validate)]
+pub struct RefreshTokenReq {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, Deserialize, Validate)]
+pub struct LogoutReq {
+    /// Refresh token to revoke along with the current access token.
+    pub refresh_token: Option<String>,
+}
+
+/// Only the lengths are checked, so accounts created before the password policy can still log in.
+#[derive(Serialize, Deserialize, Debug, InputObject, Validate)]
+#[graphql(name = "LoginInput")]
 pub struct LoginReq {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
     pub username: String,
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize)]
+/// Kind of change recorded in the history of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingEventType {
+    Created,
+    Updated,
+    StatusChanged,
+    Assigned,
+    /// Moved on the board, to another status and/or position.
+    Moved,
+    ParentChanged,
+    DependencyAdded,
+    DependencyRemoved,
+    LabelAdded,
+    LabelRemoved,
+    /// The next occurrence of a recurring task was created.
+    OccurrenceScheduled,
+    /// Moved to the trash.
+    Deleted,
+    /// Taken out of the trash.
+    Restored,
+    /// Free-text entry written before history entries were typed.
+    Legacy,
+}
+
+impl FromStr for TrackingEventType {
+    type Err = DBError;
+
+    fn from_str(s: &str) -> Result<TrackingEventType, DBError> {
+        match s {
+            "created" => Ok(TrackingEventType::Created),
+            "updated" => Ok(TrackingEventType::Updated),
+            "status_changed" => Ok(TrackingEventType::StatusChanged),
+            "assigned" => Ok(TrackingEventType::Assigned),
+            "moved" => Ok(TrackingEventType::Moved),
+            "parent_changed" => Ok(TrackingEventType::ParentChanged),
+            "dependency_added" => Ok(TrackingEventType::DependencyAdded),
+            "dependency_removed" => Ok(TrackingEventType::DependencyRemoved),
+            "label_added" => Ok(TrackingEventType::LabelAdded),
+            "label_removed" => Ok(TrackingEventType::LabelRemoved),
+            "occurrence_scheduled" => Ok(TrackingEventType::OccurrenceScheduled),
+            "deleted" => Ok(TrackingEventType::Deleted),
+            "restored" => Ok(TrackingEventType::Restored),
+            "legacy" => Ok(TrackingEventType::Legacy),
+            _ => Err(DBError::Other(format!("Invalid tracking event type: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for TrackingEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackingEventType::Created => write!(f, "created"),
+            TrackingEventType::Updated => write!(f, "updated"),
+            TrackingEventType::StatusChanged => write!(f, "status_changed"),
+            TrackingEventType::Assigned => write!(f, "assigned"),
+            TrackingEventType::Moved => write!(f, "moved"),
+            TrackingEventType::ParentChanged => write!(f, "parent_changed"),
+            TrackingEventType::DependencyAdded => write!(f, "dependency_added"),
+            TrackingEventType::DependencyRemoved => write!(f, "dependency_removed"),
+            TrackingEventType::LabelAdded => write!(f, "label_added"),
+            TrackingEventType::LabelRemoved => write!(f, "label_removed"),
+            TrackingEventType::OccurrenceScheduled => write!(f, "occurrence_scheduled"),
+            TrackingEventType::Deleted => write!(f, "deleted"),
+            TrackingEventType::Restored => write!(f, "restored"),
+            TrackingEventType::Legacy => write!(f, "legacy"),
+        }
+    }
+}
+
+/// Value of a task field before and after a change, `null` when unset.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, SimpleObject)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+/// A history entry to record for a task.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Tracking {
-   pub status: String,
-   pub task_uuid: String,
+    pub task_uuid: String,
+    pub event_type: TrackingEventType,
+    /// User who made the change.
+    pub actor: String,
+    pub changes: Vec<FieldChange>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl Tracking {
+    pub fn new(task_uuid: &str, event_type: TrackingEventType, actor: &str) -> Tracking {
+        Tracking {
+            task_uuid: task_uuid.to_string(),
+            event_type,
+            actor: actor.to_string(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Records `field` unless its value is the same before and after.
+    pub fn change(mut self, field: &str, before: impl Serialize, after: impl Serialize) -> Tracking {
+        let before = serde_json::to_value(before).unwrap_or_default();
+        let after = serde_json::to_value(after).unwrap_or_default();
+        if before != after {
+            self.changes.push(FieldChange {
+                field: field.to_string(),
+                before,
+                after,
+            });
+        }
+        self
+    }
+
+    /// Records every tracked field of the task that differs from `before`, all of its set
+    /// fields for a new task.
+    pub fn task_changes(self, before: Option<&TaskDetail>, after: &TaskDetail) -> Tracking {
+        let field = |f: fn(&TaskDetail) -> serde_json::Value| {
+            (before.map(f).unwrap_or_default(), f(after))
+        };
+        let fields = [
+            ("title", field(|t| t.title.clone().into())),
+            ("description", field(|t| t.description.clone().into())),
+            ("status", field(|t| serde_json::to_value(&t.status).unwrap_or_default())),
+            ("priority", field(|t| serde_json::to_value(t.priority).unwrap_or_default())),
+            ("position", field(|t| t.position.into())),
+            ("assignee", field(|t| t.assignee.clone().into())),
+            ("project_uuid", field(|t| t.project_uuid.clone().into())),
+            ("due_at", field(|t| t.due_at.clone().into())),
+            ("remind_offsets", field(|t| t.remind_offsets.clone().into())),
+            ("recurrence", field(|t| t.recurrence.clone().into())),
+            ("series_uuid", field(|t| t.series_uuid.clone().into())),
+            ("occurrence", field(|t| t.occurrence.into())),
+            ("parent_task_uuid", field(|t| t.parent_task_uuid.clone().into())),
+            ("subtask_position", field(|t| t.subtask_position.into())),
+        ];
+        fields.into_iter().fold(self, |tracking, (name, (before, after))| tracking.change(name, before, after))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject)]
+#[graphql(name = "Tracking")]
 pub struct TrackingDetail {
     pub id: String,
-    pub status: String,
     pub task_uuid: String,
+    pub event_type: TrackingEventType,
+    /// `None` for legacy entries.
+    pub actor: Option<String>,
+    pub changes: Vec<FieldChange>,
+    /// Free text of legacy entries.
+    pub message: Option<String>,
     pub created_at: String,
 }
 
+/// Query parameters of `GET /{id}/history`.
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    pub event_type: Option<TrackingEventType>,
+}
+
+/// Completion of a task's subtasks, counted over the whole subtree.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, SimpleObject)]
+pub struct TaskProgress {
+    pub total: i64,
+    pub done: i64,
+    pub percent: f64,
+}
+
+impl TaskProgress {
+    /// `None` for tasks without subtasks.
+    pub fn from_subtasks<'a>(subtasks: impl IntoIterator<Item = &'a TaskDetail>) -> Option<TaskProgress> {
+        let (total, done) = subtasks.into_iter().fold((0, 0), |(total, done), task| {
+            (total + 1, done + (task.status_category == StatusCategory::Done) as i64)
+        });
+        (total > 0).then(|| TaskProgress {
+            total,
+            done,
+            percent: (done as f64 * 100.0 / total as f64 * 10.0).round() / 10.0,
+        })
+    }
+}
+
+/// A subtask together with its own subtasks.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SubtaskNode {
+    #[serde(flatten)]
+    pub task: TaskDetail,
+    pub progress: Option<TaskProgress>,
+    pub subtasks: Vec<SubtaskNode>,
+}
+
+impl SubtaskNode {
+    /// Builds the tree below `parent_uuid` from its descendants, keeping their order.
+    pub fn tree(parent_uuid: &str, descendants: &[TaskDetail]) -> Vec<SubtaskNode> {
+        descendants.iter()
+            .filter(|task| task.parent_task_uuid.as_deref() == Some(parent_uuid))
+            .map(|task| {
+                let subtasks = SubtaskNode::tree(&task.task_uuid, descendants);
+                SubtaskNode {
+                    task: task.clone(),
+                    progress: TaskProgress::from_subtasks(subtasks.iter().flat_map(SubtaskNode::flatten)),
+                    subtasks,
+                }
+            })
+            .collect()
+    }
+
+    fn flatten(&self) -> Vec<&TaskDetail> {
+        std::iter::once(&self.task)
+            .chain(self.subtasks.iter().flat_map(SubtaskNode::flatten))
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct TaskDetailResponse {
     pub task: TaskDetail,
     pub tracking: Option<Vec<TrackingDetail>>,
+    pub progress: Option<TaskProgress>,
+    pub subtasks: Vec<SubtaskNode>,
+    /// Tasks this task is blocked by.
+    pub blockers: Vec<TaskDetail>,
+    /// Tasks blocked by this task.
+    pub dependents: Vec<TaskDetail>,
+}
+
+/// Role of a member within a shared project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectRole {
+    /// Manages members and can edit every task.
+    Owner,
+    /// Can create, edit and delete tasks.
+    Editor,
+    /// Read-only access.
+    Viewer,
+}
+
+impl FromStr for ProjectRole {
+    type Err = DBError;
+
+    fn from_str(s: &str) -> Result<ProjectRole, DBError> {
+        match s {
+            "owner" => Ok(ProjectRole::Owner),
+            "editor" => Ok(ProjectRole::Editor),
+            "viewer" => Ok(ProjectRole::Viewer),
+            _ => Err(DBError::Other(format!("Invalid role: {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for ProjectRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectRole::Owner => write!(f, "owner"),
+            ProjectRole::Editor => write!(f, "editor"),
+            ProjectRole::Viewer => write!(f, "viewer"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, InputObject, Validate)]
+#[graphql(name = "ProjectInput")]
+pub struct Project {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject)]
+#[graphql(name = "Project")]
+pub struct ProjectDetail {
+    pub project_uuid: String,
+    pub name: String,
+    pub created_by: String,
+    pub created_at: String,
+    /// Role of the caller in this project.
+    pub role: ProjectRole,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject)]
+pub struct ProjectMember {
+    pub username: String,
+    pub role: ProjectRole,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, InputObject, Validate)]
+#[graphql(name = "ProjectMemberInput")]
+pub struct ProjectMemberReq {
+    #[validate(length(min = 1, max = 255, message = "must be between 1 and 255 characters"))]
+    pub username: String,
+    pub role: ProjectRole,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct ProjectRoleReq {
+    pub role: ProjectRole,
+}
+
+/// A new label. Without `project_uuid` it is a personal label for the caller's own tasks.
+#[derive(Serialize, Deserialize, Clone, Debug, InputObject, Validate)]
+#[graphql(name = "LabelInput")]
+pub struct Label {
+    pub name: String,
+    /// Hex color such as `#ff8800`.
+    pub color: Option<String>,
+    pub project_uuid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, SimpleObject)]
+#[graphql(name = "Label")]
+pub struct LabelDetail {
+    pub label_uuid: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub project_uuid: Option<String>,
+}
+
+/// Body of `PATCH /labels/{id}`; absent fields keep their value.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct LabelUpdateReq {
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+/// Query parameters of `GET /labels`: the labels of a project, or the personal ones without `project_uuid`.
+#[derive(Debug, Default, Deserialize)]
+pub struct LabelListQuery {
+    pub project_uuid: Option<String>,
+}
+
+/// Body of `POST /{id}/labels`.
+#[derive(Serialize, Deserialize, Clone, Debug, Validate)]
+pub struct TaskLabelReq {
+    pub label_uuid: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject, InputObject)]
+#[graphql(input_name = "WorkflowStatusInput")]
+pub struct WorkflowStatus {
+    pub key: TaskStatus,
+    pub name: String,
+    pub category: StatusCategory,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject, InputObject)]
+#[graphql(input_name = "WorkflowTransitionInput")]
+pub struct WorkflowTransition {
+    pub from: TaskStatus,
+    pub to: TaskStatus,
+}
+
+/// Body of `PUT /workflow` and `PUT /projects/{id}/workflow`: statuses in board order and the
+/// allowed status changes, any change being allowed without `transitions`.
+#[derive(Serialize, Deserialize, Clone, Debug, InputObject, Validate)]
+#[graphql(name = "WorkflowInput")]
+pub struct Workflow {
+    pub statuses: Vec<WorkflowStatus>,
+    #[serde(default)]
+    #[graphql(default)]
+    pub transitions: Vec<WorkflowTransition>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, SimpleObject)]
+#[graphql(name = "Workflow")]
+pub struct WorkflowDetail {
+    /// `None` for the built-in workflow.
+    pub workflow_uuid: Option<String>,
+    pub project_uuid: Option<String>,
+    pub statuses: Vec<WorkflowStatus>,
+    pub transitions: Vec<WorkflowTransition>,
+}
+
+impl WorkflowDetail {
+    /// The `todo`, `in_progress` and `done` workflow used until a custom one is set.
+    pub fn builtin(project_uuid: Option<String>) -> WorkflowDetail {
+        let status = |key: TaskStatus, name: &str, category| WorkflowStatus {
+            key,
+            name: name.to_string(),
+            category,
+        };
+        WorkflowDetail {
+            workflow_uuid: None,
+            project_uuid,
+            statuses: vec![
+                status(TaskStatus::todo(), "To do", StatusCategory::Todo),
+                status(TaskStatus::in_progress(), "In progress", StatusCategory::Active),
+                status(TaskStatus::done(), "Done", StatusCategory::Done),
+            ],
+            transitions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum TaskEventKind {
+    Created,
+    Updated,
+    StatusUpdated,
+    Assigned,
+    Deleted,
+    Restored,
+}
+
+/// A change to a task, as pushed to GraphQL subscribers.
+#[derive(Clone, SimpleObject)]
+pub struct TaskEvent {
+    pub kind: TaskEventKind,
+    pub task: TaskDetail,
+    /// The tracking row written for the change.
+    pub tracking: Option<TrackingDetail>,
+    /// User who made the change; differs from the task creator for shared or assigned tasks.
+    #[graphql(name = "changedBy")]
+    pub user_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderKind {
+    /// One of the task's `remind_offsets` was reached.
+    DueSoon,
+    /// The task passed its due date without being done.
+    Overdue,
+}
+
+/// A reminder produced by the background job and handed to a `Notifier`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskReminder {
+    pub kind: ReminderKind,
+    /// The offset that fired, for `DueSoon` reminders.
+    pub offset_secs: Option<i32>,
+    pub task: TaskDetail,
 }
 
 #[derive(Debug)]
 pub enum DBError {
+  /// The request is malformed, such as an unparsable uuid, timestamp or cursor.
   InvalidInput(String),
+  /// Missing, invalid or revoked credentials.
   UnAuthorized(String),
+  /// The caller is known but their role does not allow the change.
+  Forbidden(String),
+  /// The task, project, label or user does not exist or is not visible to the caller.
+  NotFound(String),
+  /// The change clashes with the current state: a duplicate, a cycle, a disallowed status change, ...
+  Conflict(String),
+  /// The task is no longer at the version the change is based on.
+  VersionConflict(String),
+  /// Like `VersionConflict`, for versions sent in `If-Match`.
+  PreconditionFailed(String),
+  /// The request is well-formed but a value breaks a rule, such as a name that is too long.
+  Validation(String),
+  /// Like `Validation`, for the fields of a request body that break their declared rules.
+  InvalidFields(Vec<FieldError>),
   Other(String),
-}
\ No newline at end of file
+}
+
+impl DBError {
+  /// Stable identifier of the kind of error, sent to clients next to the message.
+  pub fn code(&self) -> &'static str {
+    match self {
+      DBError::InvalidInput(_) => "invalid_input",
+      DBError::UnAuthorized(_) => "unauthorized",
+      DBError::Forbidden(_) => "forbidden",
+      DBError::NotFound(_) => "not_found",
+      DBError::Conflict(_) | DBError::VersionConflict(_) => "conflict",
+      DBError::PreconditionFailed(_) => "precondition_failed",
+      DBError::Validation(_) | DBError::InvalidFields(_) => "validation_failed",
+      DBError::Other(_) => "internal",
+    }
+  }
+}
+
+impl fmt::Display for DBError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DBError::InvalidInput(msg) => write!(f, "{msg}"),
+            DBError::UnAuthorized(msg) => write!(f, "{msg}"),
+            DBError::Forbidden(msg) => write!(f, "{msg}"),
+            DBError::NotFound(msg) => write!(f, "{msg}"),
+            DBError::Conflict(msg) => write!(f, "{msg}"),
+            DBError::VersionConflict(msg) => write!(f, "{msg}"),
+            DBError::PreconditionFailed(msg) => write!(f, "{msg}"),
+            DBError::Validation(msg) => write!(f, "{msg}"),
+            DBError::InvalidFields(fields) => {
+                let fields: Vec<String> = fields.iter().map(|e| format!("{} {}", e.field, e.message)).collect();
+                write!(f, "Invalid request: {}", fields.join(", "))
+            }
+            DBError::Other(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+/// Maps what Postgres reports to what it means for the caller: a missing row, a duplicate key, or
+/// a reference to a row that does not exist. Anything else is an internal error.
+impl From<sqlx::Error> for DBError {
+    fn from(e: sqlx::Error) -> DBError {
+        let Some(db_error) = e.as_database_error() else {
+            return match e {
+                sqlx::Error::RowNotFound => DBError::NotFound("Not found".to_string()),
+                e => DBError::Other(e.to_string()),
+            };
+        };
+        // The detail names the key, e.g. `Key (username)=(alice) already exists.`
+        let detail = db_error.try_downcast_ref::<sqlx::postgres::PgDatabaseError>()
+            .and_then(|e| e.detail())
+            .unwrap_or(db_error.message())
+            .to_string();
+
+        match db_error.code().as_deref() {
+            Some("23505") => DBError::Conflict(detail),
+            // The detail of a foreign key violation names tables and keys of other users, callers
+            // that can run into one say what is missing instead.
+            Some("23503") => DBError::Validation("The change refers to a record that does not exist".to_string()),
+            _ => DBError::Other(e.to_string()),
+        }
+    }
+}
+
+/// One error per broken rule, in field order.
+impl From<ValidationErrors> for DBError {
+    fn from(errors: ValidationErrors) -> DBError {
+        let mut fields: Vec<FieldError> = errors.field_errors().into_iter().flat_map(|(field, errors)| {
+            errors.iter().map(move |e| FieldError {
+                field: field.to_string(),
+                code: e.code.to_string(),
+                message: e.message.as_deref().unwrap_or("is invalid").to_string(),
+            })
+        }).collect();
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        DBError::InvalidFields(fields)
+    }
+}
+
+/// Body of every error response.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ErrorBody {
+    /// One of the `DBError::code` values.
+    pub code: String,
+    pub message: String,
+    /// Id of the request, also sent in the `x-request-id` header and written to the logs.
+    pub request_id: Option<String>,
+    /// The fields that failed validation, for `validation_failed` errors about a request body.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    /// Rule that failed, such as `length`, `email` or `charset`.
+    pub code: String,
+    pub message: String,
+}
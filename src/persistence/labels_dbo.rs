@@ -0,0 +1,274 @@
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, Label, LabelDetail, LabelUpdateReq};
+use crate::persistence::unit_of_work::UnitOfWork;
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[async_trait]
+pub trait LabelsDbo {
+  /// Creates a personal label, or a project label when `project_uuid` is set (owners and editors only).
+  async fn create_label(&self, label: Label, user: String) -> Result<LabelDetail, DBError>;
+  /// Labels of the project, or the personal labels of `user` without one.
+  async fn list_labels(&self, project_uuid: Option<String>, user: String) -> Result<Vec<LabelDetail>, DBError>;
+  async fn update_label(&self, label_uuid: &str, label: LabelUpdateReq, user: String) -> Result<LabelDetail, DBError>;
+  /// Deletes the label and detaches it from all tasks.
+  async fn delete_label(&self, label_uuid: &str, user: String) -> Result<(), DBError>;
+  /// Attaches a label to a task. Project tasks take labels of their project, personal tasks the
+  /// personal labels of their creator.
+  async fn attach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError>;
+  async fn detach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError>;
+}
+
+pub struct LabelsDboImpl {
+  db: PgPool,
+}
+
+impl LabelsDboImpl {
+  pub fn new(db: PgPool) -> Self {
+    Self {
+      db,
+    }
+  }
+
+  /// Label visible to `user`.
+  async fn get_label<'e>(executor: impl PgExecutor<'e>, label_uuid: Uuid, user: &str) -> Result<LabelDetail, DBError> {
+    let record = sqlx::query!(
+        r#"
+        SELECT label_uuid, name, color, project_uuid FROM labels
+        WHERE label_uuid = $1 AND (user_username = $2 OR project_role(project_uuid, $2) IS NOT NULL)
+        "#,
+        label_uuid,
+        user
+    ).fetch_optional(executor).await.map_err(DBError::from)?;
+
+    let record = record.ok_or_else(|| DBError::NotFound(format!("Label {label_uuid} not found")))?;
+    Ok(LabelDetail {
+      label_uuid: record.label_uuid.to_string(),
+      name: record.name,
+      color: record.color,
+      project_uuid: record.project_uuid.map(|uuid| uuid.to_string()),
+    })
+  }
+
+  /// Explains why a label write matched nothing: it is missing/invisible, or the caller is a project viewer.
+  async fn not_manageable(&self, label_uuid: Uuid, user: &str) -> DBError {
+    match Self::get_label(&self.db, label_uuid, user).await {
+      Ok(_) => DBError::Forbidden("Only project owners and editors can manage labels".to_string()),
+      Err(e) => e,
+    }
+  }
+
+  /// Checks that `user` may change the labels of the task.
+  async fn require_task_editor(conn: &mut PgConnection, task_uuid: Uuid, user: &str) -> Result<(), DBError> {
+    let role = sqlx::query_scalar!(
+        r#"
+        SELECT task_role(tasks, $2) FROM tasks WHERE task_uuid = $1
+        "#,
+        task_uuid,
+        user
+    ).fetch_optional(conn).await.map_err(DBError::from)?;
+
+    match role.flatten().as_deref() {
+      Some("owner" | "editor") => Ok(()),
+      Some(_) => Err(DBError::Forbidden("Viewers cannot modify tasks".to_string())),
+      None => Err(DBError::NotFound(format!("Task {task_uuid} not found"))),
+    }
+  }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, DBError> {
+  Uuid::parse_str(value).map_err(|e| DBError::InvalidInput(e.to_string()))
+}
+
+pub(crate) fn check_name(name: &str) -> Result<String, DBError> {
+  let name = name.trim();
+  if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+    return Err(DBError::Validation(format!("Label names need 1 to {MAX_NAME_LENGTH} characters")));
+  }
+  if name.contains(',') {
+    return Err(DBError::Validation("Label names cannot contain commas".to_string()));
+  }
+  Ok(name.to_string())
+}
+
+pub(crate) fn check_color(color: &str) -> Result<String, DBError> {
+  let valid = color.len() == 7
+    && color.starts_with('#')
+    && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+  if !valid {
+    return Err(DBError::Validation(format!("Invalid color {color}, expected #rrggbb")));
+  }
+  Ok(color.to_ascii_lowercase())
+}
+
+/// Maps the unique name indexes of `labels` to a readable error.
+fn duplicate_name(e: sqlx::Error, name: &str) -> DBError {
+  match DBError::from(e) {
+    DBError::Conflict(_) => DBError::Conflict(format!("Label {name} already exists")),
+    e => e,
+  }
+}
+
+#[async_trait]
+impl LabelsDbo for LabelsDboImpl {
+  async fn create_label(&self, label: Label, user: String) -> Result<LabelDetail, DBError> {
+    let name = check_name(&label.name)?;
+    let color = label.color.as_deref().map(check_color).transpose()?;
+    let project_uuid = label.project_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let record = sqlx::query!(
+        r#"
+        INSERT INTO labels (name, color, user_username, project_uuid, created_by)
+        SELECT $1, $2, CASE WHEN $3::uuid IS NULL THEN $4 END, $3, $4
+        WHERE $3::uuid IS NULL OR project_role($3, $4) IN ('owner', 'editor')
+        RETURNING label_uuid, name, color, project_uuid
+        "#,
+        name,
+        color,
+        project_uuid,
+        user
+    ).fetch_optional(&self.db).await.map_err(|e| duplicate_name(e, &name))?;
+
+    let Some(record) = record else {
+      let role = sqlx::query_scalar!("SELECT project_role($1, $2)", project_uuid, user)
+        .fetch_one(&self.db).await.map_err(DBError::from)?;
+      return Err(match role {
+        Some(_) => DBError::Forbidden("Only project owners and editors can manage labels".to_string()),
+        None => DBError::NotFound(format!("Project {} not found", label.project_uuid.unwrap_or_default())),
+      });
+    };
+    Ok(LabelDetail {
+      label_uuid: record.label_uuid.to_string(),
+      name: record.name,
+      color: record.color,
+      project_uuid: record.project_uuid.map(|uuid| uuid.to_string()),
+    })
+  }
+
+  async fn list_labels(&self, project_uuid: Option<String>, user: String) -> Result<Vec<LabelDetail>, DBError> {
+    let project_uuid = project_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let records = sqlx::query!(
+        r#"
+        SELECT label_uuid, name, color, project_uuid FROM labels
+        WHERE CASE WHEN $1::uuid IS NULL THEN user_username = $2
+                   ELSE project_uuid = $1 AND project_role($1, $2) IS NOT NULL END
+        ORDER BY lower(name)
+        "#,
+        project_uuid,
+        user
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    Ok(records.into_iter().map(|r| {
+      LabelDetail {
+        label_uuid: r.label_uuid.to_string(),
+        name: r.name,
+        color: r.color,
+        project_uuid: r.project_uuid.map(|uuid| uuid.to_string()),
+      }
+    }).collect())
+  }
+
+  async fn update_label(&self, label_uuid: &str, label: LabelUpdateReq, user: String) -> Result<LabelDetail, DBError> {
+    let uuid = parse_uuid(label_uuid)?;
+    let name = label.name.as_deref().map(check_name).transpose()?;
+    let color = label.color.as_deref().map(check_color).transpose()?;
+
+    let record = sqlx::query!(
+        r#"
+        UPDATE labels SET name = COALESCE($2, name), color = COALESCE($3, color)
+        WHERE label_uuid = $1 AND (user_username = $4 OR project_role(project_uuid, $4) IN ('owner', 'editor'))
+        RETURNING label_uuid, name, color, project_uuid
+        "#,
+        uuid,
+        name,
+        color,
+        user
+    ).fetch_optional(&self.db).await.map_err(|e| duplicate_name(e, name.as_deref().unwrap_or_default()))?;
+
+    match record {
+      Some(record) => Ok(LabelDetail {
+        label_uuid: record.label_uuid.to_string(),
+        name: record.name,
+        color: record.color,
+        project_uuid: record.project_uuid.map(|uuid| uuid.to_string()),
+      }),
+      None => Err(self.not_manageable(uuid, &user).await),
+    }
+  }
+
+  async fn delete_label(&self, label_uuid: &str, user: String) -> Result<(), DBError> {
+    let uuid = parse_uuid(label_uuid)?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM labels
+        WHERE label_uuid = $1 AND (user_username = $2 OR project_role(project_uuid, $2) IN ('owner', 'editor'))
+        "#,
+        uuid,
+        user
+    ).execute(&self.db).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(self.not_manageable(uuid, &user).await);
+    }
+    Ok(())
+  }
+
+  async fn attach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError> {
+    let task = parse_uuid(task_uuid)?;
+    let uuid = parse_uuid(label_uuid)?;
+    Self::require_task_editor(uow.conn()?, task, &user).await?;
+    let label = Self::get_label(uow.conn()?, uuid, &user).await?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO task_labels (task_uuid, label_uuid)
+        SELECT t.task_uuid, l.label_uuid
+        FROM tasks t, labels l
+        WHERE t.task_uuid = $1 AND l.label_uuid = $2
+          AND (l.project_uuid = t.project_uuid OR (t.project_uuid IS NULL AND l.user_username = t.user_username))
+        ON CONFLICT DO NOTHING
+        "#,
+        task,
+        uuid
+    ).execute(uow.conn()?).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      let attached = sqlx::query_scalar!(
+          r#"
+          SELECT EXISTS(SELECT 1 FROM task_labels WHERE task_uuid = $1 AND label_uuid = $2) AS "exists!"
+          "#,
+          task,
+          uuid
+      ).fetch_one(uow.conn()?).await.map_err(DBError::from)?;
+      if attached {
+        return Err(DBError::Conflict(format!("Task {task_uuid} already has label {}", label.name)));
+      }
+      return Err(DBError::Validation(format!("Label {} cannot be used on task {task_uuid}", label.name)));
+    }
+    Ok(label)
+  }
+
+  async fn detach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError> {
+    let task = parse_uuid(task_uuid)?;
+    let uuid = parse_uuid(label_uuid)?;
+    Self::require_task_editor(uow.conn()?, task, &user).await?;
+    let label = Self::get_label(uow.conn()?, uuid, &user).await?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM task_labels WHERE task_uuid = $1 AND label_uuid = $2
+        "#,
+        task,
+        uuid
+    ).execute(uow.conn()?).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(DBError::NotFound(format!("Task {task_uuid} does not have label {}", label.name)));
+    }
+    Ok(label)
+  }
+}
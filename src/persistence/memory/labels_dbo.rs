@@ -0,0 +1,179 @@
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, Label, LabelDetail, LabelUpdateReq};
+use crate::persistence::labels_dbo::{check_color, check_name, LabelsDbo};
+use crate::persistence::tasks_dbo::parse_uuid;
+use crate::persistence::unit_of_work::UnitOfWork;
+use super::{can_edit, LabelRecord, MemoryData, MemoryStore};
+
+pub struct MemoryLabelsDbo {
+  store: MemoryStore,
+}
+
+impl MemoryLabelsDbo {
+  pub fn new(store: MemoryStore) -> Self {
+    Self {
+      store,
+    }
+  }
+}
+
+impl MemoryData {
+  /// Label visible to `user`.
+  fn visible_label(&self, label_uuid: Uuid, user: &str) -> Result<&LabelRecord, DBError> {
+    self.labels.iter()
+      .find(|l| {
+        l.label_uuid == label_uuid
+          && (l.user_username.as_deref() == Some(user) || l.project_uuid.is_some_and(|p| self.project_role(p, user).is_some()))
+      })
+      .ok_or_else(|| DBError::NotFound(format!("Label {label_uuid} not found")))
+  }
+
+  /// Index of a label `user` may change.
+  fn manageable_label(&self, label_uuid: Uuid, user: &str) -> Result<usize, DBError> {
+    let index = self.labels.iter().position(|l| {
+      l.label_uuid == label_uuid
+        && (l.user_username.as_deref() == Some(user) || l.project_uuid.is_some_and(|p| can_edit(self.project_role(p, user))))
+    });
+    match index {
+      Some(index) => Ok(index),
+      None => {
+        self.visible_label(label_uuid, user)?;
+        Err(DBError::Forbidden("Only project owners and editors can manage labels".to_string()))
+      }
+    }
+  }
+
+  /// Stands in for the unique name indexes of `labels`.
+  fn check_unique_name(&self, label: &LabelRecord) -> Result<(), DBError> {
+    let taken = self.labels.iter().any(|l| {
+      l.label_uuid != label.label_uuid
+        && l.user_username == label.user_username
+        && l.project_uuid == label.project_uuid
+        && l.name.to_lowercase() == label.name.to_lowercase()
+    });
+    if taken {
+      return Err(DBError::Conflict(format!("Label {} already exists", label.name)));
+    }
+    Ok(())
+  }
+
+  /// Checks that `user` may change the labels of the task.
+  fn require_task_editor(&self, task_uuid: Uuid, user: &str) -> Result<(), DBError> {
+    match self.task(task_uuid).and_then(|task| self.task_role(task, user)) {
+      Some(role) if can_edit(Some(role)) => Ok(()),
+      Some(_) => Err(DBError::Forbidden("Viewers cannot modify tasks".to_string())),
+      None => Err(DBError::NotFound(format!("Task {task_uuid} not found"))),
+    }
+  }
+}
+
+#[async_trait]
+impl LabelsDbo for MemoryLabelsDbo {
+  async fn create_label(&self, label: Label, user: String) -> Result<LabelDetail, DBError> {
+    let name = check_name(&label.name)?;
+    let color = label.color.as_deref().map(check_color).transpose()?;
+    let project_uuid = label.project_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let mut data = self.store.lock().await;
+    if let Some(project) = project_uuid {
+      match data.project_role(project, &user) {
+        role if can_edit(role) => {}
+        Some(_) => return Err(DBError::Forbidden("Only project owners and editors can manage labels".to_string())),
+        None => return Err(DBError::NotFound(format!("Project {} not found", label.project_uuid.unwrap_or_default()))),
+      }
+    }
+
+    let record = LabelRecord {
+      label_uuid: Uuid::new_v4(),
+      name,
+      color,
+      user_username: project_uuid.is_none().then_some(user),
+      project_uuid,
+    };
+    data.check_unique_name(&record)?;
+    let detail = record.detail();
+    data.labels.push(record);
+    Ok(detail)
+  }
+
+  async fn list_labels(&self, project_uuid: Option<String>, user: String) -> Result<Vec<LabelDetail>, DBError> {
+    let project_uuid = project_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let data = self.store.lock().await;
+    let mut labels: Vec<&LabelRecord> = data.labels.iter()
+      .filter(|l| match project_uuid {
+        None => l.user_username.as_deref() == Some(user.as_str()),
+        Some(project) => l.project_uuid == Some(project) && data.project_role(project, &user).is_some(),
+      })
+      .collect();
+    labels.sort_by_key(|l| l.name.to_lowercase());
+
+    Ok(labels.into_iter().map(LabelRecord::detail).collect())
+  }
+
+  async fn update_label(&self, label_uuid: &str, label: LabelUpdateReq, user: String) -> Result<LabelDetail, DBError> {
+    let uuid = parse_uuid(label_uuid)?;
+    let name = label.name.as_deref().map(check_name).transpose()?;
+    let color = label.color.as_deref().map(check_color).transpose()?;
+
+    let mut data = self.store.lock().await;
+    let index = data.manageable_label(uuid, &user)?;
+    let mut record = data.labels[index].clone();
+    record.name = name.unwrap_or(record.name);
+    record.color = color.or(record.color);
+    data.check_unique_name(&record)?;
+
+    let detail = record.detail();
+    data.labels[index] = record;
+    Ok(detail)
+  }
+
+  async fn delete_label(&self, label_uuid: &str, user: String) -> Result<(), DBError> {
+    let uuid = parse_uuid(label_uuid)?;
+
+    let mut data = self.store.lock().await;
+    let index = data.manageable_label(uuid, &user)?;
+    data.labels.remove(index);
+    data.task_labels.retain(|(_, label)| *label != uuid);
+    Ok(())
+  }
+
+  async fn attach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError> {
+    let task = parse_uuid(task_uuid)?;
+    let uuid = parse_uuid(label_uuid)?;
+    let data = uow.memory()?;
+    data.require_task_editor(task, &user)?;
+    let label = data.visible_label(uuid, &user)?;
+
+    let usable = data.task(task).is_some_and(|t| match t.project_uuid {
+      Some(project) => label.project_uuid == Some(project),
+      None => label.user_username.is_some() && label.user_username == t.user_username,
+    });
+    let label = label.detail();
+    if data.task_labels.contains(&(task, uuid)) {
+      return Err(DBError::Conflict(format!("Task {task_uuid} already has label {}", label.name)));
+    }
+    if !usable {
+      return Err(DBError::Validation(format!("Label {} cannot be used on task {task_uuid}", label.name)));
+    }
+
+    data.task_labels.push((task, uuid));
+    Ok(label)
+  }
+
+  async fn detach_label(&self, uow: &mut UnitOfWork, task_uuid: &str, label_uuid: &str, user: String) -> Result<LabelDetail, DBError> {
+    let task = parse_uuid(task_uuid)?;
+    let uuid = parse_uuid(label_uuid)?;
+    let data = uow.memory()?;
+    data.require_task_editor(task, &user)?;
+    let label = data.visible_label(uuid, &user)?.detail();
+
+    let before = data.task_labels.len();
+    data.task_labels.retain(|pair| *pair != (task, uuid));
+    if data.task_labels.len() == before {
+      return Err(DBError::NotFound(format!("Task {task_uuid} does not have label {}", label.name)));
+    }
+    Ok(label)
+  }
+}
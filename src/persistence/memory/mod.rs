@@ -0,0 +1,274 @@
+//! DBOs keeping their data in memory instead of Postgres, used with `STORAGE=memory` and in tests.
+//! They follow the rules of the SQL queries and functions they stand in for (visibility, roles,
+//! workflows, trash, versions) and report the same errors. Nothing survives a restart.
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use sqlx::types::Uuid;
+use sqlx::types::time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
+use crate::models::{
+  FieldChange, LabelDetail, ProjectRole, StatusCategory, TrackingEventType, WorkflowDetail, WorkflowStatus, WorkflowTransition
+};
+use crate::persistence::tasks_dbo::TaskRow;
+
+pub mod tasks_dbo;
+pub mod users_dbo;
+pub mod tracking_dbo;
+pub mod tokens_dbo;
+pub mod projects_dbo;
+pub mod labels_dbo;
+pub mod workflows_dbo;
+
+/// Tables shared by the in-memory DBOs. Clones share the same data.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+  data: Arc<Mutex<MemoryData>>,
+}
+
+impl MemoryStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub(crate) async fn lock(&self) -> MutexGuard<'_, MemoryData> {
+    self.data.lock().await
+  }
+
+  /// Locks the data until the returned transaction ends, like a serializable transaction would.
+  pub(crate) async fn begin(&self) -> MemoryTransaction {
+    let data = self.data.clone().lock_owned().await;
+    let snapshot = Some(data.clone());
+    MemoryTransaction {
+      data,
+      snapshot,
+    }
+  }
+}
+
+/// Writes of a unit of work on the in-memory store. Dropping it without `commit` puts back the data
+/// as it was when it began.
+pub(crate) struct MemoryTransaction {
+  data: OwnedMutexGuard<MemoryData>,
+  snapshot: Option<MemoryData>,
+}
+
+impl MemoryTransaction {
+  pub(crate) fn data(&mut self) -> &mut MemoryData {
+    &mut self.data
+  }
+
+  pub(crate) fn commit(mut self) {
+    self.snapshot = None;
+  }
+}
+
+impl Drop for MemoryTransaction {
+  fn drop(&mut self) {
+    if let Some(snapshot) = self.snapshot.take() {
+      *self.data = snapshot;
+    }
+  }
+}
+
+#[derive(Clone)]
+struct UserRecord {
+  username: String,
+  email: String,
+  password: String,
+  created_at: PrimitiveDateTime,
+}
+
+#[derive(Clone)]
+struct RefreshTokenRecord {
+  user_username: String,
+  expires_at: OffsetDateTime,
+  revoked_at: Option<OffsetDateTime>,
+}
+
+#[derive(Clone)]
+struct ProjectRecord {
+  project_uuid: Uuid,
+  name: String,
+  created_by: String,
+  created_at: PrimitiveDateTime,
+}
+
+#[derive(Clone)]
+struct MemberRecord {
+  project_uuid: Uuid,
+  user_username: String,
+  role: ProjectRole,
+  created_at: PrimitiveDateTime,
+}
+
+#[derive(Clone)]
+struct LabelRecord {
+  label_uuid: Uuid,
+  name: String,
+  color: Option<String>,
+  user_username: Option<String>,
+  project_uuid: Option<Uuid>,
+}
+
+#[derive(Clone)]
+struct WorkflowRecord {
+  workflow_uuid: Uuid,
+  user_username: Option<String>,
+  project_uuid: Option<Uuid>,
+  /// In board order.
+  statuses: Vec<WorkflowStatus>,
+  transitions: Vec<WorkflowTransition>,
+}
+
+#[derive(Clone)]
+struct DependencyRecord {
+  task_uuid: Uuid,
+  blocker_uuid: Uuid,
+}
+
+#[derive(Clone)]
+struct TrackingRecord {
+  id: Uuid,
+  task_uuid: Uuid,
+  event_type: TrackingEventType,
+  actor: Option<String>,
+  changes: Vec<FieldChange>,
+  message: Option<String>,
+  created_at: PrimitiveDateTime,
+}
+
+/// Rows of every table, kept in insertion order.
+#[derive(Clone, Default)]
+pub(crate) struct MemoryData {
+  users: Vec<UserRecord>,
+  refresh_tokens: HashMap<String, RefreshTokenRecord>,
+  /// Expiry of revoked access tokens by `jti`.
+  revoked_tokens: HashMap<String, OffsetDateTime>,
+  projects: Vec<ProjectRecord>,
+  project_members: Vec<MemberRecord>,
+  labels: Vec<LabelRecord>,
+  /// `(task_uuid, label_uuid)` pairs.
+  task_labels: Vec<(Uuid, Uuid)>,
+  workflows: Vec<WorkflowRecord>,
+  /// Stored with `blocked`, `labels` and `status_category` left empty; they are computed on read.
+  tasks: Vec<TaskRow>,
+  dependencies: Vec<DependencyRecord>,
+  tracking: Vec<TrackingRecord>,
+  /// Reminders already sent: task, due date, kind and offset.
+  notifications: HashSet<(Uuid, OffsetDateTime, &'static str, i32)>,
+}
+
+/// The current time at the precision Postgres stores.
+fn now() -> OffsetDateTime {
+  let now = OffsetDateTime::now_utc();
+  now.replace_nanosecond(now.nanosecond() / 1000 * 1000).unwrap_or(now)
+}
+
+/// The current time as a `TIMESTAMP` column gets it from `CURRENT_TIMESTAMP`.
+fn timestamp() -> PrimitiveDateTime {
+  let now = now();
+  PrimitiveDateTime::new(now.date(), now.time())
+}
+
+/// Like the `IN ('owner', 'editor')` checks of the queries.
+fn can_edit(role: Option<ProjectRole>) -> bool {
+  matches!(role, Some(ProjectRole::Owner | ProjectRole::Editor))
+}
+
+/// Counterparts of the SQL functions of the migrations.
+impl MemoryData {
+  fn user_exists(&self, username: &str) -> bool {
+    self.users.iter().any(|u| u.username == username)
+  }
+
+  fn project_role(&self, project_uuid: Uuid, user: &str) -> Option<ProjectRole> {
+    self.project_members.iter()
+      .find(|m| m.project_uuid == project_uuid && m.user_username == user)
+      .map(|m| m.role)
+  }
+
+  fn task(&self, task_uuid: Uuid) -> Option<&TaskRow> {
+    self.tasks.iter().find(|t| t.task_uuid == task_uuid)
+  }
+
+  /// Role on a task whether or not it is in the trash.
+  fn task_member_role(&self, task: &TaskRow, user: &str) -> Option<ProjectRole> {
+    match task.project_uuid {
+      None if task.user_username.as_deref() == Some(user) => Some(ProjectRole::Owner),
+      None if task.assignee.as_deref() == Some(user) => Some(ProjectRole::Editor),
+      None => None,
+      Some(project_uuid) => self.project_role(project_uuid, user),
+    }
+  }
+
+  /// Role on a task, `None` for trashed tasks.
+  fn task_role(&self, task: &TaskRow, user: &str) -> Option<ProjectRole> {
+    task.deleted_at.is_none().then(|| self.task_member_role(task, user)).flatten()
+  }
+
+  fn workflow_of(&self, project_uuid: Option<Uuid>, owner: &str) -> Option<&WorkflowRecord> {
+    self.workflows.iter().find(|w| match project_uuid {
+      None => w.user_username.as_deref() == Some(owner),
+      Some(project_uuid) => w.project_uuid == Some(project_uuid),
+    })
+  }
+
+  fn status_category(&self, project_uuid: Option<Uuid>, owner: &str, status: &str) -> Option<StatusCategory> {
+    let statuses = match self.workflow_of(project_uuid, owner) {
+      Some(workflow) => workflow.statuses.clone(),
+      None => WorkflowDetail::builtin(None).statuses,
+    };
+    statuses.into_iter().find(|s| s.key.key() == status).map(|s| s.category)
+  }
+
+  fn workflow_allows(&self, project_uuid: Option<Uuid>, owner: &str, from: Option<&str>, to: &str) -> bool {
+    if from == Some(to) {
+      return true;
+    }
+    if self.status_category(project_uuid, owner, to).is_none() {
+      return false;
+    }
+    match (from, self.workflow_of(project_uuid, owner)) {
+      (Some(from), Some(workflow)) if !workflow.transitions.is_empty() => {
+        workflow.transitions.iter().any(|t| t.from.key() == from && t.to.key() == to)
+      }
+      _ => true,
+    }
+  }
+
+  fn initial_status(&self, project_uuid: Option<Uuid>, owner: &str) -> String {
+    self.workflow_of(project_uuid, owner)
+      .and_then(|w| w.statuses.iter().find(|s| s.category == StatusCategory::Todo))
+      .map_or_else(|| "todo".to_string(), |s| s.key.key().to_string())
+  }
+
+  fn task_blocked(&self, task_uuid: Uuid) -> bool {
+    self.dependencies.iter()
+      .filter(|d| d.task_uuid == task_uuid)
+      .filter_map(|d| self.task(d.blocker_uuid))
+      .any(|b| {
+        let owner = b.user_username.as_deref().unwrap_or_default();
+        b.deleted_at.is_none() && self.status_category(b.project_uuid, owner, &b.status) != Some(StatusCategory::Done)
+      })
+  }
+
+  fn task_label_list(&self, task_uuid: Uuid) -> Vec<LabelDetail> {
+    let mut labels: Vec<&LabelRecord> = self.task_labels.iter()
+      .filter(|(task, _)| *task == task_uuid)
+      .filter_map(|(_, label)| self.labels.iter().find(|l| l.label_uuid == *label))
+      .collect();
+    labels.sort_by_key(|l| l.name.to_lowercase());
+    labels.into_iter().map(LabelRecord::detail).collect()
+  }
+}
+
+impl LabelRecord {
+  fn detail(&self) -> LabelDetail {
+    LabelDetail {
+      label_uuid: self.label_uuid.to_string(),
+      name: self.name.clone(),
+      color: self.color.clone(),
+      project_uuid: self.project_uuid.map(|uuid| uuid.to_string()),
+    }
+  }
+}
@@ -0,0 +1,177 @@
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, Project, ProjectDetail, ProjectMember, ProjectRole};
+use crate::persistence::projects_dbo::ProjectsDbo;
+use crate::persistence::tasks_dbo::parse_uuid;
+use super::{timestamp, MemberRecord, MemoryData, MemoryStore, ProjectRecord};
+
+pub struct MemoryProjectsDbo {
+  store: MemoryStore,
+}
+
+impl MemoryProjectsDbo {
+  pub fn new(store: MemoryStore) -> Self {
+    Self {
+      store,
+    }
+  }
+}
+
+impl MemoryData {
+  fn require_role(&self, project_uuid: &str, user: &str) -> Result<ProjectRole, DBError> {
+    self.project_role(parse_uuid(project_uuid)?, user)
+      .ok_or_else(|| DBError::NotFound(format!("Project {project_uuid} not found")))
+  }
+
+  fn require_owner(&self, project_uuid: &str, user: &str) -> Result<(), DBError> {
+    match self.require_role(project_uuid, user)? {
+      ProjectRole::Owner => Ok(()),
+      _ => Err(DBError::Forbidden("Only project owners can manage members".to_string())),
+    }
+  }
+
+  /// Fails when `member` is the last owner, so a project always keeps someone who can manage it.
+  fn ensure_other_owner(&self, project_uuid: Uuid, member: &str) -> Result<(), DBError> {
+    let other_owner = self.project_members.iter().any(|m| {
+      m.project_uuid == project_uuid && m.role == ProjectRole::Owner && m.user_username != member
+    });
+    if !other_owner {
+      return Err(DBError::Conflict("A project needs at least one owner".to_string()));
+    }
+    Ok(())
+  }
+}
+
+impl MemberRecord {
+  fn detail(&self) -> ProjectMember {
+    ProjectMember {
+      username: self.user_username.clone(),
+      role: self.role,
+      created_at: self.created_at.to_string(),
+    }
+  }
+}
+
+#[async_trait]
+impl ProjectsDbo for MemoryProjectsDbo {
+  async fn create_project(&self, project: Project, user: String) -> Result<ProjectDetail, DBError> {
+    let mut data = self.store.lock().await;
+    if !data.user_exists(&user) {
+      return Err(DBError::Other(format!("User {user} does not exist")));
+    }
+
+    let record = ProjectRecord {
+      project_uuid: Uuid::new_v4(),
+      name: project.name,
+      created_by: user.clone(),
+      created_at: timestamp(),
+    };
+    data.project_members.push(MemberRecord {
+      project_uuid: record.project_uuid,
+      user_username: user,
+      role: ProjectRole::Owner,
+      created_at: record.created_at,
+    });
+    data.projects.push(record.clone());
+
+    Ok(ProjectDetail {
+      project_uuid: record.project_uuid.to_string(),
+      name: record.name,
+      created_by: record.created_by,
+      created_at: record.created_at.to_string(),
+      role: ProjectRole::Owner,
+    })
+  }
+
+  async fn list_projects(&self, user: String) -> Result<Vec<ProjectDetail>, DBError> {
+    let data = self.store.lock().await;
+    let mut projects: Vec<(&ProjectRecord, ProjectRole)> = data.projects.iter()
+      .filter_map(|p| data.project_role(p.project_uuid, &user).map(|role| (p, role)))
+      .collect();
+    projects.sort_by_key(|(p, _)| p.created_at);
+
+    Ok(projects.into_iter().map(|(p, role)| {
+      ProjectDetail {
+        project_uuid: p.project_uuid.to_string(),
+        name: p.name.clone(),
+        created_by: p.created_by.clone(),
+        created_at: p.created_at.to_string(),
+        role,
+      }
+    }).collect())
+  }
+
+  async fn get_role(&self, project_uuid: &str, user: String) -> Result<Option<ProjectRole>, DBError> {
+    let uuid = parse_uuid(project_uuid)?;
+    Ok(self.store.lock().await.project_role(uuid, &user))
+  }
+
+  async fn list_members(&self, project_uuid: &str, user: String) -> Result<Vec<ProjectMember>, DBError> {
+    let data = self.store.lock().await;
+    data.require_role(project_uuid, &user)?;
+    let uuid = parse_uuid(project_uuid)?;
+
+    let mut members: Vec<&MemberRecord> = data.project_members.iter().filter(|m| m.project_uuid == uuid).collect();
+    members.sort_by_key(|m| m.created_at);
+    Ok(members.into_iter().map(MemberRecord::detail).collect())
+  }
+
+  async fn add_member(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError> {
+    let mut data = self.store.lock().await;
+    data.require_owner(project_uuid, &user)?;
+    let uuid = parse_uuid(project_uuid)?;
+
+    if !data.user_exists(&member) {
+      return Err(DBError::NotFound(format!("User {member} not found")));
+    }
+    if data.project_role(uuid, &member).is_some() {
+      return Err(DBError::Conflict(format!("User {member} is already a member of project {project_uuid}")));
+    }
+
+    let record = MemberRecord {
+      project_uuid: uuid,
+      user_username: member,
+      role,
+      created_at: timestamp(),
+    };
+    let detail = record.detail();
+    data.project_members.push(record);
+    Ok(detail)
+  }
+
+  async fn update_role(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError> {
+    let mut data = self.store.lock().await;
+    data.require_owner(project_uuid, &user)?;
+    let uuid = parse_uuid(project_uuid)?;
+
+    if role != ProjectRole::Owner {
+      data.ensure_other_owner(uuid, &member)?;
+    }
+
+    let record = data.project_members.iter_mut()
+      .find(|m| m.project_uuid == uuid && m.user_username == member)
+      .ok_or_else(|| DBError::NotFound(format!("User {member} is not a member of project {project_uuid}")))?;
+    record.role = role;
+    Ok(record.detail())
+  }
+
+  async fn remove_member(&self, project_uuid: &str, member: String, user: String) -> Result<(), DBError> {
+    let mut data = self.store.lock().await;
+    // Members may always leave; removing anyone else needs an owner.
+    if member == user {
+      data.require_role(project_uuid, &user)?;
+    } else {
+      data.require_owner(project_uuid, &user)?;
+    }
+    let uuid = parse_uuid(project_uuid)?;
+    data.ensure_other_owner(uuid, &member)?;
+
+    let before = data.project_members.len();
+    data.project_members.retain(|m| !(m.project_uuid == uuid && m.user_username == member));
+    if data.project_members.len() == before {
+      return Err(DBError::NotFound(format!("User {member} is not a member of project {project_uuid}")));
+    }
+
+    Ok(())
+  }
+}
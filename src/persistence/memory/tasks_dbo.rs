@@ -0,0 +1,1130 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use std::time::Duration;
+use sqlx::types::Uuid;
+use sqlx::types::time::PrimitiveDateTime;
+use time::macros::format_description;
+use async_trait::async_trait;
+use crate::models::{
+  Task, TaskStatus, StatusCategory, TaskPriority, TaskDetail, TaskUpdateReq, TaskListQuery, TaskPage, TaskSortField, SortDirection,
+  TaskSearchHit, TaskReminder, ReminderKind, TagMatch, ProjectRole, DBError
+};
+use crate::recurrence::Recurrence;
+use crate::persistence::tasks_dbo::{
+  canonical_recurrence, check_remind_offsets, format_timestamp, parse_due_at, parse_tags, parse_timestamp, parse_uuid,
+  TaskCursor, TaskRow, TasksDbo, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, POSITION_STEP
+};
+use crate::persistence::unit_of_work::UnitOfWork;
+use super::{can_edit, now, timestamp, DependencyRecord, MemoryData, MemoryStore, TrackingRecord};
+
+pub struct MemoryTasksDbo {
+  store: MemoryStore,
+}
+
+impl MemoryTasksDbo {
+  pub fn new(store: MemoryStore) -> Self {
+    Self {
+      store,
+    }
+  }
+}
+
+/// Creator of the task, whose workflow applies to personal tasks.
+fn owner(task: &TaskRow) -> &str {
+  task.user_username.as_deref().unwrap_or_default()
+}
+
+/// Board a task is shown on, like `task_board`: its project, or the personal tasks of its creator.
+/// Positions are only compared within one status column of a board.
+#[derive(PartialEq)]
+enum Board<'a> {
+  Project(Uuid),
+  Personal(&'a str),
+}
+
+impl Board<'_> {
+  fn new(project_uuid: Option<Uuid>, owner: &str) -> Board<'_> {
+    match project_uuid {
+      Some(project_uuid) => Board::Project(project_uuid),
+      None => Board::Personal(owner),
+    }
+  }
+
+  fn of(task: &TaskRow) -> Board<'_> {
+    Board::new(task.project_uuid, owner(task))
+  }
+}
+
+/// Value of the column a listing is sorted by.
+#[derive(PartialEq, PartialOrd)]
+enum SortKey {
+  Time(PrimitiveDateTime),
+  Text(String),
+  Rank(i32),
+  Position(f64),
+}
+
+impl SortKey {
+  fn of(task: &TaskRow, sort_by: TaskSortField) -> Result<SortKey, DBError> {
+    Ok(match sort_by {
+      TaskSortField::CreatedAt => SortKey::Time(task.created_at),
+      TaskSortField::Title => SortKey::Text(task.title.clone()),
+      TaskSortField::Status => SortKey::Text(task.status.clone()),
+      TaskSortField::Priority => SortKey::Rank(TaskPriority::from_str(&task.priority)?.rank()),
+      TaskSortField::Position => SortKey::Position(task.position),
+    })
+  }
+
+  /// Reads back the value `TaskCursor::from_row` wrote.
+  fn of_cursor(value: &str, sort_by: TaskSortField) -> Result<SortKey, DBError> {
+    let invalid = |e: &dyn std::fmt::Display| DBError::InvalidInput(format!("Invalid cursor: {e}"));
+    Ok(match sort_by {
+      TaskSortField::CreatedAt => SortKey::Time(PrimitiveDateTime::parse(
+        value,
+        format_description!("[year]-[month]-[day] [hour padding:none]:[minute]:[second].[subsecond]")
+      ).map_err(|e| invalid(&e))?),
+      TaskSortField::Title | TaskSortField::Status => SortKey::Text(value.to_string()),
+      TaskSortField::Priority => SortKey::Rank(value.parse().map_err(|e| invalid(&e))?),
+      TaskSortField::Position => SortKey::Position(value.parse().map_err(|e| invalid(&e))?),
+    })
+  }
+}
+
+/// Parsed form of the filters of a `TaskListQuery`.
+struct TaskFilter<'q> {
+  query: &'q TaskListQuery,
+  project_uuid: Option<Uuid>,
+  tags: Vec<String>,
+  created_after: Option<PrimitiveDateTime>,
+  created_before: Option<PrimitiveDateTime>,
+  q: Option<String>,
+}
+
+impl<'q> TaskFilter<'q> {
+  fn new(query: &'q TaskListQuery) -> Result<Self, DBError> {
+    Ok(Self {
+      query,
+      project_uuid: query.project_uuid.as_deref().map(parse_uuid).transpose()?,
+      tags: parse_tags(query.tags.as_deref()),
+      created_after: query.created_after.as_deref().map(parse_timestamp).transpose()?,
+      created_before: query.created_before.as_deref().map(parse_timestamp).transpose()?,
+      q: query.q.as_deref().filter(|q| !q.is_empty()).map(str::to_lowercase),
+    })
+  }
+
+  /// The `WHERE` clause of `push_task_filters`.
+  fn matches(&self, data: &MemoryData, task: &TaskRow, user: &str) -> bool {
+    let query = self.query;
+    let category = data.status_category(task.project_uuid, owner(task), &task.status);
+    let overdue = task.due_at.is_some_and(|due_at| due_at < now()) && category != Some(StatusCategory::Done);
+
+    data.task_role(task, user).is_some()
+      && self.project_uuid.is_none_or(|project_uuid| task.project_uuid == Some(project_uuid))
+      && query.assignee.as_ref().is_none_or(|assignee| task.assignee.as_ref() == Some(assignee))
+      && query.status.as_ref().is_none_or(|status| task.status == status.key())
+      && query.status_category.is_none_or(|status_category| category == Some(status_category))
+      && query.overdue.is_none_or(|wanted| overdue == wanted)
+      && query.blocked.is_none_or(|wanted| data.task_blocked(task.task_uuid) == wanted)
+      && self.matches_tags(data, task)
+      && self.created_after.is_none_or(|created_after| task.created_at >= created_after)
+      && self.created_before.is_none_or(|created_before| task.created_at < created_before)
+      && self.q.as_ref().is_none_or(|q| {
+        task.title.to_lowercase().contains(q) || task.description.to_lowercase().contains(q)
+      })
+  }
+
+  fn matches_tags(&self, data: &MemoryData, task: &TaskRow) -> bool {
+    if self.tags.is_empty() {
+      return true;
+    }
+    let mut matching: Vec<String> = data.task_label_list(task.task_uuid).into_iter()
+      .map(|label| label.name.to_lowercase())
+      .filter(|name| self.tags.contains(name))
+      .collect();
+    matching.sort();
+    matching.dedup();
+    match self.query.tags_mode {
+      TagMatch::Any => !matching.is_empty(),
+      TagMatch::All => matching.len() >= self.tags.len(),
+    }
+  }
+}
+
+/// Words of a search query, standing in for `websearch_to_tsquery`: every word must occur, except
+/// for words prefixed with `-`, which must not. Quotes and `or` are ignored.
+struct SearchTerms {
+  required: Vec<String>,
+  excluded: Vec<String>,
+}
+
+impl SearchTerms {
+  fn parse(q: &str) -> Self {
+    let mut terms = SearchTerms {
+      required: Vec::new(),
+      excluded: Vec::new(),
+    };
+    for token in q.split_whitespace().filter(|token| !token.eq_ignore_ascii_case("or")) {
+      let (words, token) = match token.strip_prefix('-') {
+        Some(token) => (&mut terms.excluded, token),
+        None => (&mut terms.required, token),
+      };
+      words.extend(token.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).map(str::to_lowercase));
+    }
+    terms
+  }
+
+  /// Number of occurrences of the required words, `None` when the text doesn't match.
+  fn rank(&self, text: &str) -> Option<usize> {
+    let text = text.to_lowercase();
+    if self.required.is_empty() && self.excluded.is_empty() {
+      return None;
+    }
+    if self.excluded.iter().any(|word| text.contains(word.as_str())) {
+      return None;
+    }
+    let counts: Vec<usize> = self.required.iter().map(|word| text.matches(word.as_str()).count()).collect();
+    (!counts.contains(&0)).then(|| counts.iter().sum())
+  }
+
+  /// The text with the required words wrapped in `<mark>`, like `ts_headline`.
+  fn highlight(&self, text: &str) -> String {
+    let lower = text.to_lowercase();
+    // Offsets into the lowercase text only fit the original when lowercasing kept the byte length.
+    if lower.len() != text.len() {
+      return text.to_string();
+    }
+
+    let mut ranges: Vec<(usize, usize)> = self.required.iter()
+      .flat_map(|word| lower.match_indices(word.as_str()).map(|(start, word)| (start, start + word.len())))
+      .collect();
+    ranges.sort();
+    let mut highlighted = String::with_capacity(text.len());
+    let mut end = 0;
+    for (start, stop) in ranges {
+      if start < end {
+        continue;
+      }
+      highlighted.push_str(&text[end..start]);
+      highlighted.push_str("<mark>");
+      highlighted.push_str(&text[start..stop]);
+      highlighted.push_str("</mark>");
+      end = stop;
+    }
+    highlighted.push_str(&text[end..]);
+    highlighted
+  }
+}
+
+impl TrackingRecord {
+  /// Searchable text of the entry, like `tracking_text`.
+  fn text(&self) -> String {
+    self.message.iter().cloned()
+      .chain(self.changes.iter().filter_map(|change| match &change.after {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(after) => Some(after.clone()),
+        after => Some(after.to_string()),
+      }))
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+}
+
+impl MemoryData {
+  /// The task with its computed columns.
+  fn task_detail(&self, task: &TaskRow) -> Result<TaskDetail, DBError> {
+    let mut row = task.clone();
+    row.blocked = Some(self.task_blocked(task.task_uuid));
+    row.status_category = self.status_category(task.project_uuid, owner(task), &task.status).map(|c| c.to_string());
+    let mut detail = row.into_detail()?;
+    detail.labels = self.task_label_list(task.task_uuid);
+    Ok(detail)
+  }
+
+  fn task_details(&self, tasks: Vec<&TaskRow>) -> Result<Vec<TaskDetail>, DBError> {
+    tasks.into_iter().map(|task| self.task_detail(task)).collect()
+  }
+
+  /// A task visible to `user`.
+  fn visible_task(&self, task_uuid: &str, user: &str) -> Result<&TaskRow, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    self.task(uuid)
+      .filter(|task| self.task_role(task, user).is_some())
+      .ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found")))
+  }
+
+  /// Role of `user` on the task, `None` when it doesn't exist or is invisible to them.
+  fn role_of(&self, task_uuid: Uuid, user: &str) -> Option<ProjectRole> {
+    self.task(task_uuid).and_then(|task| self.task_role(task, user))
+  }
+
+  /// Writes back a changed task. Like the `tasks_bump_version` trigger, only actual changes bump the version.
+  fn save_task(&mut self, mut task: TaskRow) {
+    if let Some(existing) = self.tasks.iter_mut().find(|t| t.task_uuid == task.task_uuid) {
+      if *existing != task {
+        task.version = existing.version + 1;
+        *existing = task;
+      }
+    }
+  }
+
+  /// The task and the subtasks below it reached through subtasks accepted by `follow`, parents first.
+  fn subtree(&self, root: Uuid, follow: impl Fn(&TaskRow) -> bool) -> Vec<Uuid> {
+    let mut uuids = vec![root];
+    let mut next = 0;
+    while next < uuids.len() {
+      let parent = uuids[next];
+      let children: Vec<Uuid> = self.tasks.iter()
+        .filter(|t| t.parent_task_uuid == Some(parent) && follow(t) && !uuids.contains(&t.task_uuid))
+        .map(|t| t.task_uuid)
+        .collect();
+      uuids.extend(children);
+      next += 1;
+    }
+    uuids
+  }
+
+  /// Tasks of the `status` column of `board`, leaving out the trash.
+  fn column<'a>(&'a self, board: &'a Board, status: &'a str) -> impl Iterator<Item = &'a TaskRow> + 'a {
+    self.tasks.iter().filter(move |t| t.deleted_at.is_none() && t.status == status && Board::of(t) == *board)
+  }
+
+  /// Position after the last task of the `status` column of `board`, leaving out `task_uuid`.
+  fn last_position(&self, board: &Board, status: &str, task_uuid: Option<Uuid>) -> f64 {
+    self.column(board, status)
+      .filter(|t| Some(t.task_uuid) != task_uuid)
+      .map(|t| t.position)
+      .reduce(f64::max)
+      .unwrap_or(0.0) + POSITION_STEP
+  }
+
+  /// Position for a new last subtask of `parent_uuid`.
+  fn next_subtask_position(&self, parent_uuid: Option<Uuid>) -> i32 {
+    self.tasks.iter()
+      .filter(|t| parent_uuid.is_some() && t.parent_task_uuid == parent_uuid)
+      .map(|t| t.subtask_position + 1)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Checks that `user` may add subtasks to the parent. Personal tasks only take subtasks from their
+  /// owner, so every subtask stays visible to whoever sees the parent.
+  fn check_parent(&self, parent_uuid: Uuid, user: &str) -> Result<TaskRow, DBError> {
+    let parent = self.visible_task(&parent_uuid.to_string(), user)
+      .map_err(|_| DBError::NotFound(format!("Parent task {parent_uuid} not found")))?;
+
+    let allowed = match self.role_of(parent_uuid, user) {
+      Some(ProjectRole::Owner) => true,
+      Some(ProjectRole::Editor) => parent.project_uuid.is_some(),
+      _ => false,
+    };
+    if !allowed {
+      return Err(DBError::Forbidden(format!("Not allowed to add subtasks to task {parent_uuid}")));
+    }
+    Ok(parent.clone())
+  }
+
+  /// Checks that the task is still at `version`.
+  fn check_version(&self, task_uuid: Uuid, version: Option<i32>, user: &str) -> Result<(), DBError> {
+    let Some(version) = version else {
+      return Ok(());
+    };
+    match self.task(task_uuid).filter(|task| self.task_role(task, user).is_some()) {
+      None => Err(DBError::NotFound(format!("Task {task_uuid} not found"))),
+      Some(task) if task.version != version => Err(DBError::VersionConflict(format!(
+        "Task {task_uuid} was changed by someone else, it is at version {} instead of {version}", task.version
+      ))),
+      Some(_) => Ok(()),
+    }
+  }
+
+  /// Explains why a write matched no task: it is missing/invisible, or the caller is only a viewer.
+  fn not_editable(&self, task_uuid: Uuid, user: &str) -> DBError {
+    match self.role_of(task_uuid, user) {
+      Some(_) => DBError::Forbidden("Viewers cannot modify tasks".to_string()),
+      None => DBError::NotFound(format!("Task {task_uuid} not found")),
+    }
+  }
+
+  /// Checks that the workflow of a task allows changing its status from `from` (`None` for new tasks) to `to`.
+  fn check_transition(&self, project_uuid: Option<Uuid>, owner: &str, from: Option<&str>, to: &TaskStatus) -> Result<(), DBError> {
+    let allowed = self.workflow_allows(project_uuid, owner, from, to.key());
+    let known = self.status_category(project_uuid, owner, to.key()).is_some();
+
+    match (allowed, known, from) {
+      (true, _, _) => Ok(()),
+      (false, false, _) | (false, _, None) => Err(DBError::Validation(format!("Status {to} is not part of the workflow"))),
+      (false, true, Some(from)) => Err(DBError::Conflict(format!("The workflow does not allow moving from {from} to {to}"))),
+    }
+  }
+
+  /// Whether the workflow and the blockers of the task let it change to `status`.
+  fn allows_status(&self, task: &TaskRow, status: &TaskStatus) -> bool {
+    let owner = owner(task);
+    self.workflow_allows(task.project_uuid, owner, Some(&task.status), status.key())
+      && (task.status == status.key()
+        || self.status_category(task.project_uuid, owner, status.key()) == Some(StatusCategory::Todo)
+        || !self.task_blocked(task.task_uuid))
+  }
+
+  /// Like `not_editable`, but also reports status changes the workflow rejects and unfinished
+  /// blockers keeping the task from being started.
+  fn not_updatable(&self, task_uuid: Uuid, status: &TaskStatus, user: &str) -> DBError {
+    match self.check_status_change(task_uuid, status, user) {
+      Err(e) => e,
+      Ok(()) => self.not_editable(task_uuid, user),
+    }
+  }
+
+  /// Like `not_updatable`, for a patch that may leave the status out or set an assignee.
+  fn not_patchable(&self, task_uuid: Uuid, status: Option<&TaskStatus>, assignee: Option<String>, user: &str) -> DBError {
+    if let Some(Err(e)) = status.map(|status| self.check_status_change(task_uuid, status, user)) {
+      return e;
+    }
+    match assignee {
+      // The caller may edit the task, so it is the assignee that was rejected.
+      Some(assignee) if can_edit(self.role_of(task_uuid, user)) => DBError::Validation(format!(
+        "{assignee} is not a user who can be assigned task {task_uuid}"
+      )),
+      _ => self.not_editable(task_uuid, user),
+    }
+  }
+
+  /// Whether `assignee` is a user who can be assigned the task: any user for personal tasks, a member for project tasks.
+  fn assignable(&self, task: &TaskRow, assignee: &str) -> bool {
+    self.user_exists(assignee)
+      && task.project_uuid.is_none_or(|project_uuid| self.project_role(project_uuid, assignee).is_some())
+  }
+
+  fn check_status_change(&self, task_uuid: Uuid, status: &TaskStatus, user: &str) -> Result<(), DBError> {
+    if !can_edit(self.role_of(task_uuid, user)) {
+      return Ok(());
+    }
+    let task = self.visible_task(&task_uuid.to_string(), user)?;
+    self.check_transition(task.project_uuid, owner(task), Some(&task.status), status)?;
+
+    let blockers: Vec<String> = self.dependencies.iter()
+      .filter(|d| d.task_uuid == task_uuid)
+      .filter(|d| self.task(d.blocker_uuid).is_some_and(|b| {
+        b.deleted_at.is_none() && self.status_category(b.project_uuid, owner(b), &b.status) != Some(StatusCategory::Done)
+      }))
+      .map(|d| d.blocker_uuid.to_string())
+      .collect();
+
+    if !blockers.is_empty() {
+      return Err(DBError::Conflict(format!(
+        "Task {task_uuid} is blocked by unfinished tasks: {}", blockers.join(", ")
+      )));
+    }
+    Ok(())
+  }
+
+  /// Position of a neighbour of a moved task, which must be visible and already in the target column
+  /// of the same board.
+  fn neighbour_position(&self, task_uuid: Uuid, moved: &TaskRow, status: &TaskStatus, user: &str) -> Result<f64, DBError> {
+    let task = self.task(task_uuid)
+      .filter(|task| self.task_role(task, user).is_some())
+      .ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found")))?;
+    if task.status != status.key() || Board::of(task) != Board::of(moved) {
+      return Err(DBError::Validation(format!("Task {task_uuid} is not in the {status} column")));
+    }
+    Ok(task.position)
+  }
+
+  /// Free position for the moved task in the `status` column of its board, right after `after` and/or
+  /// right before `before`, or last. `None` when there is no room left between the neighbours.
+  fn free_position(
+    &self,
+    moved: &TaskRow,
+    status: &TaskStatus,
+    after: Option<Uuid>,
+    before: Option<Uuid>,
+    user: &str,
+  ) -> Result<Option<f64>, DBError> {
+    let lower = after.map(|after| self.neighbour_position(after, moved, status, user)).transpose()?;
+    let upper = before.map(|before| self.neighbour_position(before, moved, status, user)).transpose()?;
+    let board = Board::of(moved);
+    let column = || self.column(&board, status.key()).filter(|t| t.task_uuid != moved.task_uuid).map(|t| t.position);
+
+    let (lower, upper) = match (lower, upper) {
+      (Some(lower), Some(upper)) => (lower, upper),
+      (Some(lower), None) => {
+        let next = column().filter(|position| *position > lower).reduce(f64::min);
+        (lower, next.unwrap_or(lower + 2.0 * POSITION_STEP))
+      }
+      (None, Some(upper)) => {
+        let previous = column().filter(|position| *position < upper).reduce(f64::max);
+        (previous.unwrap_or(upper - 2.0 * POSITION_STEP), upper)
+      }
+      (None, None) => return Ok(Some(self.last_position(&board, status.key(), Some(moved.task_uuid)))),
+    };
+
+    let position = lower + (upper - lower) / 2.0;
+    Ok((lower < position && position < upper).then_some(position))
+  }
+
+  /// Spreads the positions of a column of the moved task's board evenly again, once repeated moves used
+  /// up the room between two tasks. Other boards are left alone, so their versions don't change.
+  fn renumber_column(&mut self, moved: &TaskRow, status: &TaskStatus) {
+    let mut column: Vec<TaskRow> = self.column(&Board::of(moved), status.key()).cloned().collect();
+    column.sort_by(|a, b| {
+      a.position.total_cmp(&b.position)
+        .then(a.created_at.cmp(&b.created_at))
+        .then(a.task_uuid.cmp(&b.task_uuid))
+    });
+    for (index, mut task) in column.into_iter().enumerate() {
+      task.position = (index + 1) as f64 * POSITION_STEP;
+      self.save_task(task);
+    }
+  }
+
+  /// Trashed tasks of `user`, ordered like the trash listing.
+  fn sort_trash(&self, tasks: &mut [&TaskRow]) {
+    tasks.sort_by(|a, b| {
+      b.deleted_at.cmp(&a.deleted_at)
+        .then(a.subtask_position.cmp(&b.subtask_position))
+        .then(a.created_at.cmp(&b.created_at))
+    });
+  }
+
+  /// Writes `change` to every task of `uuids` and returns the task `root` and the others, by subtask position.
+  fn change_tasks(&mut self, root: Uuid, uuids: &[Uuid], change: impl Fn(&mut TaskRow)) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+    for uuid in uuids {
+      if let Some(mut task) = self.task(*uuid).cloned() {
+        change(&mut task);
+        self.save_task(task);
+      }
+    }
+    let task = self.task(root).ok_or_else(|| DBError::NotFound(format!("Task {root} not found")))?;
+    let mut subtasks: Vec<&TaskRow> = uuids.iter()
+      .filter(|uuid| **uuid != root)
+      .filter_map(|uuid| self.task(*uuid))
+      .collect();
+    subtasks.sort_by_key(|task| task.subtask_position);
+    Ok((self.task_detail(task)?, self.task_details(subtasks)?))
+  }
+}
+
+#[async_trait]
+impl TasksDbo for MemoryTasksDbo {
+  async fn begin(&self) -> Result<UnitOfWork, DBError> {
+    Ok(UnitOfWork::in_memory(self.store.begin().await))
+  }
+
+  async fn get_all_tasks(&self, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let data = self.store.lock().await;
+    let tasks = data.tasks.iter().filter(|task| data.task_role(task, &user).is_some()).collect();
+    data.task_details(tasks)
+  }
+
+  async fn list_tasks(&self, query: TaskListQuery, user: String) -> Result<TaskPage, DBError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+    let filter = TaskFilter::new(&query)?;
+    let cursor = match &query.cursor {
+      Some(cursor) => {
+        let cursor = TaskCursor::decode(cursor)?;
+        let cursor_uuid = Uuid::parse_str(&cursor.task_uuid).map_err(|e| {
+          DBError::InvalidInput(format!("Invalid cursor: {e}"))
+        })?;
+        Some((SortKey::of_cursor(&cursor.value, query.sort_by)?, cursor_uuid))
+      }
+      None => None,
+    };
+
+    let data = self.store.lock().await;
+    let mut rows = Vec::new();
+    for task in data.tasks.iter().filter(|task| filter.matches(&data, task, &user)) {
+      rows.push((SortKey::of(task, query.sort_by)?, task));
+    }
+    let total = rows.len() as i64;
+
+    let compare = |(a_key, a_uuid): (&SortKey, Uuid), (b_key, b_uuid): (&SortKey, Uuid)| {
+      let ordering = a_key.partial_cmp(b_key).unwrap_or(Ordering::Equal).then(a_uuid.cmp(&b_uuid));
+      match query.order {
+        SortDirection::Asc => ordering,
+        SortDirection::Desc => ordering.reverse(),
+      }
+    };
+    rows.sort_by(|(a_key, a), (b_key, b)| compare((a_key, a.task_uuid), (b_key, b.task_uuid)));
+    if let Some((cursor_key, cursor_uuid)) = &cursor {
+      rows.retain(|(key, task)| compare((key, task.task_uuid), (cursor_key, *cursor_uuid)) == Ordering::Greater);
+    }
+
+    let mut tasks: Vec<&TaskRow> = rows.into_iter().map(|(_, task)| task).take(limit + 1).collect();
+    let next_cursor = if tasks.len() > limit {
+      tasks.truncate(limit);
+      tasks.last().map(|task| TaskCursor::from_row(task, query.sort_by)?.encode()).transpose()?
+    } else {
+      None
+    };
+
+    Ok(TaskPage {
+      tasks: data.task_details(tasks)?,
+      next_cursor,
+      total,
+    })
+  }
+
+  async fn search_tasks(&self, q: &str, limit: Option<i64>, user: String) -> Result<Vec<TaskSearchHit>, DBError> {
+    if q.trim().is_empty() {
+      return Err(DBError::Validation("Search query must not be empty".to_string()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+    let terms = SearchTerms::parse(q);
+
+    let data = self.store.lock().await;
+    let mut hits = Vec::new();
+    for task in data.tasks.iter().filter(|task| data.task_role(task, &user).is_some()) {
+      let rank = terms.rank(&format!("{} {}", task.title, task.description));
+      let tracking = data.tracking.iter()
+        .filter(|r| r.task_uuid == task.task_uuid)
+        .filter_map(|r| terms.rank(&r.text()).map(|rank| (rank, r)))
+        .max_by_key(|(rank, _)| *rank);
+      if rank.is_none() && tracking.is_none() {
+        continue;
+      }
+
+      let rank = rank.unwrap_or_default() + tracking.map_or(0, |(rank, _)| rank);
+      hits.push((task.created_at, TaskSearchHit {
+        task: data.task_detail(task)?,
+        rank: rank as f32 / 10.0,
+        title_highlight: terms.highlight(&task.title),
+        description_highlight: terms.highlight(&task.description),
+        tracking_highlight: tracking.map(|(_, r)| terms.highlight(&r.text())),
+      }));
+    }
+
+    hits.sort_by(|(a_created, a), (b_created, b)| b.rank.total_cmp(&a.rank).then(b_created.cmp(a_created)));
+    Ok(hits.into_iter().take(limit).map(|(_, hit)| hit).collect())
+  }
+
+  async fn create_task(&self, uow: &mut UnitOfWork, task: Task, user: String) -> Result<TaskDetail, DBError> {
+    let project_uuid = task.project_uuid.as_deref().map(parse_uuid).transpose()?;
+    let parent_uuid = task.parent_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    let due_at = task.due_at.as_deref().map(parse_due_at).transpose()?;
+    check_remind_offsets(&task.remind_offsets)?;
+    let recurrence = task.recurrence.as_deref().map(canonical_recurrence).transpose()?;
+
+    let data = uow.memory()?;
+    let parent = parent_uuid.map(|parent_uuid| data.check_parent(parent_uuid, &user)).transpose()?;
+    let scope = parent.map_or(project_uuid, |parent| parent.project_uuid);
+    data.check_transition(scope, &user, None, &task.status)?;
+
+    // Project tasks can only be created by owners and editors of the project. Subtasks take the
+    // project of their parent and go last among its subtasks. New tasks go last in their column.
+    if scope.is_some_and(|project_uuid| !can_edit(data.project_role(project_uuid, &user))) {
+      return Err(DBError::Forbidden("Only project owners and editors can add tasks".to_string()));
+    }
+
+    let position = data.last_position(&Board::new(scope, &user), task.status.key(), None);
+    let row = TaskRow {
+      task_uuid: Uuid::new_v4(),
+      title: task.title,
+      description: task.description,
+      status: task.status.to_string(),
+      priority: task.priority.to_string(),
+      position,
+      created_at: timestamp(),
+      user_username: Some(user),
+      project_uuid: scope,
+      assignee: None,
+      due_at,
+      remind_offsets: task.remind_offsets,
+      recurrence,
+      series_uuid: None,
+      occurrence: 1,
+      parent_task_uuid: parent_uuid,
+      subtask_position: data.next_subtask_position(parent_uuid),
+      blocked: None,
+      labels: None,
+      status_category: None,
+      deleted_at: None,
+      version: 1,
+    };
+    data.tasks.push(row.clone());
+    data.task_detail(&row)
+  }
+
+  async fn get_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let data = self.store.lock().await;
+    data.task_detail(data.visible_task(task_uuid, &user)?)
+  }
+
+  async fn update_task(&self, uow: &mut UnitOfWork, task: TaskUpdateReq, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(&task.task_uuid)?;
+    let due_at = task.due_at.map_value(|due_at| parse_due_at(&due_at)).transpose()?;
+    if let Some(remind_offsets) = &task.remind_offsets {
+      check_remind_offsets(remind_offsets)?;
+    }
+    let recurrence = task.recurrence.map_value(|rule| canonical_recurrence(&rule)).transpose()?;
+    let assignee: Option<Option<String>> = task.assignee.into();
+
+    let data = uow.memory()?;
+    data.check_version(uuid, version, &user)?;
+    let previous = data.task_detail(data.visible_task(&task.task_uuid, &user)?)?;
+    let current = match data.task(uuid) {
+      Some(current) if can_edit(data.task_role(current, &user))
+        && task.status.as_ref().is_none_or(|status| data.allows_status(current, status))
+        && assignee.as_ref().and_then(Option::as_deref).is_none_or(|assignee| data.assignable(current, assignee)) => current.clone(),
+      _ => return Err(data.not_patchable(uuid, task.status.as_ref(), assignee.flatten(), &user)),
+    };
+
+    let mut updated = current.clone();
+    if let Some(title) = task.title {
+      updated.title = title;
+    }
+    if let Some(description) = task.description {
+      updated.description = description;
+    }
+    if let Some(status) = task.status.filter(|status| current.status != status.key()) {
+      updated.position = data.last_position(&Board::of(&current), status.key(), None);
+      updated.status = status.to_string();
+    }
+    if let Some(priority) = task.priority {
+      updated.priority = priority.to_string();
+    }
+    due_at.update_to(&mut updated.due_at);
+    if let Some(remind_offsets) = task.remind_offsets {
+      updated.remind_offsets = remind_offsets;
+    }
+    recurrence.update_to(&mut updated.recurrence);
+    if let Some(assignee) = assignee {
+      updated.assignee = assignee;
+    }
+    data.save_task(updated);
+    Ok((previous, data.task_detail(data.visible_task(&task.task_uuid, &user)?)?))
+  }
+
+  async fn update_task_status(&self, uow: &mut UnitOfWork, task_status: TaskStatus, task_uuid: String, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(&task_uuid)?;
+
+    let data = uow.memory()?;
+    data.check_version(uuid, version, &user)?;
+    let previous = data.task_detail(data.visible_task(&task_uuid, &user)?)?;
+    let mut task = match data.task(uuid) {
+      Some(task) if can_edit(data.task_role(task, &user)) && data.allows_status(task, &task_status) => task.clone(),
+      _ => return Err(data.not_updatable(uuid, &task_status, &user)),
+    };
+
+    if task.status != task_status.key() {
+      task.position = data.last_position(&Board::of(&task), task_status.key(), None);
+      task.status = task_status.to_string();
+    }
+    data.save_task(task);
+    Ok((previous, data.task_detail(data.visible_task(&task_uuid, &user)?)?))
+  }
+
+  async fn delete_task(&self, uow: &mut UnitOfWork, task_uuid: String, cascade: bool, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+    let uuid = parse_uuid(&task_uuid)?;
+    let data = uow.memory()?;
+
+    if !cascade {
+      data.visible_task(&task_uuid, &user)?;
+      let subtasks = data.tasks.iter().filter(|t| t.parent_task_uuid == Some(uuid) && t.deleted_at.is_none()).count();
+      if subtasks > 0 {
+        return Err(DBError::Conflict(format!(
+          "Task {task_uuid} has {subtasks} subtasks, delete them first or pass cascade=true"
+        )));
+      }
+    }
+
+    if !can_edit(data.role_of(uuid, &user)) {
+      return Err(data.not_editable(uuid, &user));
+    }
+
+    // The subtasks share the deletion time of the task, which is how restoring finds them again.
+    let deleted_at = now();
+    let target = data.subtree(uuid, |t| t.deleted_at.is_none());
+    data.change_tasks(uuid, &target, |task| task.deleted_at = Some(deleted_at))
+  }
+
+  async fn list_trash(&self, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let data = self.store.lock().await;
+    let mut tasks: Vec<&TaskRow> = data.tasks.iter()
+      .filter(|task| task.deleted_at.is_some() && data.task_member_role(task, &user).is_some())
+      .collect();
+    data.sort_trash(&mut tasks);
+    data.task_details(tasks)
+  }
+
+  async fn get_trashed_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+
+    let data = self.store.lock().await;
+    let task = data.task(uuid)
+      .filter(|task| task.deleted_at.is_some() && data.task_member_role(task, &user).is_some())
+      .ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found in the trash")))?;
+    data.task_detail(task)
+  }
+
+  async fn restore_task(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let data = uow.memory()?;
+
+    let (task, role) = match data.task(uuid).filter(|task| task.deleted_at.is_some()) {
+      Some(task) => match data.task_member_role(task, &user) {
+        Some(role) => (task, role),
+        None => return Err(DBError::NotFound(format!("Task {task_uuid} not found in the trash"))),
+      },
+      None => return Err(DBError::NotFound(format!("Task {task_uuid} not found in the trash"))),
+    };
+    if !can_edit(Some(role)) {
+      return Err(DBError::Forbidden("Viewers cannot modify tasks".to_string()));
+    }
+    let trashed_parent = task.parent_task_uuid
+      .and_then(|parent_uuid| data.task(parent_uuid))
+      .filter(|parent| parent.deleted_at.is_some());
+    if let Some(parent) = trashed_parent {
+      return Err(DBError::Conflict(format!(
+        "Task {task_uuid} is a subtask of task {}, which is in the trash too, restore that one first", parent.task_uuid
+      )));
+    }
+
+    // Subtasks deleted on their own before the task stay in the trash.
+    let deleted_at = task.deleted_at;
+    let target = data.subtree(uuid, |t| t.deleted_at == deleted_at);
+    data.change_tasks(uuid, &target, |task| task.deleted_at = None)
+  }
+
+  async fn purge_trash(&self, retention: Duration) -> Result<u64, DBError> {
+    let mut data = self.store.lock().await;
+    // A retention reaching before the earliest date leaves nothing old enough to purge.
+    let Some(cutoff) = time::Duration::try_from(retention).ok().and_then(|retention| now().checked_sub(retention)) else {
+      return Ok(0);
+    };
+
+    // Subtasks go with their parent even when they were deleted later, they could not be restored anymore.
+    let mut target: Vec<Uuid> = Vec::new();
+    let expired: Vec<Uuid> = data.tasks.iter()
+      .filter(|task| task.deleted_at.is_some_and(|deleted_at| deleted_at < cutoff))
+      .map(|task| task.task_uuid)
+      .collect();
+    for uuid in expired {
+      for uuid in data.subtree(uuid, |_| true) {
+        if !target.contains(&uuid) {
+          target.push(uuid);
+        }
+      }
+    }
+
+    data.tracking.retain(|r| !target.contains(&r.task_uuid));
+    data.task_labels.retain(|(task, _)| !target.contains(task));
+    data.dependencies.retain(|d| !target.contains(&d.task_uuid) && !target.contains(&d.blocker_uuid));
+    data.notifications.retain(|(task, ..)| !target.contains(task));
+    data.tasks.retain(|task| !target.contains(&task.task_uuid));
+    Ok(target.len() as u64)
+  }
+
+  async fn get_subtree(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let data = self.store.lock().await;
+    let root = data.visible_task(task_uuid, &user)?;
+
+    let mut subtree = Vec::new();
+    let mut level = vec![root.task_uuid];
+    while !level.is_empty() {
+      let mut children: Vec<&TaskRow> = data.tasks.iter()
+        .filter(|t| t.parent_task_uuid.is_some_and(|parent| level.contains(&parent)) && t.deleted_at.is_none())
+        .collect();
+      children.sort_by_key(|t| (t.subtask_position, t.created_at));
+      level = children.iter().map(|t| t.task_uuid).collect();
+      subtree.extend(children);
+    }
+    data.task_details(subtree)
+  }
+
+  async fn move_to_parent(&self, uow: &mut UnitOfWork, task_uuid: &str, parent_task_uuid: Option<String>, position: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let parent_uuid = parent_task_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let data = uow.memory()?;
+    let task = data.visible_task(task_uuid, &user)?.clone();
+    if !can_edit(data.role_of(uuid, &user)) {
+      return Err(data.not_editable(uuid, &user));
+    }
+
+    let mut siblings = Vec::new();
+
+    if let Some(parent_uuid) = parent_uuid {
+      let parent = data.check_parent(parent_uuid, &user)?;
+      let same_owner = task.project_uuid.is_some() || parent.user_username == task.user_username;
+      if parent.project_uuid != task.project_uuid || !same_owner {
+        return Err(DBError::Validation("Subtasks must belong to the same project as their parent".to_string()));
+      }
+      if data.subtree(uuid, |_| true).contains(&parent_uuid) {
+        return Err(DBError::Validation("A task cannot be moved below itself or one of its subtasks".to_string()));
+      }
+
+      let mut children: Vec<&TaskRow> = data.tasks.iter()
+        .filter(|t| t.parent_task_uuid == Some(parent_uuid) && t.task_uuid != uuid && t.deleted_at.is_none())
+        .collect();
+      children.sort_by_key(|t| (t.subtask_position, t.created_at));
+      siblings = children.into_iter().map(|t| t.task_uuid).collect();
+    }
+
+    // Renumber the new siblings so positions stay contiguous.
+    let index = position.map_or(siblings.len(), |p| (p.max(0) as usize).min(siblings.len()));
+    siblings.insert(index, uuid);
+
+    let previous = data.task_detail(&task)?;
+    data.save_task(TaskRow {
+      parent_task_uuid: parent_uuid,
+      ..task
+    });
+    for (position, sibling) in siblings.into_iter().enumerate() {
+      if let Some(mut sibling) = data.task(sibling).cloned() {
+        sibling.subtask_position = position as i32;
+        data.save_task(sibling);
+      }
+    }
+
+    Ok((previous, data.task_detail(data.visible_task(task_uuid, &user)?)?))
+  }
+
+  async fn add_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let blocker = parse_uuid(blocker_uuid)?;
+    if uuid == blocker {
+      return Err(DBError::Validation("A task cannot be blocked by itself".to_string()));
+    }
+
+    let data = uow.memory()?;
+    data.visible_task(task_uuid, &user)?;
+    if !can_edit(data.role_of(uuid, &user)) {
+      return Err(data.not_editable(uuid, &user));
+    }
+    data.visible_task(blocker_uuid, &user)
+      .map_err(|_| DBError::NotFound(format!("Blocker task {blocker_uuid} not found")))?;
+
+    // Follow the blockers of the blocker; reaching the task means it would end up waiting on itself.
+    let mut chain = vec![blocker];
+    let mut next = 0;
+    while next < chain.len() {
+      let current = chain[next];
+      for d in data.dependencies.iter().filter(|d| d.task_uuid == current) {
+        if !chain.contains(&d.blocker_uuid) {
+          chain.push(d.blocker_uuid);
+        }
+      }
+      next += 1;
+    }
+    if chain[1..].contains(&uuid) {
+      return Err(DBError::Conflict(format!(
+        "Task {blocker_uuid} already depends on task {task_uuid}, the dependency would create a cycle"
+      )));
+    }
+
+    if !data.dependencies.iter().any(|d| d.task_uuid == uuid && d.blocker_uuid == blocker) {
+      data.dependencies.push(DependencyRecord {
+        task_uuid: uuid,
+        blocker_uuid: blocker,
+      });
+    }
+
+    data.task_detail(data.visible_task(task_uuid, &user)?)
+  }
+
+  async fn remove_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let blocker = parse_uuid(blocker_uuid)?;
+
+    let data = uow.memory()?;
+    let exists = data.dependencies.iter().any(|d| d.task_uuid == uuid && d.blocker_uuid == blocker);
+    if !exists || !can_edit(data.role_of(uuid, &user)) {
+      return match data.role_of(uuid, &user) {
+        role if can_edit(role) => Err(DBError::NotFound(format!(
+          "Task {task_uuid} is not blocked by task {blocker_uuid}"
+        ))),
+        _ => Err(data.not_editable(uuid, &user)),
+      };
+    }
+    data.dependencies.retain(|d| !(d.task_uuid == uuid && d.blocker_uuid == blocker));
+
+    data.task_detail(data.visible_task(task_uuid, &user)?)
+  }
+
+  async fn get_blockers(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let data = self.store.lock().await;
+    let task = data.visible_task(task_uuid, &user)?;
+
+    let blockers = data.dependencies.iter()
+      .filter(|d| d.task_uuid == task.task_uuid)
+      .filter_map(|d| data.task(d.blocker_uuid))
+      .filter(|blocker| data.task_role(blocker, &user).is_some())
+      .collect();
+    data.task_details(blockers)
+  }
+
+  async fn get_dependents(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let data = self.store.lock().await;
+    let task = data.visible_task(task_uuid, &user)?;
+
+    let dependents = data.dependencies.iter()
+      .filter(|d| d.blocker_uuid == task.task_uuid)
+      .filter_map(|d| data.task(d.task_uuid))
+      .filter(|dependent| data.task_role(dependent, &user).is_some())
+      .collect();
+    data.task_details(dependents)
+  }
+
+  async fn move_task(&self, uow: &mut UnitOfWork, task_uuid: &str, status: TaskStatus, after_task_uuid: Option<String>, before_task_uuid: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let after = after_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    let before = before_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    if after == Some(uuid) || before == Some(uuid) {
+      return Err(DBError::Validation("A task cannot be moved next to itself".to_string()));
+    }
+
+    let data = uow.memory()?;
+    let moved = data.visible_task(task_uuid, &user)?.clone();
+    if !can_edit(data.role_of(uuid, &user)) {
+      return Err(data.not_editable(uuid, &user));
+    }
+
+    let mut position = data.free_position(&moved, &status, after, before, &user)?;
+    if position.is_none() {
+      data.renumber_column(&moved, &status);
+      position = data.free_position(&moved, &status, after, before, &user)?;
+    }
+    let position = position.ok_or_else(|| DBError::Validation(format!(
+      "Task {} does not come before task {} in the {status} column",
+      after_task_uuid.unwrap_or_default(),
+      before_task_uuid.unwrap_or_default()
+    )))?;
+
+    let task = match data.task(uuid) {
+      Some(task) if data.allows_status(task, &status) => task.clone(),
+      _ => return Err(data.not_updatable(uuid, &status, &user)),
+    };
+    data.save_task(TaskRow {
+      status: status.to_string(),
+      position,
+      ..task
+    });
+
+    Ok((data.task_detail(&moved)?, data.task_detail(data.visible_task(task_uuid, &user)?)?))
+  }
+
+  async fn assign_task(&self, uow: &mut UnitOfWork, task_uuid: &str, assignee: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+
+    let data = uow.memory()?;
+    let task = data.task(uuid).filter(|task| can_edit(data.task_role(task, &user))).cloned();
+    let Some(task) = task else {
+      return Err(data.not_editable(uuid, &user));
+    };
+    if !assignee.as_deref().is_none_or(|assignee| data.assignable(&task, assignee)) {
+      // The caller may edit the task, so it is the assignee that was rejected.
+      return Err(DBError::Validation(format!(
+        "{} is not a user who can be assigned task {task_uuid}",
+        assignee.unwrap_or_default()
+      )));
+    }
+
+    let previous = data.task_detail(&task)?;
+    data.save_task(TaskRow {
+      assignee,
+      ..task
+    });
+    Ok((previous, data.task_detail(data.visible_task(task_uuid, &user)?)?))
+  }
+
+  async fn schedule_next_occurrence(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<Option<TaskDetail>, DBError> {
+    let data = uow.memory()?;
+    let task = data.visible_task(task_uuid, &user)?.clone();
+    let Some(rule) = task.recurrence.as_deref() else {
+      return Ok(None);
+    };
+    if data.status_category(task.project_uuid, owner(&task), &task.status) != Some(StatusCategory::Done) {
+      return Ok(None);
+    }
+
+    let anchor = task.anchor();
+    let recurrence = Recurrence::parse(rule)?.pinned(anchor);
+    let Some(next_due) = recurrence.next_after(anchor, task.occurrence as u32) else {
+      return Ok(None);
+    };
+
+    // Like the unique index on (series_uuid, occurrence), an existing next occurrence wins.
+    let series_uuid = task.series_uuid.unwrap_or(task.task_uuid);
+    if data.tasks.iter().any(|t| t.series_uuid == Some(series_uuid) && t.occurrence == task.occurrence + 1) {
+      return Ok(None);
+    }
+
+    // Reminders and assignment carry over; the rule is stored pinned so later occurrences keep the day.
+    // The new occurrence starts in the first not-started status of the workflow.
+    let status = data.initial_status(task.project_uuid, owner(&task));
+    let row = TaskRow {
+      task_uuid: Uuid::new_v4(),
+      position: data.last_position(&Board::of(&task), &status, None),
+      status,
+      created_at: timestamp(),
+      due_at: Some(next_due),
+      recurrence: Some(recurrence.to_string()),
+      series_uuid: Some(series_uuid),
+      occurrence: task.occurrence + 1,
+      subtask_position: data.next_subtask_position(task.parent_task_uuid),
+      deleted_at: None,
+      version: 1,
+      ..task
+    };
+    data.tasks.push(row.clone());
+    data.task_detail(&row).map(Some)
+  }
+
+  async fn preview_occurrences(&self, task_uuid: &str, count: usize, user: String) -> Result<Vec<String>, DBError> {
+    let data = self.store.lock().await;
+    let task = data.visible_task(task_uuid, &user)?;
+    let Some(rule) = task.recurrence.as_deref() else {
+      return Err(DBError::Validation(format!("Task {task_uuid} does not recur")));
+    };
+
+    Recurrence::parse(rule)?
+      .preview(task.anchor(), task.occurrence as u32, count)
+      .into_iter()
+      .map(format_timestamp)
+      .collect()
+  }
+
+  async fn claim_due_reminders(&self) -> Result<Vec<TaskReminder>, DBError> {
+    let mut data = self.store.lock().await;
+    let now = now();
+
+    let mut due = Vec::new();
+    for task in data.tasks.iter().filter(|task| task.deleted_at.is_none()) {
+      let Some(due_at) = task.due_at else {
+        continue;
+      };
+      if data.status_category(task.project_uuid, owner(task), &task.status) == Some(StatusCategory::Done) {
+        continue;
+      }
+      if due_at > now {
+        for offset_secs in &task.remind_offsets {
+          if due_at - time::Duration::seconds(*offset_secs as i64) <= now {
+            due.push((task.task_uuid, due_at, "reminder", *offset_secs));
+          }
+        }
+      } else {
+        due.push((task.task_uuid, due_at, "overdue", 0));
+      }
+    }
+
+    let mut reminders = Vec::new();
+    for notification in due {
+      if !data.notifications.insert(notification) {
+        continue;
+      }
+      let (task_uuid, _, kind, offset_secs) = notification;
+      let (kind, offset_secs) = match kind {
+        "overdue" => (ReminderKind::Overdue, None),
+        _ => (ReminderKind::DueSoon, Some(offset_secs)),
+      };
+      if let Some(task) = data.task(task_uuid) {
+        reminders.push(TaskReminder {
+          kind,
+          offset_secs,
+          task: data.task_detail(task)?,
+        });
+      }
+    }
+    Ok(reminders)
+  }
+}
@@ -0,0 +1,73 @@
+use time::Duration;
+use async_trait::async_trait;
+use sqlx::types::time::OffsetDateTime;
+use crate::models::DBError;
+use crate::persistence::tokens_dbo::TokensDbo;
+use super::{now, MemoryStore, RefreshTokenRecord};
+
+pub struct MemoryTokensDbo {
+    store: MemoryStore,
+}
+
+impl MemoryTokensDbo {
+    pub fn new(store: MemoryStore) -> Self {
+        Self {
+            store,
+        }
+    }
+}
+
+#[async_trait]
+impl TokensDbo for MemoryTokensDbo {
+    async fn create_refresh_token(&self, token_hash: String, user: String, ttl_secs: i64) -> Result<(), DBError> {
+        let mut data = self.store.lock().await;
+        if data.refresh_tokens.contains_key(&token_hash) {
+            return Err(DBError::Conflict("Refresh token already exists".to_string()));
+        }
+
+        data.refresh_tokens.insert(token_hash, RefreshTokenRecord {
+            user_username: user,
+            expires_at: now() + Duration::seconds(ttl_secs),
+            revoked_at: None,
+        });
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: String) -> Result<String, DBError> {
+        let mut data = self.store.lock().await;
+        let now = now();
+
+        match data.refresh_tokens.get_mut(&token_hash) {
+            Some(record) if record.revoked_at.is_none() && record.expires_at > now => {
+                record.revoked_at = Some(now);
+                Ok(record.user_username.clone())
+            }
+            _ => Err(DBError::UnAuthorized("refresh_token is invalid or expired".to_string())),
+        }
+    }
+
+    async fn revoke_refresh_token(&self, token_hash: String, user: String) -> Result<(), DBError> {
+        let mut data = self.store.lock().await;
+        if let Some(record) = data.refresh_tokens.get_mut(&token_hash) {
+            if record.user_username == user && record.revoked_at.is_none() {
+                record.revoked_at = Some(now());
+            }
+        }
+        Ok(())
+    }
+
+    async fn revoke_access_token(&self, jti: String, exp: i64) -> Result<(), DBError> {
+        let expires_at = OffsetDateTime::from_unix_timestamp(exp).map_err(|e| DBError::Other(e.to_string()))?;
+        let mut data = self.store.lock().await;
+        data.revoked_tokens.entry(jti).or_insert(expires_at);
+
+        // Entries are only needed until the token would have expired anyway.
+        let now = now();
+        data.revoked_tokens.retain(|_, expires_at| *expires_at >= now);
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(&self, jti: String) -> Result<bool, DBError> {
+        Ok(self.store.lock().await.revoked_tokens.contains_key(&jti))
+    }
+}
@@ -0,0 +1,74 @@
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{Tracking, TrackingDetail, TrackingEventType, DBError};
+use crate::persistence::tracking_dbo::TrackingDbo;
+use crate::persistence::unit_of_work::UnitOfWork;
+use super::{timestamp, MemoryStore, TrackingRecord};
+
+pub struct MemoryTrackingDbo {
+    store: MemoryStore,
+}
+
+impl MemoryTrackingDbo {
+    pub fn new(store: MemoryStore) -> Self {
+        Self {
+            store,
+        }
+    }
+}
+
+impl TrackingRecord {
+    fn detail(&self) -> TrackingDetail {
+        TrackingDetail {
+            id: self.id.to_string(),
+            task_uuid: self.task_uuid.to_string(),
+            event_type: self.event_type,
+            actor: self.actor.clone(),
+            changes: self.changes.clone(),
+            message: self.message.clone(),
+            created_at: self.created_at.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl TrackingDbo for MemoryTrackingDbo {
+    async fn create_tracking(&self, uow: &mut UnitOfWork, tracking: Tracking) -> Result<TrackingDetail, DBError> {
+        let task_uuid = Uuid::parse_str(&tracking.task_uuid).map_err(|e| {
+          DBError::InvalidInput(e.to_string())
+        })?;
+
+        let data = uow.memory()?;
+        if data.task(task_uuid).is_none() {
+            return Err(DBError::Other(format!("Task {task_uuid} does not exist, it cannot have tracking")));
+        }
+
+        let record = TrackingRecord {
+            id: Uuid::new_v4(),
+            task_uuid,
+            event_type: tracking.event_type,
+            actor: Some(tracking.actor),
+            changes: tracking.changes,
+            message: None,
+            created_at: timestamp(),
+        };
+        let detail = record.detail();
+        data.tracking.push(record);
+        Ok(detail)
+    }
+
+    async fn get_tracking(&self, task_uuid: String, event_type: Option<TrackingEventType>) -> Result<Vec<TrackingDetail>, DBError> {
+        let uuid = Uuid::parse_str(&task_uuid).map_err(|e| {
+          DBError::InvalidInput(e.to_string())
+        })?;
+
+        // Entries are kept in write order, which a stable sort preserves for equal timestamps.
+        let data = self.store.lock().await;
+        let mut records: Vec<&TrackingRecord> = data.tracking.iter()
+            .filter(|r| r.task_uuid == uuid && event_type.is_none_or(|event_type| r.event_type == event_type))
+            .collect();
+        records.sort_by_key(|r| r.created_at);
+
+        Ok(records.into_iter().map(TrackingRecord::detail).collect())
+    }
+}
@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use crate::models::{User, UserDetail, DBError};
+use crate::persistence::users_dbo::UsersDbo;
+use super::{timestamp, MemoryStore, UserRecord};
+
+pub struct MemoryUsersDbo {
+  store: MemoryStore,
+}
+
+impl MemoryUsersDbo {
+  pub fn new(store: MemoryStore) -> Self {
+    Self {
+      store,
+    }
+  }
+}
+
+#[async_trait]
+impl UsersDbo for MemoryUsersDbo {
+  async fn create_user(&self, user: User) -> Result<User, DBError> {
+    let mut data = self.store.lock().await;
+    if data.user_exists(&user.username) {
+      return Err(DBError::Conflict(format!("User {} already exists", user.username)));
+    }
+
+    data.users.push(UserRecord {
+      username: user.username.clone(),
+      email: user.email.clone(),
+      password: user.password.clone(),
+      created_at: timestamp(),
+    });
+    Ok(user)
+  }
+
+  async fn get_user(&self, username: String) -> Result<UserDetail, DBError> {
+    let data = self.store.lock().await;
+    let record = data.users.iter()
+      .find(|u| u.username == username)
+      .ok_or_else(|| DBError::NotFound(format!("User {username} not found")))?;
+
+    Ok(UserDetail {
+      username: record.username.clone(),
+      email: record.email.clone(),
+      password: record.password.clone(),
+      created_at: record.created_at.to_string(),
+    })
+  }
+}
@@ -0,0 +1,143 @@
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, ProjectRole, Workflow, WorkflowDetail, WorkflowStatus, WorkflowTransition};
+use crate::persistence::tasks_dbo::parse_uuid;
+use crate::persistence::workflows_dbo::{check_workflow, WorkflowsDbo};
+use super::{MemoryData, MemoryStore, WorkflowRecord};
+
+pub struct MemoryWorkflowsDbo {
+  store: MemoryStore,
+}
+
+impl MemoryWorkflowsDbo {
+  pub fn new(store: MemoryStore) -> Self {
+    Self {
+      store,
+    }
+  }
+}
+
+impl MemoryData {
+  /// Checks that `user` may see the scope, and also change it when `manage` is set.
+  fn check_workflow_access(&self, project_uuid: Option<Uuid>, user: &str, manage: bool) -> Result<(), DBError> {
+    let Some(project_uuid) = project_uuid else {
+      return Ok(());
+    };
+
+    match self.project_role(project_uuid, user) {
+      None => Err(DBError::NotFound(format!("Project {project_uuid} not found"))),
+      Some(ProjectRole::Owner) => Ok(()),
+      Some(_) if manage => Err(DBError::Forbidden("Only project owners can change the workflow".to_string())),
+      Some(_) => Ok(()),
+    }
+  }
+
+  /// Statuses of tasks in the scope that are not among `keys`.
+  fn statuses_in_use(&self, project_uuid: Option<Uuid>, user: &str, keys: &[String]) -> Result<(), DBError> {
+    let mut missing: Vec<&str> = self.tasks.iter()
+      .filter(|t| match project_uuid {
+        None => t.project_uuid.is_none() && t.user_username.as_deref() == Some(user),
+        Some(project_uuid) => t.project_uuid == Some(project_uuid),
+      })
+      .map(|t| t.status.as_str())
+      .filter(|status| !keys.iter().any(|key| key == status))
+      .collect();
+    missing.sort();
+    missing.dedup();
+
+    if !missing.is_empty() {
+      return Err(DBError::Conflict(format!("Statuses still used by tasks: {}", missing.join(", "))));
+    }
+    Ok(())
+  }
+
+  fn workflow_detail(&self, project_uuid: Option<String>, project: Option<Uuid>, user: &str) -> WorkflowDetail {
+    let Some(workflow) = self.workflow_of(project, user) else {
+      return WorkflowDetail::builtin(project_uuid);
+    };
+
+    let position = |key: &str| workflow.statuses.iter().position(|s| s.key.key() == key);
+    let mut transitions = workflow.transitions.clone();
+    transitions.sort_by_key(|t| (position(t.from.key()), position(t.to.key())));
+
+    WorkflowDetail {
+      workflow_uuid: Some(workflow.workflow_uuid.to_string()),
+      project_uuid,
+      statuses: workflow.statuses.clone(),
+      transitions,
+    }
+  }
+}
+
+#[async_trait]
+impl WorkflowsDbo for MemoryWorkflowsDbo {
+  async fn get_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    let data = self.store.lock().await;
+    data.check_workflow_access(project, &user, false)?;
+
+    Ok(data.workflow_detail(project_uuid, project, &user))
+  }
+
+  async fn set_workflow(&self, project_uuid: Option<String>, workflow: Workflow, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    check_workflow(&workflow)?;
+    let mut data = self.store.lock().await;
+    data.check_workflow_access(project, &user, true)?;
+
+    let keys: Vec<String> = workflow.statuses.iter().map(|s| s.key.key().to_string()).collect();
+    data.statuses_in_use(project, &user, &keys)?;
+
+    let statuses = workflow.statuses.into_iter().map(|s| {
+      WorkflowStatus {
+        name: s.name.trim().to_string(),
+        ..s
+      }
+    }).collect();
+    let mut transitions: Vec<WorkflowTransition> = Vec::new();
+    for transition in workflow.transitions {
+      if !transitions.iter().any(|t| t.from == transition.from && t.to == transition.to) {
+        transitions.push(transition);
+      }
+    }
+
+    let existing = data.workflows.iter_mut().find(|w| match project {
+      None => w.user_username.as_deref() == Some(user.as_str()),
+      Some(project) => w.project_uuid == Some(project),
+    });
+    match existing {
+      Some(existing) => {
+        existing.statuses = statuses;
+        existing.transitions = transitions;
+      }
+      None => data.workflows.push(WorkflowRecord {
+        workflow_uuid: Uuid::new_v4(),
+        user_username: project.is_none().then(|| user.clone()),
+        project_uuid: project,
+        statuses,
+        transitions,
+      }),
+    }
+
+    Ok(data.workflow_detail(project_uuid, project, &user))
+  }
+
+  async fn reset_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    let mut data = self.store.lock().await;
+    data.check_workflow_access(project, &user, true)?;
+
+    if data.workflow_of(project, &user).is_some() {
+      let builtin: Vec<String> = WorkflowDetail::builtin(None).statuses.iter()
+        .map(|s| s.key.key().to_string())
+        .collect();
+      data.statuses_in_use(project, &user, &builtin)?;
+      data.workflows.retain(|w| match project {
+        None => w.user_username.as_deref() != Some(user.as_str()),
+        Some(project) => w.project_uuid != Some(project),
+      });
+    }
+
+    Ok(WorkflowDetail::builtin(project_uuid))
+  }
+}
@@ -1,3 +1,9 @@
 pub mod tasks_dbo;
 pub mod users_dbo;
-pub mod tracking_dbo;
\ No newline at end of file
+pub mod tracking_dbo;
+pub mod tokens_dbo;
+pub mod projects_dbo;
+pub mod labels_dbo;
+pub mod workflows_dbo;
+pub mod unit_of_work;
+pub mod memory;
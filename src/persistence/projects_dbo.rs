@@ -0,0 +1,236 @@
+use std::str::FromStr;
+use sqlx::PgPool;
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, Project, ProjectDetail, ProjectMember, ProjectRole};
+
+#[async_trait]
+pub trait ProjectsDbo {
+  /// Creates a project with `user` as its first owner.
+  async fn create_project(&self, project: Project, user: String) -> Result<ProjectDetail, DBError>;
+  async fn list_projects(&self, user: String) -> Result<Vec<ProjectDetail>, DBError>;
+  /// Role of `user` in the project, `None` when not a member.
+  async fn get_role(&self, project_uuid: &str, user: String) -> Result<Option<ProjectRole>, DBError>;
+  async fn list_members(&self, project_uuid: &str, user: String) -> Result<Vec<ProjectMember>, DBError>;
+  async fn add_member(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError>;
+  async fn update_role(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError>;
+  async fn remove_member(&self, project_uuid: &str, member: String, user: String) -> Result<(), DBError>;
+}
+
+pub struct ProjectsDboImpl {
+  db: PgPool,
+}
+
+impl ProjectsDboImpl {
+  pub fn new(db: PgPool) -> Self {
+    Self {
+      db,
+    }
+  }
+
+  async fn require_role(&self, project_uuid: &str, user: String) -> Result<ProjectRole, DBError> {
+    self.get_role(project_uuid, user).await?
+      .ok_or_else(|| DBError::NotFound(format!("Project {project_uuid} not found")))
+  }
+
+  async fn require_owner(&self, project_uuid: &str, user: String) -> Result<(), DBError> {
+    match self.require_role(project_uuid, user).await? {
+      ProjectRole::Owner => Ok(()),
+      _ => Err(DBError::Forbidden("Only project owners can manage members".to_string())),
+    }
+  }
+
+  /// Fails when `member` is the last owner, so a project always keeps someone who can manage it.
+  async fn ensure_other_owner(&self, project_uuid: Uuid, member: &str) -> Result<(), DBError> {
+    let owners = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!" FROM project_members
+        WHERE project_uuid = $1 AND role = 'owner' AND user_username <> $2
+        "#,
+        project_uuid,
+        member
+    ).fetch_one(&self.db).await.map_err(DBError::from)?;
+
+    if owners == 0 {
+      return Err(DBError::Conflict("A project needs at least one owner".to_string()));
+    }
+    Ok(())
+  }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, DBError> {
+  Uuid::parse_str(value).map_err(|e| DBError::InvalidInput(e.to_string()))
+}
+
+#[async_trait]
+impl ProjectsDbo for ProjectsDboImpl {
+  async fn create_project(&self, project: Project, user: String) -> Result<ProjectDetail, DBError> {
+    let mut tx = self.db.begin().await.map_err(DBError::from)?;
+
+    let record = sqlx::query!(
+        r#"
+        INSERT INTO projects (name, created_by)
+        VALUES ($1, $2)
+        RETURNING *
+        "#,
+        project.name,
+        user
+    ).fetch_one(&mut *tx).await.map_err(DBError::from)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO project_members (project_uuid, user_username, role)
+        VALUES ($1, $2, 'owner')
+        "#,
+        record.project_uuid,
+        user
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+
+    tx.commit().await.map_err(DBError::from)?;
+
+    Ok(ProjectDetail {
+      project_uuid: record.project_uuid.to_string(),
+      name: record.name,
+      created_by: record.created_by,
+      created_at: record.created_at.to_string(),
+      role: ProjectRole::Owner,
+    })
+  }
+
+  async fn list_projects(&self, user: String) -> Result<Vec<ProjectDetail>, DBError> {
+    let records = sqlx::query!(
+        r#"
+        SELECT p.*, m.role FROM projects p
+        JOIN project_members m ON m.project_uuid = p.project_uuid
+        WHERE m.user_username = $1
+        ORDER BY p.created_at
+        "#,
+        user
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(|r| {
+      Ok(ProjectDetail {
+        project_uuid: r.project_uuid.to_string(),
+        name: r.name,
+        created_by: r.created_by,
+        created_at: r.created_at.to_string(),
+        role: ProjectRole::from_str(&r.role)?,
+      })
+    }).collect()
+  }
+
+  async fn get_role(&self, project_uuid: &str, user: String) -> Result<Option<ProjectRole>, DBError> {
+    let uuid = parse_uuid(project_uuid)?;
+
+    let role = sqlx::query_scalar!(
+        r#"
+        SELECT role FROM project_members WHERE project_uuid = $1 AND user_username = $2
+        "#,
+        uuid,
+        user
+    ).fetch_optional(&self.db).await.map_err(DBError::from)?;
+
+    role.as_deref().map(ProjectRole::from_str).transpose()
+  }
+
+  async fn list_members(&self, project_uuid: &str, user: String) -> Result<Vec<ProjectMember>, DBError> {
+    self.require_role(project_uuid, user).await?;
+
+    let records = sqlx::query!(
+        r#"
+        SELECT user_username, role, created_at FROM project_members
+        WHERE project_uuid = $1
+        ORDER BY created_at
+        "#,
+        parse_uuid(project_uuid)?
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(|r| {
+      Ok(ProjectMember {
+        username: r.user_username,
+        role: ProjectRole::from_str(&r.role)?,
+        created_at: r.created_at.to_string(),
+      })
+    }).collect()
+  }
+
+  async fn add_member(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError> {
+    self.require_owner(project_uuid, user).await?;
+
+    let record = sqlx::query!(
+        r#"
+        INSERT INTO project_members (project_uuid, user_username, role)
+        VALUES ($1, $2, $3)
+        RETURNING user_username, role, created_at
+        "#,
+        parse_uuid(project_uuid)?,
+        member,
+        role.to_string()
+    ).fetch_one(&self.db).await.map_err(|e| match DBError::from(e) {
+      DBError::Conflict(_) => DBError::Conflict(format!("User {member} is already a member of project {project_uuid}")),
+      DBError::Validation(_) => DBError::NotFound(format!("User {member} not found")),
+      e => e,
+    })?;
+
+    Ok(ProjectMember {
+      username: record.user_username,
+      role: ProjectRole::from_str(&record.role)?,
+      created_at: record.created_at.to_string(),
+    })
+  }
+
+  async fn update_role(&self, project_uuid: &str, member: String, role: ProjectRole, user: String) -> Result<ProjectMember, DBError> {
+    self.require_owner(project_uuid, user).await?;
+    let uuid = parse_uuid(project_uuid)?;
+
+    if role != ProjectRole::Owner {
+      self.ensure_other_owner(uuid, &member).await?;
+    }
+
+    let record = sqlx::query!(
+        r#"
+        UPDATE project_members SET role = $3
+        WHERE project_uuid = $1 AND user_username = $2
+        RETURNING user_username, role, created_at
+        "#,
+        uuid,
+        member,
+        role.to_string()
+    ).fetch_optional(&self.db).await.map_err(DBError::from)?;
+
+    let record = record.ok_or_else(|| {
+      DBError::NotFound(format!("User {member} is not a member of project {project_uuid}"))
+    })?;
+
+    Ok(ProjectMember {
+      username: record.user_username,
+      role: ProjectRole::from_str(&record.role)?,
+      created_at: record.created_at.to_string(),
+    })
+  }
+
+  async fn remove_member(&self, project_uuid: &str, member: String, user: String) -> Result<(), DBError> {
+    // Members may always leave; removing anyone else needs an owner.
+    if member == user {
+      self.require_role(project_uuid, user).await?;
+    } else {
+      self.require_owner(project_uuid, user).await?;
+    }
+    let uuid = parse_uuid(project_uuid)?;
+    self.ensure_other_owner(uuid, &member).await?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM project_members WHERE project_uuid = $1 AND user_username = $2
+        "#,
+        uuid,
+        member
+    ).execute(&self.db).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(DBError::NotFound(format!("User {member} is not a member of project {project_uuid}")));
+    }
+
+    Ok(())
+  }
+}
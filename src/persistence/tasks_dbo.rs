@@ -1,17 +1,73 @@
-use sqlx::PgPool;
-use crate::models::{Task, TaskStatus, TaskDetail, TaskUpdateReq, DBError};
+use std::str::FromStr;
+use std::time::Duration;
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
+use sqlx::types::{Uuid, time::{OffsetDateTime, PrimitiveDateTime}};
+use time::format_description::well_known::Rfc3339;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+use crate::models::{
+  Task, TaskStatus, StatusCategory, TaskPriority, TaskDetail, TaskUpdateReq, TaskListQuery, TaskPage, TaskSortField, SortDirection,
+  TaskSearchHit, TaskReminder, ReminderKind, TagMatch, DBError
+};
+use crate::recurrence::Recurrence;
+use crate::persistence::unit_of_work::UnitOfWork;
 use async_trait::async_trait;
-use async_graphql::*;
 
+pub(crate) const DEFAULT_PAGE_SIZE: i64 = 50;
+pub(crate) const MAX_PAGE_SIZE: i64 = 100;
+/// Gap between the positions of neighbouring tasks when they are appended or renumbered.
+pub(crate) const POSITION_STEP: f64 = 1024.0;
 
 #[async_trait]
 pub trait TasksDbo {
+  /// Starts a unit of work for task writes and the tracking rows describing them.
+  async fn begin(&self) -> Result<UnitOfWork, DBError>;
   async fn get_all_tasks(&self, user: String) -> Result<Vec<TaskDetail>, DBError>;
-  async fn create_task(&self, task: Task, user: String) -> Result<TaskDetail, DBError>;
+  async fn list_tasks(&self, query: TaskListQuery, user: String) -> Result<TaskPage, DBError>;
+  async fn search_tasks(&self, q: &str, limit: Option<i64>, user: String) -> Result<Vec<TaskSearchHit>, DBError>;
+  async fn create_task(&self, uow: &mut UnitOfWork, task: Task, user: String) -> Result<TaskDetail, DBError>;
   async fn get_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError>;
-  async fn update_task(&self, task: TaskUpdateReq, user: String) -> Result<TaskDetail, DBError>;
-  async fn update_task_status(&self, task_status: TaskStatus, task_uuid: String, user: String) -> Result<TaskDetail, DBError>;
-  async fn delete_task(&self, task_uuid: String, user: String) -> Result<(), DBError>;
+  /// Applies the fields of the patch, provided the task is still at `version` when one is given;
+  /// `DBError::VersionConflict` otherwise. Status and assignee changes get the checks of `update_task_status`
+  /// and `assign_task`. Like the other changes of a single task, returns the task before and after
+  /// the change, the first one read under the lock of the unit of work.
+  async fn update_task(&self, uow: &mut UnitOfWork, task: TaskUpdateReq, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError>;
+  /// Changes the status, with the same `version` check as `update_task`.
+  async fn update_task_status(&self, uow: &mut UnitOfWork, task_status: TaskStatus, task_uuid: String, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError>;
+  /// Moves the task to the trash and returns it. Tasks with subtasks are only deleted, together with
+  /// the subtasks not in the trash yet, when `cascade` is set; those are returned next to the task.
+  async fn delete_task(&self, uow: &mut UnitOfWork, task_uuid: String, cascade: bool, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError>;
+  /// Tasks in the trash that `user` has access to, most recently deleted first.
+  async fn list_trash(&self, user: String) -> Result<Vec<TaskDetail>, DBError>;
+  async fn get_trashed_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError>;
+  /// Takes the task out of the trash with the subtasks deleted along with it, and returns both.
+  /// Subtasks of a parent still in the trash cannot be restored on their own.
+  async fn restore_task(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError>;
+  /// Permanently deletes tasks, with their subtasks and history, that have been in the trash for
+  /// longer than `retention`. Returns the number of deleted tasks.
+  async fn purge_trash(&self, retention: Duration) -> Result<u64, DBError>;
+  /// All subtasks below the task, parents before children and siblings in order.
+  async fn get_subtree(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError>;
+  /// Moves the task under another parent (`None` for top level) at `position` among its siblings.
+  async fn move_to_parent(&self, uow: &mut UnitOfWork, task_uuid: &str, parent_task_uuid: Option<String>, position: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError>;
+  /// Records that the task is blocked by `blocker_uuid`. Rejected when it would create a cycle.
+  async fn add_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError>;
+  async fn remove_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError>;
+  /// Tasks the task is blocked by.
+  async fn get_blockers(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError>;
+  /// Tasks blocked by the task.
+  async fn get_dependents(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError>;
+  /// Moves the task to the `status` column, between `after_task_uuid` and `before_task_uuid` when given.
+  async fn move_task(&self, uow: &mut UnitOfWork, task_uuid: &str, status: TaskStatus, after_task_uuid: Option<String>, before_task_uuid: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError>;
+  /// Sets or clears (`None`) the assignee. Project tasks can only be assigned to project members.
+  async fn assign_task(&self, uow: &mut UnitOfWork, task_uuid: &str, assignee: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError>;
+  /// Creates the next occurrence of a recurring task that is done. `None` when the task doesn't
+  /// recur, the series ended, or the next occurrence already exists.
+  async fn schedule_next_occurrence(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<Option<TaskDetail>, DBError>;
+  /// Due dates of the next `count` occurrences of a recurring task.
+  async fn preview_occurrences(&self, task_uuid: &str, count: usize, user: String) -> Result<Vec<String>, DBError>;
+  /// Reminders that became due since the last call. Each one is returned only once.
+  async fn claim_due_reminders(&self) -> Result<Vec<TaskReminder>, DBError>;
 }
 
 #[derive(Debug, Clone)]
@@ -27,168 +83,1397 @@ impl TasksDboImpl {
   }
 }
 
+/// A full row of `tasks`; every query selects `*` so new columns only need adding here. `blocked`,
+/// `labels` and `status_category` are computed by `task_blocked`, `task_label_list` and
+/// `status_category` next to it. The in-memory store keeps rows of this shape with those left empty.
+#[derive(sqlx::FromRow, Clone, PartialEq)]
+pub(crate) struct TaskRow {
+  pub(crate) task_uuid: Uuid,
+  pub(crate) title: String,
+  pub(crate) description: String,
+  pub(crate) status: String,
+  pub(crate) priority: String,
+  pub(crate) position: f64,
+  pub(crate) created_at: PrimitiveDateTime,
+  pub(crate) user_username: Option<String>,
+  pub(crate) project_uuid: Option<Uuid>,
+  pub(crate) assignee: Option<String>,
+  pub(crate) due_at: Option<OffsetDateTime>,
+  pub(crate) remind_offsets: Vec<i32>,
+  pub(crate) recurrence: Option<String>,
+  pub(crate) series_uuid: Option<Uuid>,
+  pub(crate) occurrence: i32,
+  pub(crate) parent_task_uuid: Option<Uuid>,
+  pub(crate) subtask_position: i32,
+  pub(crate) blocked: Option<bool>,
+  pub(crate) labels: Option<serde_json::Value>,
+  pub(crate) status_category: Option<String>,
+  pub(crate) deleted_at: Option<OffsetDateTime>,
+  pub(crate) version: i32,
+}
+
+impl TaskRow {
+  pub(crate) fn into_detail(self) -> Result<TaskDetail, DBError> {
+    let status = TaskStatus::from_str(&self.status)?;
+    // Statuses missing from the workflow only come from data predating it; treat them as not started.
+    let status_category = self.status_category.as_deref().map(StatusCategory::from_str).transpose()?
+      .unwrap_or(StatusCategory::Todo);
+    let overdue = status_category != StatusCategory::Done
+      && self.due_at.is_some_and(|due_at| due_at < OffsetDateTime::now_utc());
+    let due_at = self.due_at.map(format_timestamp).transpose()?;
+    let deleted_at = self.deleted_at.map(format_timestamp).transpose()?;
+    let series_uuid = self.series_uuid.or(self.recurrence.is_some().then_some(self.task_uuid));
+    let labels = match self.labels {
+      Some(labels) => serde_json::from_value(labels).map_err(|e| DBError::Other(e.to_string()))?,
+      None => Vec::new(),
+    };
+
+    Ok(TaskDetail {
+      task_uuid: self.task_uuid.to_string(),
+      title: self.title,
+      description: self.description,
+      status,
+      status_category,
+      priority: TaskPriority::from_str(&self.priority)?,
+      position: self.position,
+      created_by: self.user_username.unwrap_or_default(),
+      assignee: self.assignee,
+      created_at: self.created_at.to_string(),
+      project_uuid: self.project_uuid.map(|uuid| uuid.to_string()),
+      due_at,
+      remind_offsets: self.remind_offsets,
+      overdue,
+      recurrence: self.recurrence,
+      series_uuid: series_uuid.map(|uuid| uuid.to_string()),
+      occurrence: self.occurrence,
+      parent_task_uuid: self.parent_task_uuid.map(|uuid| uuid.to_string()),
+      subtask_position: self.subtask_position,
+      blocked: self.blocked.unwrap_or_default(),
+      labels,
+      deleted_at,
+      version: self.version,
+    })
+  }
+
+  /// Start of the current occurrence: the due date, or the creation time for tasks without one.
+  pub(crate) fn anchor(&self) -> OffsetDateTime {
+    self.due_at.unwrap_or(self.created_at.assume_utc())
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct ReminderRow {
+  #[sqlx(flatten)]
+  task: TaskRow,
+  kind: String,
+  offset_secs: i32,
+}
+
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+  #[sqlx(flatten)]
+  task: TaskRow,
+  rank: f32,
+  title_highlight: String,
+  description_highlight: String,
+  tracking_highlight: Option<String>,
+}
+
+/// Position after the last task of a page: its sort key plus `task_uuid` as tie-breaker.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TaskCursor {
+  pub(crate) value: String,
+  pub(crate) task_uuid: String,
+}
+
+impl TaskCursor {
+  pub(crate) fn from_row(row: &TaskRow, sort_by: TaskSortField) -> Result<Self, DBError> {
+    let value = match sort_by {
+      TaskSortField::CreatedAt => row.created_at.to_string(),
+      TaskSortField::Title => row.title.clone(),
+      TaskSortField::Status => row.status.clone(),
+      TaskSortField::Priority => TaskPriority::from_str(&row.priority)?.rank().to_string(),
+      TaskSortField::Position => row.position.to_string(),
+    };
+    Ok(Self {
+      value,
+      task_uuid: row.task_uuid.to_string(),
+    })
+  }
+
+  pub(crate) fn encode(&self) -> Result<String, DBError> {
+    let json = serde_json::to_vec(self).map_err(|e| DBError::Other(e.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+  }
+
+  pub(crate) fn decode(cursor: &str) -> Result<Self, DBError> {
+    let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|e| {
+      DBError::InvalidInput(format!("Invalid cursor: {e}"))
+    })?;
+    serde_json::from_slice(&json).map_err(|e| DBError::InvalidInput(format!("Invalid cursor: {e}")))
+  }
+}
+
+fn sort_column(sort_by: TaskSortField) -> &'static str {
+  match sort_by {
+    TaskSortField::CreatedAt => "created_at",
+    TaskSortField::Title => "title",
+    TaskSortField::Status => "status",
+    TaskSortField::Priority => "priority_rank(priority)",
+    TaskSortField::Position => "position",
+  }
+}
+
+/// Cast turning a cursor value back into the type of the sort column.
+fn sort_cast(sort_by: TaskSortField) -> &'static str {
+  match sort_by {
+    TaskSortField::CreatedAt => "::timestamp",
+    TaskSortField::Priority => "::int",
+    TaskSortField::Position => "::float8",
+    TaskSortField::Title | TaskSortField::Status => "",
+  }
+}
+
+pub(crate) fn parse_uuid(value: &str) -> Result<Uuid, DBError> {
+  Uuid::parse_str(value).map_err(|e| DBError::InvalidInput(e.to_string()))
+}
+
+pub(crate) fn parse_due_at(value: &str) -> Result<OffsetDateTime, DBError> {
+  OffsetDateTime::parse(value, &Rfc3339).map_err(|e| {
+    DBError::InvalidInput(format!("Invalid due_at {value}: {e}"))
+  })
+}
+
+pub(crate) fn format_timestamp(value: OffsetDateTime) -> Result<String, DBError> {
+  value.format(&Rfc3339).map_err(|e| DBError::Other(e.to_string()))
+}
+
+/// Validates a recurrence rule and returns it in canonical RRULE form.
+pub(crate) fn canonical_recurrence(rule: &str) -> Result<String, DBError> {
+  Ok(Recurrence::parse(rule)?.to_string())
+}
+
+pub(crate) fn check_remind_offsets(offsets: &[i32]) -> Result<(), DBError> {
+  if offsets.iter().any(|offset| *offset < 0) {
+    return Err(DBError::Validation("remind_offsets must not be negative".to_string()));
+  }
+  Ok(())
+}
+
+pub(crate) fn parse_timestamp(value: &str) -> Result<PrimitiveDateTime, DBError> {
+  let timestamp = OffsetDateTime::parse(value, &Rfc3339).map_err(|e| {
+    DBError::InvalidInput(format!("Invalid timestamp {value}: {e}"))
+  })?.to_offset(time::UtcOffset::UTC);
+  Ok(PrimitiveDateTime::new(timestamp.date(), timestamp.time()))
+}
+
+/// Splits the rows of a task and the subtasks changed with it into the task and its subtasks, those
+/// by subtask position.
+pub(crate) fn split_subtree(root: Uuid, mut rows: Vec<TaskRow>) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+  let index = rows.iter().position(|row| row.task_uuid == root)
+    .ok_or_else(|| DBError::NotFound(format!("Task {root} not found")))?;
+  let task = rows.remove(index).into_detail()?;
+  rows.sort_by_key(|row| row.subtask_position);
+  let subtasks = rows.into_iter().map(TaskRow::into_detail).collect::<Result<_, _>>()?;
+  Ok((task, subtasks))
+}
+
+/// Label names of the `tags` filter, lowercased, sorted and without duplicates, so that `tags_mode=all`
+/// needs each name once.
+pub(crate) fn parse_tags(tags: Option<&str>) -> Vec<String> {
+  let mut tags: Vec<String> = tags.unwrap_or_default()
+    .split(',')
+    .map(|tag| tag.trim().to_lowercase())
+    .filter(|tag| !tag.is_empty())
+    .collect();
+  tags.sort();
+  tags.dedup();
+  tags
+}
+
+/// Appends the `WHERE` clause shared by the page query and the total count.
+fn push_task_filters(
+  qb: &mut QueryBuilder<'_, Postgres>,
+  query: &TaskListQuery,
+  user: &str,
+) -> Result<(), DBError> {
+  qb.push(" WHERE task_role(tasks, ").push_bind(user.to_string()).push(") IS NOT NULL");
+
+  if let Some(project_uuid) = &query.project_uuid {
+    qb.push(" AND project_uuid = ").push_bind(parse_uuid(project_uuid)?);
+  }
+  if let Some(assignee) = &query.assignee {
+    qb.push(" AND assignee = ").push_bind(assignee.clone());
+  }
+  if let Some(status) = &query.status {
+    qb.push(" AND status = ").push_bind(status.to_string());
+  }
+  if let Some(category) = query.status_category {
+    qb.push(" AND status_category(project_uuid, user_username, status) = ").push_bind(category.to_string());
+  }
+  match query.overdue {
+    Some(true) => {
+      qb.push(" AND due_at < now() AND status_category(project_uuid, user_username, status) IS DISTINCT FROM 'done'");
+    }
+    Some(false) => {
+      qb.push(" AND (due_at IS NULL OR due_at >= now() OR status_category(project_uuid, user_username, status) = 'done')");
+    }
+    None => {}
+  }
+  match query.blocked {
+    Some(true) => {
+      qb.push(" AND task_blocked(task_uuid)");
+    }
+    Some(false) => {
+      qb.push(" AND NOT task_blocked(task_uuid)");
+    }
+    None => {}
+  }
+  let tags = parse_tags(query.tags.as_deref());
+  if !tags.is_empty() {
+    let matching = match query.tags_mode {
+      TagMatch::Any => 1,
+      TagMatch::All => tags.len() as i64,
+    };
+    qb.push(" AND (SELECT COUNT(DISTINCT lower(l.name)) FROM task_labels tl JOIN labels l ON l.label_uuid = tl.label_uuid")
+      .push(" WHERE tl.task_uuid = tasks.task_uuid AND lower(l.name) = ANY(").push_bind(tags)
+      .push(")) >= ").push_bind(matching);
+  }
+  if let Some(created_after) = &query.created_after {
+    qb.push(" AND created_at >= ").push_bind(parse_timestamp(created_after)?);
+  }
+  if let Some(created_before) = &query.created_before {
+    qb.push(" AND created_at < ").push_bind(parse_timestamp(created_before)?);
+  }
+  if let Some(q) = query.q.as_deref().filter(|q| !q.is_empty()) {
+    let pattern = format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    qb.push(" AND (title ILIKE ").push_bind(pattern.clone())
+      .push(" OR description ILIKE ").push_bind(pattern).push(")");
+  }
+
+  Ok(())
+}
+
+/// A task visible to `user`, read through `executor` so that uncommitted writes of a unit of work are seen.
+async fn fetch_row<'e>(executor: impl PgExecutor<'e>, task_uuid: &str, user: &str) -> Result<TaskRow, DBError> {
+  let uuid = parse_uuid(task_uuid)?;
+
+  let record = sqlx::query_as!(
+      TaskRow,
+      r#"
+      SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+             status_category(project_uuid, user_username, status) AS status_category
+      FROM tasks WHERE task_uuid = $1 AND task_role(tasks, $2) IS NOT NULL
+      "#,
+      uuid,
+      user
+  ).fetch_optional(executor).await.map_err(DBError::from)?;
+
+  record.ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found")))
+}
+
+/// Like `fetch_row`, locking the task until the end of the unit of work: the row is the task as it
+/// is before a change, which concurrent writes can no longer slip past.
+async fn fetch_row_for_update(conn: &mut PgConnection, task_uuid: &str, user: &str) -> Result<TaskRow, DBError> {
+  let uuid = parse_uuid(task_uuid)?;
+
+  let record = sqlx::query_as!(
+      TaskRow,
+      r#"
+      SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+             status_category(project_uuid, user_username, status) AS status_category
+      FROM tasks WHERE task_uuid = $1 AND task_role(tasks, $2) IS NOT NULL
+      FOR UPDATE
+      "#,
+      uuid,
+      user
+  ).fetch_optional(conn).await.map_err(DBError::from)?;
+
+  record.ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found")))
+}
+
+/// Checks that the task, locked by `fetch_row_for_update`, is still at `version` when one is given.
+fn check_version(task: &TaskRow, version: Option<i32>) -> Result<(), DBError> {
+  match version {
+    Some(version) if task.version != version => Err(DBError::VersionConflict(format!(
+      "Task {} was changed by someone else, it is at version {} instead of {version}", task.task_uuid, task.version
+    ))),
+    _ => Ok(()),
+  }
+}
+
+/// Position of a neighbour of a moved task, which must be visible and already in the target column
+/// of the same board.
+async fn neighbour_position(conn: &mut PgConnection, task_uuid: Uuid, moved: &TaskRow, status: &TaskStatus, user: &str) -> Result<f64, DBError> {
+  let record = sqlx::query!(
+      r#"
+      SELECT status, position, task_board(project_uuid, user_username) = task_board($3, $4) AS "same_board!"
+      FROM tasks WHERE task_uuid = $1 AND task_role(tasks, $2) IS NOT NULL
+      "#,
+      task_uuid,
+      user,
+      moved.project_uuid,
+      moved.user_username
+  ).fetch_optional(conn).await.map_err(DBError::from)?;
+
+  let record = record.ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found")))?;
+  if record.status != status.key() || !record.same_board {
+    return Err(DBError::Validation(format!("Task {task_uuid} is not in the {status} column")));
+  }
+  Ok(record.position)
+}
+
+/// Free position for the moved task in the `status` column of its board, right after `after` and/or
+/// right before `before`, or last. `None` when there is no room left between the neighbours.
+async fn free_position(
+  conn: &mut PgConnection,
+  moved: &TaskRow,
+  status: &TaskStatus,
+  after: Option<Uuid>,
+  before: Option<Uuid>,
+  user: &str,
+) -> Result<Option<f64>, DBError> {
+  let lower = match after {
+    Some(after) => Some(neighbour_position(conn, after, moved, status, user).await?),
+    None => None,
+  };
+  let upper = match before {
+    Some(before) => Some(neighbour_position(conn, before, moved, status, user).await?),
+    None => None,
+  };
+
+  let (lower, upper) = match (lower, upper) {
+    (Some(lower), Some(upper)) => (lower, upper),
+    (Some(lower), None) => {
+      let next = sqlx::query_scalar!(
+          r#"
+          SELECT MIN(position) FROM tasks
+          WHERE task_board(project_uuid, user_username) = task_board($4, $5) AND status = $1 AND deleted_at IS NULL
+            AND position > $2 AND task_uuid <> $3
+          "#,
+          status.to_string(),
+          lower,
+          moved.task_uuid,
+          moved.project_uuid,
+          moved.user_username
+      ).fetch_one(&mut *conn).await.map_err(DBError::from)?;
+      (lower, next.unwrap_or(lower + 2.0 * POSITION_STEP))
+    }
+    (None, Some(upper)) => {
+      let previous = sqlx::query_scalar!(
+          r#"
+          SELECT MAX(position) FROM tasks
+          WHERE task_board(project_uuid, user_username) = task_board($4, $5) AND status = $1 AND deleted_at IS NULL
+            AND position < $2 AND task_uuid <> $3
+          "#,
+          status.to_string(),
+          upper,
+          moved.task_uuid,
+          moved.project_uuid,
+          moved.user_username
+      ).fetch_one(&mut *conn).await.map_err(DBError::from)?;
+      (previous.unwrap_or(upper - 2.0 * POSITION_STEP), upper)
+    }
+    (None, None) => {
+      let last = sqlx::query_scalar!(
+          r#"
+          SELECT MAX(position) FROM tasks
+          WHERE task_board(project_uuid, user_username) = task_board($3, $4) AND status = $1 AND deleted_at IS NULL
+            AND task_uuid <> $2
+          "#,
+          status.to_string(),
+          moved.task_uuid,
+          moved.project_uuid,
+          moved.user_username
+      ).fetch_one(&mut *conn).await.map_err(DBError::from)?;
+      return Ok(Some(last.unwrap_or(0.0) + POSITION_STEP));
+    }
+  };
+
+  let position = lower + (upper - lower) / 2.0;
+  Ok((lower < position && position < upper).then_some(position))
+}
+
+/// Spreads the positions of a column of the moved task's board evenly again, once repeated moves used
+/// up the room between two tasks. Other boards are left alone, so their versions don't change.
+async fn renumber_column(conn: &mut PgConnection, moved: &TaskRow, status: &TaskStatus) -> Result<(), DBError> {
+  sqlx::query!(
+      r#"
+      UPDATE tasks SET position = ordered.rn * $2::float8
+      FROM (
+          SELECT task_uuid, row_number() OVER (ORDER BY position, created_at, task_uuid) AS rn
+          FROM tasks
+          WHERE task_board(project_uuid, user_username) = task_board($3, $4) AND status = $1 AND deleted_at IS NULL
+      ) ordered
+      WHERE tasks.task_uuid = ordered.task_uuid
+      "#,
+      status.to_string(),
+      POSITION_STEP,
+      moved.project_uuid,
+      moved.user_username
+  ).execute(conn).await.map_err(DBError::from)?;
+
+  Ok(())
+}
+
 #[async_trait]
 impl TasksDbo for TasksDboImpl {
+  async fn begin(&self) -> Result<UnitOfWork, DBError> {
+    UnitOfWork::begin(&self.db).await
+  }
+
   async fn get_all_tasks(&self, user: String) -> Result<Vec<TaskDetail>, DBError> {
-    let records = sqlx::query!(
+    let records = sqlx::query_as!(
+        TaskRow,
         r#"
-        SELECT * FROM tasks WHERE user_username = $1
+        SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        FROM tasks WHERE task_role(tasks, $1) IS NOT NULL
         "#,
         user
-    ).fetch_all(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
-    })?;
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(TaskRow::into_detail).collect()
+  }
+
+  async fn list_tasks(&self, query: TaskListQuery, user: String) -> Result<TaskPage, DBError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let column = sort_column(query.sort_by);
+    let (direction, comparison) = match query.order {
+      SortDirection::Asc => ("ASC", ">"),
+      SortDirection::Desc => ("DESC", "<"),
+    };
+
+    let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM tasks");
+    push_task_filters(&mut count_qb, &query, &user)?;
+    let total: i64 = count_qb.build_query_scalar().fetch_one(&self.db).await.map_err(DBError::from)?;
+
+    let mut qb = QueryBuilder::new(
+      "SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels, \
+       status_category(project_uuid, user_username, status) AS status_category FROM tasks"
+    );
+    push_task_filters(&mut qb, &query, &user)?;
+
+    if let Some(cursor) = &query.cursor {
+      let cursor = TaskCursor::decode(cursor)?;
+      let cursor_uuid = Uuid::parse_str(&cursor.task_uuid).map_err(|e| {
+        DBError::InvalidInput(format!("Invalid cursor: {e}"))
+      })?;
+      qb.push(format!(" AND ({column}, task_uuid) {comparison} ("));
+      qb.push_bind(cursor.value).push(sort_cast(query.sort_by));
+      qb.push(", ").push_bind(cursor_uuid).push(")");
+    }
+
+    // One extra row tells us whether there is a next page.
+    qb.push(format!(" ORDER BY {column} {direction}, task_uuid {direction} LIMIT "))
+      .push_bind(limit + 1);
+
+    let mut rows: Vec<TaskRow> = qb.build_query_as().fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    let next_cursor = if rows.len() as i64 > limit {
+      rows.truncate(limit as usize);
+      rows.last().map(|row| TaskCursor::from_row(row, query.sort_by)?.encode()).transpose()?
+    } else {
+      None
+    };
+
+    let tasks = rows.into_iter().map(TaskRow::into_detail).collect::<Result<Vec<_>, DBError>>()?;
+
+    Ok(TaskPage {
+      tasks,
+      next_cursor,
+      total,
+    })
+  }
 
-    println!("Records: {:?}", records);
-
-    Ok(
-      records.iter().map(|r| {
-        TaskDetail {
-          task_uuid: r.task_uuid.to_string(),
-          title: r.title.to_string(),
-          description: r.description.to_string(),
-          status: TaskStatus::from_str(&r.status).unwrap(),
-          user_name: user.clone(),
-          created_at: r.created_at.to_string(),
-        }
-      }).collect()
+  async fn search_tasks(&self, q: &str, limit: Option<i64>, user: String) -> Result<Vec<TaskSearchHit>, DBError> {
+    if q.trim().is_empty() {
+      return Err(DBError::Validation("Search query must not be empty".to_string()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // The to_tsvector expressions must match the indexes in 002_task_search and 013_structured_tracking.
+    let records: Vec<SearchRow> = sqlx::query_as(
+        r#"
+        SELECT t.*, task_blocked(t.task_uuid) AS blocked, task_label_list(t.task_uuid) AS labels,
+               status_category(t.project_uuid, t.user_username, t.status) AS status_category,
+               ts_rank(to_tsvector('english', t.title || ' ' || t.description), q) + COALESCE(h.rank, 0) AS rank,
+               ts_headline('english', t.title, q, 'StartSel=<mark>, StopSel=</mark>, HighlightAll=true') AS title_highlight,
+               ts_headline('english', t.description, q, 'StartSel=<mark>, StopSel=</mark>') AS description_highlight,
+               h.highlight AS tracking_highlight
+        FROM tasks t
+        CROSS JOIN websearch_to_tsquery('english', $2) q
+        LEFT JOIN LATERAL (
+            SELECT ts_rank(to_tsvector('english', tracking_text(tr.message, tr.changes)), q) AS rank,
+                   ts_headline('english', tracking_text(tr.message, tr.changes), q, 'StartSel=<mark>, StopSel=</mark>') AS highlight
+            FROM tracking tr
+            WHERE tr.task_task_uuid = t.task_uuid
+              AND to_tsvector('english', tracking_text(tr.message, tr.changes)) @@ q
+            ORDER BY rank DESC
+            LIMIT 1
+        ) h ON true
+        WHERE task_role(t, $1) IS NOT NULL
+          AND (to_tsvector('english', t.title || ' ' || t.description) @@ q OR h.rank IS NOT NULL)
+        ORDER BY rank DESC, t.created_at DESC
+        LIMIT $3
+        "#
     )
+    .bind(user)
+    .bind(q)
+    .bind(limit)
+    .fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(|r| {
+      Ok(TaskSearchHit {
+        task: r.task.into_detail()?,
+        rank: r.rank,
+        title_highlight: r.title_highlight,
+        description_highlight: r.description_highlight,
+        tracking_highlight: r.tracking_highlight,
+      })
+    }).collect()
   }
 
-  async fn create_task(&self, task: Task, user: String) -> Result<TaskDetail, DBError> {
-    let record = sqlx::query!(
+  async fn create_task(&self, uow: &mut UnitOfWork, task: Task, user: String) -> Result<TaskDetail, DBError> {
+    let project_uuid = task.project_uuid.as_deref().map(parse_uuid).transpose()?;
+    let parent_uuid = task.parent_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    let due_at = task.due_at.as_deref().map(parse_due_at).transpose()?;
+    check_remind_offsets(&task.remind_offsets)?;
+    let recurrence = task.recurrence.as_deref().map(canonical_recurrence).transpose()?;
+
+    let parent = match parent_uuid {
+      Some(parent_uuid) => Some(Self::check_parent(uow.conn()?, parent_uuid, &user).await?),
+      None => None,
+    };
+    let scope = parent.map_or(project_uuid, |parent| parent.project_uuid);
+    Self::check_transition(uow.conn()?, scope, &user, None, &task.status).await?;
+
+    // Project tasks can only be created by owners and editors of the project. Subtasks take the
+    // project of their parent and go last among its subtasks. New tasks go last in their column.
+    let record = sqlx::query_as!(
+        TaskRow,
         r#"
-        INSERT INTO tasks (title, description, status, user_username)
-        VALUES ($1, $2, $3, $4)
-        RETURNING *
+        INSERT INTO tasks (
+            title, description, status, user_username, project_uuid, due_at, remind_offsets, recurrence,
+            parent_task_uuid, subtask_position, priority, position
+        )
+        SELECT $1, $2, $3, $4, target.project_uuid, $6, $7, $8, $9,
+               COALESCE((SELECT MAX(subtask_position) + 1 FROM tasks WHERE parent_task_uuid = $9), 0),
+               $10, COALESCE((
+                   SELECT MAX(s.position) FROM tasks s
+                   WHERE task_board(s.project_uuid, s.user_username) = task_board(target.project_uuid, $4)
+                     AND s.status = $3::varchar AND s.deleted_at IS NULL
+               ), 0) + $11
+        FROM (
+            SELECT CASE WHEN $9::uuid IS NULL THEN $5::uuid
+                        ELSE (SELECT project_uuid FROM tasks WHERE task_uuid = $9) END AS project_uuid
+        ) target
+        WHERE target.project_uuid IS NULL OR project_role(target.project_uuid, $4) IN ('owner', 'editor')
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
         "#,
         task.title,
         task.description,
         task.status.to_string(),
-        user
-    ).fetch_one(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
-    })?;
+        user,
+        project_uuid,
+        due_at,
+        &task.remind_offsets,
+        recurrence,
+        parent_uuid,
+        task.priority.to_string(),
+        POSITION_STEP
+    ).fetch_optional(uow.conn()?).await.map_err(DBError::from)?;
 
-    Ok(
-      TaskDetail {
-        task_uuid: record.task_uuid.to_string(),
-        title: record.title,
-        description: record.description,
-        status: TaskStatus::from_str(&record.status)?,
-        user_name: user,
-        created_at: record.created_at.to_string(),
-      }
-    )
+    record
+      .ok_or_else(|| DBError::Forbidden("Only project owners and editors can add tasks".to_string()))?
+      .into_detail()
   }
 
   async fn get_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
-    let uuid = sqlx::types::Uuid::parse_str(task_uuid).map_err(|e| {
-      DBError::InvalidInput(e.to_string())
-    })?;
+    self.get_row(task_uuid, user).await?.into_detail()
+  }
+
+  async fn update_task(&self, uow: &mut UnitOfWork, task: TaskUpdateReq, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(&task.task_uuid)?;
+    let due_at = task.due_at.map_value(|due_at| parse_due_at(&due_at)).transpose()?;
+    if let Some(remind_offsets) = &task.remind_offsets {
+      check_remind_offsets(remind_offsets)?;
+    }
+    let recurrence = task.recurrence.map_value(|rule| canonical_recurrence(&rule)).transpose()?;
+    let assignee: Option<Option<String>> = task.assignee.into();
+    let previous = fetch_row_for_update(uow.conn()?, &task.task_uuid, &user).await?;
+    check_version(&previous, version)?;
+
+    // Only the fields of the patch are set; `version = version` keeps the list valid when it is empty.
+    // Unchanged values leave the row as it was, so they don't bump the version.
+    let mut qb = QueryBuilder::new("UPDATE tasks SET version = version");
+    if let Some(title) = task.title {
+      qb.push(", title = ").push_bind(title);
+    }
+    if let Some(description) = task.description {
+      qb.push(", description = ").push_bind(description);
+    }
+    if let Some(status) = &task.status {
+      qb.push(", status = ").push_bind(status.to_string())
+        .push(", position = CASE WHEN status = ").push_bind(status.to_string())
+        .push(" THEN position ELSE COALESCE((SELECT MAX(t.position) FROM tasks t")
+        .push(" WHERE task_board(t.project_uuid, t.user_username) = task_board(tasks.project_uuid, tasks.user_username)")
+        .push(" AND t.deleted_at IS NULL AND t.status = ").push_bind(status.to_string())
+        .push("), 0) + ").push_bind(POSITION_STEP).push(" END");
+    }
+    if let Some(priority) = task.priority {
+      qb.push(", priority = ").push_bind(priority.to_string());
+    }
+    if let Some(due_at) = Option::<Option<OffsetDateTime>>::from(due_at) {
+      qb.push(", due_at = ").push_bind(due_at);
+    }
+    if let Some(remind_offsets) = task.remind_offsets {
+      qb.push(", remind_offsets = ").push_bind(remind_offsets);
+    }
+    if let Some(recurrence) = Option::<Option<String>>::from(recurrence) {
+      qb.push(", recurrence = ").push_bind(recurrence);
+    }
+    if let Some(assignee) = &assignee {
+      qb.push(", assignee = ").push_bind(assignee.clone());
+    }
+
+    qb.push(" WHERE task_uuid = ").push_bind(uuid)
+      .push(" AND task_role(tasks, ").push_bind(user.clone()).push(") IN ('owner', 'editor')");
+    if let Some(status) = &task.status {
+      qb.push(" AND workflow_allows(project_uuid, user_username, status, ").push_bind(status.to_string()).push(")")
+        .push(" AND (status = ").push_bind(status.to_string())
+        .push(" OR status_category(project_uuid, user_username, ").push_bind(status.to_string()).push(") = 'todo'")
+        .push(" OR NOT task_blocked(task_uuid))");
+    }
+    if let Some(Some(assignee)) = &assignee {
+      qb.push(" AND EXISTS(SELECT 1 FROM users WHERE username = ").push_bind(assignee.clone()).push(")")
+        .push(" AND (project_uuid IS NULL OR project_role(project_uuid, ").push_bind(assignee.clone()).push(") IS NOT NULL)");
+    }
+    qb.push(
+      " RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels, \
+       status_category(project_uuid, user_username, status) AS status_category"
+    );
+
+    let record: Option<TaskRow> = qb.build_query_as().fetch_optional(uow.conn()?).await.map_err(DBError::from)?;
+
+    match record {
+      Some(record) => Ok((previous.into_detail()?, record.into_detail()?)),
+      None => Err(Self::not_patchable(uow.conn()?, uuid, task.status.as_ref(), assignee.flatten(), user).await),
+    }
+  }
 
-    let records = sqlx::query!(
+  async fn update_task_status(&self, uow: &mut UnitOfWork, task_status: TaskStatus, task_uuid: String, version: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(&task_uuid)?;
+    let previous = fetch_row_for_update(uow.conn()?, &task_uuid, &user).await?;
+    check_version(&previous, version)?;
+
+    let record = sqlx::query_as!(
+        TaskRow,
         r#"
-        SELECT * FROM tasks WHERE task_uuid = $1 AND user_username = $2
+        UPDATE tasks
+        SET status = $1,
+            position = CASE WHEN $1 = status THEN position
+                            ELSE COALESCE((
+                                SELECT MAX(t.position) FROM tasks t
+                                WHERE task_board(t.project_uuid, t.user_username) = task_board(tasks.project_uuid, tasks.user_username)
+                                  AND t.status = $1 AND t.deleted_at IS NULL
+                            ), 0) + $4 END
+        WHERE task_uuid = $2 AND task_role(tasks, $3) IN ('owner', 'editor')
+          AND workflow_allows(project_uuid, user_username, status, $1)
+          AND ($1 = status OR status_category(project_uuid, user_username, $1) = 'todo' OR NOT task_blocked(task_uuid))
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
         "#,
+        task_status.to_string(),
         uuid,
-        user
-    ).fetch_all(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
-    })?;
+        user,
+        POSITION_STEP
+    ).fetch_optional(uow.conn()?).await.map_err(DBError::from)?;
 
-    let r = &records[0];
+    match record {
+      Some(record) => Ok((previous.into_detail()?, record.into_detail()?)),
+      None => Err(Self::not_updatable(uow.conn()?, uuid, &task_status, user).await),
+    }
+  }
+
+  async fn delete_task(&self, uow: &mut UnitOfWork, task_uuid: String, cascade: bool, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+    let uuid = parse_uuid(&task_uuid)?;
 
-    Ok(
-      TaskDetail {
-        task_uuid: r.task_uuid.to_string(),
-        title: r.title.to_string(),
-        description: r.description.to_string(),
-        status: TaskStatus::from_str(&r.status).unwrap(),
-        user_name: "".to_string(),
-        created_at: r.created_at.to_string(),
+    if !cascade {
+      fetch_row(uow.conn()?, &task_uuid, &user).await?;
+      let subtasks = sqlx::query_scalar!(
+          r#"
+          SELECT COUNT(*) AS "count!" FROM tasks WHERE parent_task_uuid = $1 AND deleted_at IS NULL
+          "#,
+          uuid
+      ).fetch_one(uow.conn()?).await.map_err(DBError::from)?;
+
+      if subtasks > 0 {
+        return Err(DBError::Conflict(format!(
+          "Task {task_uuid} has {subtasks} subtasks, delete them first or pass cascade=true"
+        )));
       }
-    )
-  } 
+    }
 
-  async fn update_task(&self, task: TaskUpdateReq, user: String) -> Result<TaskDetail, DBError> {
-    let uuid = sqlx::types::Uuid::parse_str(&task.task_uuid).map_err(|e| {
-      DBError::InvalidInput(e.to_string())
-    })?;
+    // The subtasks share the deletion time of the task, which is how restoring finds them again.
+    let records = sqlx::query_as!(
+        TaskRow,
+        r#"
+        WITH RECURSIVE target AS (
+            SELECT task_uuid FROM tasks
+            WHERE task_uuid = $1 AND task_role(tasks, $2) IN ('owner', 'editor')
+            UNION ALL
+            SELECT t.task_uuid FROM tasks t JOIN target ON t.parent_task_uuid = target.task_uuid
+            WHERE t.deleted_at IS NULL
+        )
+        UPDATE tasks SET deleted_at = now()
+        WHERE task_uuid IN (SELECT task_uuid FROM target)
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        "#,
+        uuid,
+        user
+    ).fetch_all(uow.conn()?).await.map_err(DBError::from)?;
 
-    let record = sqlx::query!(
+    if records.is_empty() {
+      return Err(Self::not_editable(uow.conn()?, uuid, user).await);
+    }
+
+    split_subtree(uuid, records)
+  }
+
+  async fn list_trash(&self, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let records = sqlx::query_as!(
+        TaskRow,
         r#"
-        UPDATE tasks
-        SET title = $1, description = $2, status = $3
-        WHERE task_uuid = $4 AND user_username = $5
-        RETURNING *
+        SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        FROM tasks
+        WHERE deleted_at IS NOT NULL AND task_member_role(tasks, $1) IS NOT NULL
+        ORDER BY deleted_at DESC, subtask_position, created_at
+        "#,
+        user
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(TaskRow::into_detail).collect()
+  }
+
+  async fn get_trashed_task(&self, task_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+
+    let record = sqlx::query_as!(
+        TaskRow,
+        r#"
+        SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        FROM tasks
+        WHERE task_uuid = $1 AND deleted_at IS NOT NULL AND task_member_role(tasks, $2) IS NOT NULL
         "#,
-        task.title,
-        task.description,
-        task.status.to_string(),
         uuid,
         user
-    ).fetch_one(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
-    })?;
+    ).fetch_optional(&self.db).await.map_err(DBError::from)?;
 
-    Ok(
-      TaskDetail {
-        task_uuid: record.task_uuid.to_string(),
-        title: record.title,
-        description: record.description,
-        status: TaskStatus::from_str(&record.status)?,
-        user_name: user,
-        created_at: record.created_at.to_string(),
-      }
-    )
+    record
+      .ok_or_else(|| DBError::NotFound(format!("Task {task_uuid} not found in the trash")))?
+      .into_detail()
   }
 
-  async fn update_task_status(&self, task_status: TaskStatus, task_uuid: String, user: String) -> Result<TaskDetail, DBError> {
-    let uuid = sqlx::types::Uuid::parse_str(&task_uuid).map_err(|e| {
-      DBError::InvalidInput(e.to_string())
-    })?;
+  async fn restore_task(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<(TaskDetail, Vec<TaskDetail>), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let tx = uow.conn()?;
 
-    let record = sqlx::query!(
+    let trashed = sqlx::query!(
         r#"
-        UPDATE tasks
-        SET status = $1
-        WHERE task_uuid = $2 AND user_username = $3
-        RETURNING *
+        SELECT t.deleted_at AS "deleted_at!", task_member_role(t, $2) AS role, p.task_uuid AS "trashed_parent?"
+        FROM tasks t
+        LEFT JOIN tasks p ON p.task_uuid = t.parent_task_uuid AND p.deleted_at IS NOT NULL
+        WHERE t.task_uuid = $1 AND t.deleted_at IS NOT NULL
+        FOR UPDATE OF t
         "#,
-        task_status.to_string(),
         uuid,
         user
-    ).fetch_one(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
-    })?;
-    Ok(
-      TaskDetail {
-        task_uuid: record.task_uuid.to_string(),
-        title: record.title,
-        description: record.description,
-        status: TaskStatus::from_str(&record.status)?,
-        user_name: user,
-        created_at: record.created_at.to_string(),
-      }
+    ).fetch_optional(&mut *tx).await.map_err(DBError::from)?;
+
+    let trashed = match trashed {
+      Some(trashed) if trashed.role.is_some() => trashed,
+      _ => return Err(DBError::NotFound(format!("Task {task_uuid} not found in the trash"))),
+    };
+    if !matches!(trashed.role.as_deref(), Some("owner" | "editor")) {
+      return Err(DBError::Forbidden("Viewers cannot modify tasks".to_string()));
+    }
+    if let Some(parent_uuid) = trashed.trashed_parent {
+      return Err(DBError::Conflict(format!(
+        "Task {task_uuid} is a subtask of task {parent_uuid}, which is in the trash too, restore that one first"
+      )));
+    }
+
+    // Subtasks deleted on their own before the task stay in the trash.
+    let records = sqlx::query_as!(
+        TaskRow,
+        r#"
+        WITH RECURSIVE target AS (
+            SELECT task_uuid FROM tasks WHERE task_uuid = $1
+            UNION ALL
+            SELECT t.task_uuid FROM tasks t JOIN target ON t.parent_task_uuid = target.task_uuid
+            WHERE t.deleted_at = $2
+        )
+        UPDATE tasks SET deleted_at = NULL
+        WHERE task_uuid IN (SELECT task_uuid FROM target)
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        "#,
+        uuid,
+        trashed.deleted_at
+    ).fetch_all(&mut *tx).await.map_err(DBError::from)?;
+
+    split_subtree(uuid, records)
+  }
+
+  async fn purge_trash(&self, retention: Duration) -> Result<u64, DBError> {
+    // Subtasks go with their parent even when they were deleted later, they could not be restored anymore.
+    let result = sqlx::query!(
+        r#"
+        WITH RECURSIVE target AS (
+            SELECT task_uuid FROM tasks WHERE deleted_at < now() - make_interval(secs => $1)
+            UNION
+            SELECT t.task_uuid FROM tasks t JOIN target ON t.parent_task_uuid = target.task_uuid
+        ), history AS (
+            DELETE FROM tracking WHERE task_task_uuid IN (SELECT task_uuid FROM target)
+        )
+        DELETE FROM tasks WHERE task_uuid IN (SELECT task_uuid FROM target)
+        "#,
+        retention.as_secs_f64()
+    ).execute(&self.db).await.map_err(DBError::from)?;
+
+    Ok(result.rows_affected())
+  }
+
+  async fn get_subtree(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let root = self.get_row(task_uuid, user).await?;
+
+    let records: Vec<TaskRow> = sqlx::query_as(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT t.*, 1 AS depth FROM tasks t WHERE t.parent_task_uuid = $1 AND t.deleted_at IS NULL
+            UNION ALL
+            SELECT t.*, subtree.depth + 1 FROM tasks t JOIN subtree ON t.parent_task_uuid = subtree.task_uuid
+            WHERE t.deleted_at IS NULL
+        )
+        SELECT *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        FROM subtree ORDER BY depth, subtask_position, created_at
+        "#
     )
+    .bind(root.task_uuid)
+    .fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(TaskRow::into_detail).collect()
   }
 
-  async fn delete_task(&self, task_uuid: String, user: String) -> Result<(), DBError> {
-    let uuid = sqlx::types::Uuid::parse_str(&task_uuid).map_err(|e| {
-      DBError::InvalidInput(e.to_string())
-    })?;
+  async fn move_to_parent(&self, uow: &mut UnitOfWork, task_uuid: &str, parent_task_uuid: Option<String>, position: Option<i32>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let parent_uuid = parent_task_uuid.as_deref().map(parse_uuid).transpose()?;
+
+    let tx = uow.conn()?;
+    let task = fetch_row_for_update(&mut *tx, task_uuid, &user).await?;
+    if !matches!(Self::role_of(&mut *tx, uuid, user.clone()).await?.as_deref(), Some("owner" | "editor")) {
+      return Err(Self::not_editable(tx, uuid, user).await);
+    }
+
+    let mut siblings = Vec::new();
+
+    if let Some(parent_uuid) = parent_uuid {
+      let parent = Self::check_parent(&mut *tx, parent_uuid, &user).await?;
+      let same_owner = task.project_uuid.is_some() || parent.user_username == task.user_username;
+      if parent.project_uuid != task.project_uuid || !same_owner {
+        return Err(DBError::Validation("Subtasks must belong to the same project as their parent".to_string()));
+      }
+
+      let is_descendant = sqlx::query_scalar!(
+          r#"
+          WITH RECURSIVE subtree AS (
+              SELECT task_uuid FROM tasks WHERE task_uuid = $1
+              UNION ALL
+              SELECT t.task_uuid FROM tasks t JOIN subtree ON t.parent_task_uuid = subtree.task_uuid
+          )
+          SELECT EXISTS(SELECT 1 FROM subtree WHERE task_uuid = $2) AS "exists!"
+          "#,
+          uuid,
+          parent_uuid
+      ).fetch_one(&mut *tx).await.map_err(DBError::from)?;
+      if is_descendant {
+        return Err(DBError::Validation("A task cannot be moved below itself or one of its subtasks".to_string()));
+      }
+
+      siblings = sqlx::query_scalar!(
+          r#"
+          SELECT task_uuid FROM tasks
+          WHERE parent_task_uuid = $1 AND task_uuid <> $2 AND deleted_at IS NULL
+          ORDER BY subtask_position, created_at
+          FOR UPDATE
+          "#,
+          parent_uuid,
+          uuid
+      ).fetch_all(&mut *tx).await.map_err(DBError::from)?;
+    }
+
+    // Renumber the new siblings so positions stay contiguous.
+    let index = position.map_or(siblings.len(), |p| (p.max(0) as usize).min(siblings.len()));
+    siblings.insert(index, uuid);
+    let positions: Vec<i32> = (0..siblings.len() as i32).collect();
+
+    sqlx::query!(
+        r#"
+        UPDATE tasks SET parent_task_uuid = $1 WHERE task_uuid = $2
+        "#,
+        parent_uuid,
+        uuid
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
 
     sqlx::query!(
         r#"
-        DELETE FROM tasks WHERE task_uuid = $1 AND user_username = $2
+        UPDATE tasks SET subtask_position = v.position
+        FROM unnest($1::uuid[], $2::int[]) AS v(task_uuid, position)
+        WHERE tasks.task_uuid = v.task_uuid
+        "#,
+        &siblings,
+        &positions
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+
+    let moved = fetch_row(uow.conn()?, task_uuid, &user).await?;
+    Ok((task.into_detail()?, moved.into_detail()?))
+  }
+
+  async fn move_task(&self, uow: &mut UnitOfWork, task_uuid: &str, status: TaskStatus, after_task_uuid: Option<String>, before_task_uuid: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let after = after_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    let before = before_task_uuid.as_deref().map(parse_uuid).transpose()?;
+    if after == Some(uuid) || before == Some(uuid) {
+      return Err(DBError::Validation("A task cannot be moved next to itself".to_string()));
+    }
+
+    let tx = uow.conn()?;
+    let moved = fetch_row(&mut *tx, task_uuid, &user).await?;
+    if !matches!(Self::role_of(&mut *tx, uuid, user.clone()).await?.as_deref(), Some("owner" | "editor")) {
+      return Err(Self::not_editable(tx, uuid, user).await);
+    }
+
+    // Positions are derived from the neighbours, so moves into the same column of a board must not interleave.
+    sqlx::query!(
+        "SELECT pg_advisory_xact_lock(hashtext('task_position:' || task_board($1, $2) || ':' || $3))",
+        moved.project_uuid,
+        moved.user_username,
+        status.to_string()
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+    // Locked after the column, the order in which renumbering the column takes its locks.
+    let moved = fetch_row_for_update(&mut *tx, task_uuid, &user).await?;
+
+    let mut position = free_position(&mut *tx, &moved, &status, after, before, &user).await?;
+    if position.is_none() {
+      renumber_column(&mut *tx, &moved, &status).await?;
+      position = free_position(&mut *tx, &moved, &status, after, before, &user).await?;
+    }
+    let position = position.ok_or_else(|| DBError::Validation(format!(
+      "Task {} does not come before task {} in the {status} column",
+      after_task_uuid.unwrap_or_default(),
+      before_task_uuid.unwrap_or_default()
+    )))?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE tasks SET status = $1, position = $2
+        WHERE task_uuid = $3 AND workflow_allows(project_uuid, user_username, status, $1)
+          AND ($1 = status OR status_category(project_uuid, user_username, $1) = 'todo' OR NOT task_blocked(task_uuid))
+        "#,
+        status.to_string(),
+        position,
+        uuid
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      return Err(Self::not_updatable(tx, uuid, &status, user).await);
+    }
+
+    let task = fetch_row(uow.conn()?, task_uuid, &user).await?;
+    Ok((moved.into_detail()?, task.into_detail()?))
+  }
+
+  async fn add_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let blocker = parse_uuid(blocker_uuid)?;
+    if uuid == blocker {
+      return Err(DBError::Validation("A task cannot be blocked by itself".to_string()));
+    }
+
+    let tx = uow.conn()?;
+    fetch_row(&mut *tx, task_uuid, &user).await?;
+    if !matches!(Self::role_of(&mut *tx, uuid, user.clone()).await?.as_deref(), Some("owner" | "editor")) {
+      return Err(Self::not_editable(tx, uuid, user).await);
+    }
+    fetch_row(&mut *tx, blocker_uuid, &user).await
+      .map_err(|_| DBError::NotFound(format!("Blocker task {blocker_uuid} not found")))?;
+
+    // Cycle checks look at the whole graph, so concurrent additions must not interleave.
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext('task_dependencies'))")
+      .execute(&mut *tx).await.map_err(DBError::from)?;
+
+    let creates_cycle = sqlx::query_scalar!(
+        r#"
+        WITH RECURSIVE chain AS (
+            SELECT blocker_uuid FROM task_dependencies WHERE task_uuid = $1
+            UNION
+            SELECT d.blocker_uuid FROM task_dependencies d JOIN chain ON d.task_uuid = chain.blocker_uuid
+        )
+        SELECT EXISTS(SELECT 1 FROM chain WHERE blocker_uuid = $2) AS "exists!"
+        "#,
+        blocker,
+        uuid
+    ).fetch_one(&mut *tx).await.map_err(DBError::from)?;
+    if creates_cycle {
+      return Err(DBError::Conflict(format!(
+        "Task {blocker_uuid} already depends on task {task_uuid}, the dependency would create a cycle"
+      )));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO task_dependencies (task_uuid, blocker_uuid, created_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
         "#,
         uuid,
+        blocker,
         user
-    ).execute(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
+    ).execute(&mut *tx).await.map_err(|e| match DBError::from(e) {
+      // The blocker was purged since it was checked.
+      DBError::Validation(_) => DBError::NotFound(format!("Blocker task {blocker_uuid} not found")),
+      e => e,
     })?;
 
+    fetch_row(uow.conn()?, task_uuid, &user).await?.into_detail()
+  }
+
+  async fn remove_dependency(&self, uow: &mut UnitOfWork, task_uuid: &str, blocker_uuid: &str, user: String) -> Result<TaskDetail, DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let blocker = parse_uuid(blocker_uuid)?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM task_dependencies d
+        USING tasks t
+        WHERE d.task_uuid = $1 AND d.blocker_uuid = $2
+          AND t.task_uuid = d.task_uuid AND task_role(t, $3) IN ('owner', 'editor')
+        "#,
+        uuid,
+        blocker,
+        user
+    ).execute(uow.conn()?).await.map_err(DBError::from)?;
+
+    if result.rows_affected() == 0 {
+      return match Self::role_of(uow.conn()?, uuid, user.clone()).await?.as_deref() {
+        Some("owner" | "editor") => Err(DBError::NotFound(format!(
+          "Task {task_uuid} is not blocked by task {blocker_uuid}"
+        ))),
+        _ => Err(Self::not_editable(uow.conn()?, uuid, user).await),
+      };
+    }
+
+    fetch_row(uow.conn()?, task_uuid, &user).await?.into_detail()
+  }
+
+  async fn get_blockers(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let task = self.get_row(task_uuid, user.clone()).await?;
+
+    let records = sqlx::query_as!(
+        TaskRow,
+        r#"
+        SELECT t.*, task_blocked(t.task_uuid) AS blocked, task_label_list(t.task_uuid) AS labels,
+               status_category(t.project_uuid, t.user_username, t.status) AS status_category
+        FROM task_dependencies d
+        JOIN tasks t ON t.task_uuid = d.blocker_uuid
+        WHERE d.task_uuid = $1 AND task_role(t, $2) IS NOT NULL
+        ORDER BY d.created_at
+        "#,
+        task.task_uuid,
+        user
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(TaskRow::into_detail).collect()
+  }
+
+  async fn get_dependents(&self, task_uuid: &str, user: String) -> Result<Vec<TaskDetail>, DBError> {
+    let task = self.get_row(task_uuid, user.clone()).await?;
+
+    let records = sqlx::query_as!(
+        TaskRow,
+        r#"
+        SELECT t.*, task_blocked(t.task_uuid) AS blocked, task_label_list(t.task_uuid) AS labels,
+               status_category(t.project_uuid, t.user_username, t.status) AS status_category
+        FROM task_dependencies d
+        JOIN tasks t ON t.task_uuid = d.task_uuid
+        WHERE d.blocker_uuid = $1 AND task_role(t, $2) IS NOT NULL
+        ORDER BY d.created_at
+        "#,
+        task.task_uuid,
+        user
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(TaskRow::into_detail).collect()
+  }
+
+  async fn assign_task(&self, uow: &mut UnitOfWork, task_uuid: &str, assignee: Option<String>, user: String) -> Result<(TaskDetail, TaskDetail), DBError> {
+    let uuid = parse_uuid(task_uuid)?;
+    let previous = fetch_row_for_update(uow.conn()?, task_uuid, &user).await?;
+
+    let record = sqlx::query_as!(
+        TaskRow,
+        r#"
+        UPDATE tasks
+        SET assignee = $2
+        WHERE task_uuid = $1 AND task_role(tasks, $3) IN ('owner', 'editor')
+          AND ($2::varchar IS NULL OR (
+            EXISTS(SELECT 1 FROM users WHERE username = $2)
+            AND (project_uuid IS NULL OR project_role(project_uuid, $2) IS NOT NULL)
+          ))
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        "#,
+        uuid,
+        assignee,
+        user
+    ).fetch_optional(uow.conn()?).await.map_err(DBError::from)?;
+
+    match record {
+      Some(record) => Ok((previous.into_detail()?, record.into_detail()?)),
+      None => match Self::role_of(uow.conn()?, uuid, user.clone()).await?.as_deref() {
+        // The caller may edit the task, so it is the assignee that was rejected.
+        Some("owner" | "editor") => Err(DBError::Validation(format!(
+          "{} is not a user who can be assigned task {task_uuid}",
+          assignee.unwrap_or_default()
+        ))),
+        _ => Err(Self::not_editable(uow.conn()?, uuid, user).await),
+      },
+    }
+  }
+
+  async fn schedule_next_occurrence(&self, uow: &mut UnitOfWork, task_uuid: &str, user: String) -> Result<Option<TaskDetail>, DBError> {
+    let task = fetch_row(uow.conn()?, task_uuid, &user).await?;
+    let Some(rule) = task.recurrence.as_deref() else {
+      return Ok(None);
+    };
+    if task.status_category.as_deref() != Some("done") {
+      return Ok(None);
+    }
+
+    let anchor = task.anchor();
+    let recurrence = Recurrence::parse(rule)?.pinned(anchor);
+    let Some(next_due) = recurrence.next_after(anchor, task.occurrence as u32) else {
+      return Ok(None);
+    };
+
+    // Reminders and assignment carry over; the rule is stored pinned so later occurrences keep the day.
+    // The new occurrence starts in the first not-started status of the workflow.
+    let record = sqlx::query_as!(
+        TaskRow,
+        r#"
+        INSERT INTO tasks (
+            title, description, status, user_username, project_uuid, assignee,
+            due_at, remind_offsets, recurrence, series_uuid, occurrence, parent_task_uuid, subtask_position,
+            priority, position
+        )
+        SELECT title, description, initial_status(project_uuid, user_username), user_username, project_uuid, assignee,
+               $2, remind_offsets, $3, COALESCE(series_uuid, task_uuid), occurrence + 1, parent_task_uuid,
+               COALESCE((SELECT MAX(subtask_position) + 1 FROM tasks s WHERE s.parent_task_uuid = t.parent_task_uuid), 0),
+               priority,
+               COALESCE((
+                   SELECT MAX(s.position) FROM tasks s
+                   WHERE task_board(s.project_uuid, s.user_username) = task_board(t.project_uuid, t.user_username)
+                     AND s.status = initial_status(t.project_uuid, t.user_username) AND s.deleted_at IS NULL
+               ), 0) + $4
+        FROM tasks t WHERE task_uuid = $1
+        ON CONFLICT (series_uuid, occurrence) WHERE series_uuid IS NOT NULL DO NOTHING
+        RETURNING *, task_blocked(task_uuid) AS blocked, task_label_list(task_uuid) AS labels,
+               status_category(project_uuid, user_username, status) AS status_category
+        "#,
+        task.task_uuid,
+        next_due,
+        recurrence.to_string(),
+        POSITION_STEP
+    ).fetch_optional(uow.conn()?).await.map_err(DBError::from)?;
+
+    record.map(TaskRow::into_detail).transpose()
+  }
+
+  async fn preview_occurrences(&self, task_uuid: &str, count: usize, user: String) -> Result<Vec<String>, DBError> {
+    let task = self.get_row(task_uuid, user).await?;
+    let Some(rule) = task.recurrence.as_deref() else {
+      return Err(DBError::Validation(format!("Task {task_uuid} does not recur")));
+    };
+
+    Recurrence::parse(rule)?
+      .preview(task.anchor(), task.occurrence as u32, count)
+      .into_iter()
+      .map(format_timestamp)
+      .collect()
+  }
+
+  async fn claim_due_reminders(&self) -> Result<Vec<TaskReminder>, DBError> {
+    // Recording the notification and returning it in one statement keeps several app
+    // instances from sending the same reminder twice.
+    let records: Vec<ReminderRow> = sqlx::query_as(
+        r#"
+        WITH due AS (
+            SELECT t.task_uuid, t.due_at, 'reminder' AS kind, o.offset_secs
+            FROM tasks t
+            CROSS JOIN unnest(t.remind_offsets) AS o(offset_secs)
+            WHERE t.deleted_at IS NULL AND status_category(t.project_uuid, t.user_username, t.status) IS DISTINCT FROM 'done' AND t.due_at > now()
+              AND t.due_at - make_interval(secs => o.offset_secs) <= now()
+            UNION ALL
+            SELECT t.task_uuid, t.due_at, 'overdue', 0
+            FROM tasks t
+            WHERE t.deleted_at IS NULL AND status_category(t.project_uuid, t.user_username, t.status) IS DISTINCT FROM 'done' AND t.due_at <= now()
+        ), claimed AS (
+            INSERT INTO task_notifications (task_uuid, due_at, kind, offset_secs)
+            SELECT task_uuid, due_at, kind, offset_secs FROM due
+            ON CONFLICT DO NOTHING
+            RETURNING task_uuid, kind, offset_secs
+        )
+        SELECT t.*, task_blocked(t.task_uuid) AS blocked, task_label_list(t.task_uuid) AS labels,
+               status_category(t.project_uuid, t.user_username, t.status) AS status_category, c.kind, c.offset_secs
+        FROM claimed c
+        JOIN tasks t ON t.task_uuid = c.task_uuid
+        "#
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    records.into_iter().map(|r| {
+      let (kind, offset_secs) = match r.kind.as_str() {
+        "overdue" => (ReminderKind::Overdue, None),
+        _ => (ReminderKind::DueSoon, Some(r.offset_secs)),
+      };
+      Ok(TaskReminder {
+        kind,
+        offset_secs,
+        task: r.task.into_detail()?,
+      })
+    }).collect()
+  }
+}
+
+impl TasksDboImpl {
+  /// Checks that `user` may add subtasks to the parent. Personal tasks only take subtasks from their
+  /// owner, so every subtask stays visible to whoever sees the parent.
+  async fn check_parent(conn: &mut PgConnection, parent_uuid: Uuid, user: &str) -> Result<TaskRow, DBError> {
+    let parent = fetch_row(&mut *conn, &parent_uuid.to_string(), user).await
+      .map_err(|_| DBError::NotFound(format!("Parent task {parent_uuid} not found")))?;
+
+    let allowed = match Self::role_of(conn, parent_uuid, user.to_string()).await?.as_deref() {
+      Some("owner") => true,
+      Some("editor") => parent.project_uuid.is_some(),
+      _ => false,
+    };
+    if !allowed {
+      return Err(DBError::Forbidden(format!("Not allowed to add subtasks to task {parent_uuid}")));
+    }
+    Ok(parent)
+  }
+
+  /// A task visible to `user`.
+  async fn get_row(&self, task_uuid: &str, user: String) -> Result<TaskRow, DBError> {
+    fetch_row(&self.db, task_uuid, &user).await
+  }
+
+  /// Role of `user` on the task, `None` when it doesn't exist or is invisible to them.
+  async fn role_of(conn: &mut PgConnection, task_uuid: Uuid, user: String) -> Result<Option<String>, DBError> {
+    let role = sqlx::query_scalar!(
+        r#"
+        SELECT task_role(tasks, $2) FROM tasks WHERE task_uuid = $1
+        "#,
+        task_uuid,
+        user
+    ).fetch_optional(conn).await.map_err(DBError::from)?;
+
+    Ok(role.flatten())
+  }
+
+  /// Explains why a write matched no task: it is missing/invisible, or the caller is only a viewer.
+  async fn not_editable(conn: &mut PgConnection, task_uuid: Uuid, user: String) -> DBError {
+    match Self::role_of(conn, task_uuid, user).await {
+      Ok(Some(_)) => DBError::Forbidden("Viewers cannot modify tasks".to_string()),
+      Ok(None) => DBError::NotFound(format!("Task {task_uuid} not found")),
+      Err(e) => e,
+    }
+  }
+
+  /// Checks that the workflow of a task allows changing its status from `from` (`None` for new tasks) to `to`.
+  async fn check_transition(conn: &mut PgConnection, project_uuid: Option<Uuid>, owner: &str, from: Option<&str>, to: &TaskStatus) -> Result<(), DBError> {
+    let record = sqlx::query!(
+        r#"
+        SELECT workflow_allows($1, $2, $3, $4) AS "allowed!", status_category($1, $2, $4) IS NOT NULL AS "known!"
+        "#,
+        project_uuid,
+        owner,
+        from,
+        to.key()
+    ).fetch_one(conn).await.map_err(DBError::from)?;
+
+    match (record.allowed, record.known, from) {
+      (true, _, _) => Ok(()),
+      (false, false, _) | (false, _, None) => Err(DBError::Validation(format!("Status {to} is not part of the workflow"))),
+      (false, true, Some(from)) => Err(DBError::Conflict(format!("The workflow does not allow moving from {from} to {to}"))),
+    }
+  }
+
+  /// Like `not_editable`, but also reports status changes the workflow rejects and unfinished
+  /// blockers keeping the task from being started.
+  async fn not_updatable(conn: &mut PgConnection, task_uuid: Uuid, status: &TaskStatus, user: String) -> DBError {
+    match Self::check_status_change(&mut *conn, task_uuid, status, &user).await {
+      Err(e) => e,
+      Ok(()) => Self::not_editable(conn, task_uuid, user).await,
+    }
+  }
+
+  /// Like `not_updatable`, for a patch that may leave the status out or set an assignee.
+  async fn not_patchable(conn: &mut PgConnection, task_uuid: Uuid, status: Option<&TaskStatus>, assignee: Option<String>, user: String) -> DBError {
+    if let Some(status) = status {
+      if let Err(e) = Self::check_status_change(&mut *conn, task_uuid, status, &user).await {
+        return e;
+      }
+    }
+    match (assignee, Self::role_of(&mut *conn, task_uuid, user.clone()).await) {
+      // The caller may edit the task, so it is the assignee that was rejected.
+      (Some(assignee), Ok(Some(role))) if matches!(role.as_str(), "owner" | "editor") => DBError::Validation(format!(
+        "{assignee} is not a user who can be assigned task {task_uuid}"
+      )),
+      _ => Self::not_editable(conn, task_uuid, user).await,
+    }
+  }
+
+  async fn check_status_change(conn: &mut PgConnection, task_uuid: Uuid, status: &TaskStatus, user: &str) -> Result<(), DBError> {
+    if !matches!(Self::role_of(&mut *conn, task_uuid, user.to_string()).await?.as_deref(), Some("owner" | "editor")) {
+      return Ok(());
+    }
+    let task = fetch_row(&mut *conn, &task_uuid.to_string(), user).await?;
+    let owner = task.user_username.as_deref().unwrap_or_default();
+    Self::check_transition(&mut *conn, task.project_uuid, owner, Some(&task.status), status).await?;
+
+    let blockers = sqlx::query_scalar!(
+        r#"
+        SELECT d.blocker_uuid
+        FROM task_dependencies d
+        JOIN tasks b ON b.task_uuid = d.blocker_uuid AND b.deleted_at IS NULL
+        WHERE d.task_uuid = $1 AND status_category(b.project_uuid, b.user_username, b.status) IS DISTINCT FROM 'done'
+        ORDER BY d.created_at
+        "#,
+        task_uuid
+    ).fetch_all(conn).await.map_err(DBError::from)?;
+
+    if !blockers.is_empty() {
+      let blockers: Vec<String> = blockers.iter().map(Uuid::to_string).collect();
+      return Err(DBError::Conflict(format!(
+        "Task {task_uuid} is blocked by unfinished tasks: {}", blockers.join(", ")
+      )));
+    }
     Ok(())
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,105 @@
+use sqlx::PgPool;
+use crate::models::DBError;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait TokensDbo {
+    async fn create_refresh_token(&self, token_hash: String, user: String, ttl_secs: i64) -> Result<(), DBError>;
+    /// Revokes a live refresh token and returns its owner, so it can be exchanged exactly once.
+    async fn consume_refresh_token(&self, token_hash: String) -> Result<String, DBError>;
+    async fn revoke_refresh_token(&self, token_hash: String, user: String) -> Result<(), DBError>;
+    async fn revoke_access_token(&self, jti: String, exp: i64) -> Result<(), DBError>;
+    async fn is_access_token_revoked(&self, jti: String) -> Result<bool, DBError>;
+}
+
+#[derive(Debug)]
+pub struct TokensDboImpl {
+    db: PgPool,
+}
+
+impl TokensDboImpl {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+        }
+    }
+}
+
+#[async_trait]
+impl TokensDbo for TokensDboImpl {
+    async fn create_refresh_token(&self, token_hash: String, user: String, ttl_secs: i64) -> Result<(), DBError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, user_username, expires_at)
+            VALUES ($1, $2, now() + make_interval(secs => $3))
+            "#,
+            token_hash,
+            user,
+            ttl_secs as f64,
+        ).execute(&self.db).await.map_err(DBError::from)?;
+
+        Ok(())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: String) -> Result<String, DBError> {
+        let record = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = now()
+            WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > now()
+            RETURNING user_username
+            "#,
+            token_hash
+        ).fetch_optional(&self.db).await.map_err(DBError::from)?;
+
+        record
+            .map(|r| r.user_username)
+            .ok_or_else(|| DBError::UnAuthorized("refresh_token is invalid or expired".to_string()))
+    }
+
+    async fn revoke_refresh_token(&self, token_hash: String, user: String) -> Result<(), DBError> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = now()
+            WHERE token_hash = $1 AND user_username = $2 AND revoked_at IS NULL
+            "#,
+            token_hash,
+            user
+        ).execute(&self.db).await.map_err(DBError::from)?;
+
+        Ok(())
+    }
+
+    async fn revoke_access_token(&self, jti: String, exp: i64) -> Result<(), DBError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO revoked_tokens (jti, expires_at)
+            VALUES ($1, to_timestamp($2))
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+            jti,
+            exp as f64
+        ).execute(&self.db).await.map_err(DBError::from)?;
+
+        // Entries are only needed until the token would have expired anyway.
+        sqlx::query!(
+            r#"
+            DELETE FROM revoked_tokens WHERE expires_at < now()
+            "#
+        ).execute(&self.db).await.map_err(DBError::from)?;
+
+        Ok(())
+    }
+
+    async fn is_access_token_revoked(&self, jti: String) -> Result<bool, DBError> {
+        let record = sqlx::query!(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $1) AS "revoked!"
+            "#,
+            jti
+        ).fetch_one(&self.db).await.map_err(DBError::from)?;
+
+        Ok(record.revoked)
+    }
+}
@@ -1,12 +1,17 @@
+use std::str::FromStr;
 use sqlx::PgPool;
-use crate::models::{Tracking, TrackingDetail, DBError};
+use sqlx::types::Json;
+use time::PrimitiveDateTime;
+use crate::models::{FieldChange, Tracking, TrackingDetail, TrackingEventType, DBError};
+use crate::persistence::unit_of_work::UnitOfWork;
 use async_trait::async_trait;
 
 #[async_trait]
 pub trait TrackingDbo {
-    async fn create_tracking(&self, tracking: Tracking) -> Result<TrackingDetail, DBError>;
-    async fn get_tracking(&self, task_uuid: String) -> Result<Vec<TrackingDetail>, DBError>;
-    async fn delete_tracking(&self, task_uuid: String) -> Result<(), DBError>;
+    /// Records a history entry as part of `uow`, so it is only kept if the change it describes is.
+    async fn create_tracking(&self, uow: &mut UnitOfWork, tracking: Tracking) -> Result<TrackingDetail, DBError>;
+    /// History of the task, oldest first, optionally limited to one event type.
+    async fn get_tracking(&self, task_uuid: String, event_type: Option<TrackingEventType>) -> Result<Vec<TrackingDetail>, DBError>;
 }
 
 #[derive(Debug)]
@@ -22,73 +27,73 @@ impl TrackingDboImpl {
     }
 }
 
-#[async_trait]
-impl TrackingDbo for TrackingDboImpl {
-    async fn create_tracking(&self, tracking: Tracking) -> Result<TrackingDetail, DBError> {
-        let task_uuid = sqlx::types::Uuid::parse_str(&tracking.task_uuid).map_err(|e| {
-          DBError::InvalidInput(e.to_string())
-        })?;
-        
-        let record = sqlx::query!(
-            r#"
-            INSERT INTO tracking (task_task_uuid, status)
-            VALUES ($1, $2)
-            RETURNING *
-            "#,
-            task_uuid,
-            tracking.status,
-        ).fetch_one(&self.db).await.map_err(|e| {
-            DBError::Other(e.to_string())
-        })?;
+#[derive(sqlx::FromRow)]
+struct TrackingRow {
+    id: sqlx::types::Uuid,
+    task_task_uuid: Option<sqlx::types::Uuid>,
+    event_type: String,
+    actor: Option<String>,
+    changes: Json<Vec<FieldChange>>,
+    message: Option<String>,
+    created_at: PrimitiveDateTime,
+}
 
+impl TrackingRow {
+    fn into_detail(self) -> Result<TrackingDetail, DBError> {
         Ok(TrackingDetail {
-            id: record.id.to_string(),
-            task_uuid: tracking.task_uuid,
-            status: record.status.to_string(),
-            created_at: record.created_at.to_string(),
+            id: self.id.to_string(),
+            task_uuid: self.task_task_uuid.map(|uuid| uuid.to_string()).unwrap_or_default(),
+            event_type: TrackingEventType::from_str(&self.event_type)?,
+            actor: self.actor,
+            changes: self.changes.0,
+            message: self.message,
+            created_at: self.created_at.to_string(),
         })
     }
+}
 
-    async fn get_tracking(&self, task_uuid: String) -> Result<Vec<TrackingDetail>, DBError> {
-        let uuid = sqlx::types::Uuid::parse_str(&task_uuid).map_err(|e| {
+#[async_trait]
+impl TrackingDbo for TrackingDboImpl {
+    async fn create_tracking(&self, uow: &mut UnitOfWork, tracking: Tracking) -> Result<TrackingDetail, DBError> {
+        let task_uuid = sqlx::types::Uuid::parse_str(&tracking.task_uuid).map_err(|e| {
           DBError::InvalidInput(e.to_string())
         })?;
 
-        let records = sqlx::query!(
+        // Rows written in the same transaction would share CURRENT_TIMESTAMP, so stamp them with the
+        // wall clock to keep the history in write order.
+        let record = sqlx::query_as!(
+            TrackingRow,
             r#"
-            SELECT * FROM tracking WHERE task_task_uuid = $1
+            INSERT INTO tracking (task_task_uuid, event_type, actor, changes, created_at)
+            VALUES ($1, $2, $3, $4, clock_timestamp())
+            RETURNING id, task_task_uuid, event_type, actor, changes AS "changes: Json<Vec<FieldChange>>", message, created_at
             "#,
-            uuid
-        ).fetch_all(&self.db).await.map_err(|e| {
-            DBError::Other(e.to_string())
-        })?;
+            task_uuid,
+            tracking.event_type.to_string(),
+            tracking.actor,
+            Json(&tracking.changes) as _,
+        ).fetch_one(uow.conn()?).await.map_err(DBError::from)?;
 
-        Ok(
-            records.iter().map(|r| {
-                TrackingDetail {
-                    id: r.id.to_string(),
-                    task_uuid: task_uuid.clone(),
-                    status: r.status.to_string(),
-                    created_at: r.created_at.to_string(),
-                }
-            }).collect()
-        )
+        record.into_detail()
     }
 
-    async fn delete_tracking(&self, task_uuid: String) -> Result<(), DBError> {
+    async fn get_tracking(&self, task_uuid: String, event_type: Option<TrackingEventType>) -> Result<Vec<TrackingDetail>, DBError> {
         let uuid = sqlx::types::Uuid::parse_str(&task_uuid).map_err(|e| {
           DBError::InvalidInput(e.to_string())
         })?;
 
-        sqlx::query!(
+        let records = sqlx::query_as!(
+            TrackingRow,
             r#"
-            DELETE FROM tracking WHERE task_task_uuid = $1
+            SELECT id, task_task_uuid, event_type, actor, changes AS "changes: Json<Vec<FieldChange>>", message, created_at
+            FROM tracking
+            WHERE task_task_uuid = $1 AND ($2::varchar IS NULL OR event_type = $2)
+            ORDER BY created_at, id
             "#,
-            uuid
-        ).execute(&self.db).await.map_err(|e| {
-            DBError::Other(e.to_string())
-        })?;
+            uuid,
+            event_type.map(|event_type| event_type.to_string())
+        ).fetch_all(&self.db).await.map_err(DBError::from)?;
 
-        Ok(())
+        records.into_iter().map(TrackingRow::into_detail).collect()
     }
 }
@@ -0,0 +1,57 @@
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use crate::models::DBError;
+use crate::persistence::memory::{MemoryData, MemoryTransaction};
+
+/// A database transaction shared by several DBO calls, so a task change and the tracking rows
+/// describing it are committed together or not at all. Dropping it without `commit` rolls back.
+pub struct UnitOfWork {
+  tx: UnitOfWorkTx,
+}
+
+enum UnitOfWorkTx {
+  Postgres(Transaction<'static, Postgres>),
+  Memory(MemoryTransaction),
+}
+
+impl UnitOfWork {
+  pub async fn begin(db: &PgPool) -> Result<UnitOfWork, DBError> {
+    let tx = db.begin().await.map_err(DBError::from)?;
+    Ok(UnitOfWork {
+      tx: UnitOfWorkTx::Postgres(tx),
+    })
+  }
+
+  pub(crate) fn in_memory(tx: MemoryTransaction) -> UnitOfWork {
+    UnitOfWork {
+      tx: UnitOfWorkTx::Memory(tx),
+    }
+  }
+
+  pub async fn commit(self) -> Result<(), DBError> {
+    match self.tx {
+      UnitOfWorkTx::Postgres(tx) => tx.commit().await.map_err(DBError::from),
+      UnitOfWorkTx::Memory(tx) => {
+        tx.commit();
+        Ok(())
+      }
+    }
+  }
+
+  /// Connection of the transaction, for the queries taking part in it. Fails for a unit of work of
+  /// the in-memory store.
+  pub(crate) fn conn(&mut self) -> Result<&mut PgConnection, DBError> {
+    match &mut self.tx {
+      UnitOfWorkTx::Postgres(tx) => Ok(tx),
+      UnitOfWorkTx::Memory(_) => Err(DBError::Other("Postgres DBO used with a unit of work of the in-memory store".to_string())),
+    }
+  }
+
+  /// Data of the in-memory store, locked until the unit of work ends. Fails for a unit of work of
+  /// Postgres.
+  pub(crate) fn memory(&mut self) -> Result<&mut MemoryData, DBError> {
+    match &mut self.tx {
+      UnitOfWorkTx::Memory(tx) => Ok(tx.data()),
+      UnitOfWorkTx::Postgres(_) => Err(DBError::Other("In-memory DBO used with a unit of work of Postgres".to_string())),
+    }
+  }
+}
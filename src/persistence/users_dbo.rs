@@ -32,8 +32,9 @@ impl UsersDbo for UsersDboImpl {
         user.username,
         user.email,
         user.password,
-    ).fetch_one(&self.db).await.map_err(|e| {
-      DBError::Other(e.to_string())
+    ).fetch_one(&self.db).await.map_err(|e| match DBError::from(e) {
+      DBError::Conflict(_) => DBError::Conflict(format!("User {} already exists", user.username)),
+      e => e,
     })?;
 
     Ok(User {
@@ -50,10 +51,9 @@ impl UsersDbo for UsersDboImpl {
         WHERE username = $1
         "#,
         username
-    ).fetch_one(&self.db).await.map_err(|e| {
-      println!("Error: {}", e);
-      DBError::Other(e.to_string())
-    })?;
+    ).fetch_optional(&self.db).await.map_err(DBError::from)?;
+
+    let record = record.ok_or_else(|| DBError::NotFound(format!("User {username} not found")))?;
 
     Ok(UserDetail {
       username: record.username.to_string(),
@@ -0,0 +1,241 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use sqlx::{PgPool, Postgres, Transaction};
+use sqlx::types::Uuid;
+use async_trait::async_trait;
+use crate::models::{DBError, StatusCategory, TaskStatus, Workflow, WorkflowDetail, WorkflowStatus, WorkflowTransition};
+
+const MAX_NAME_LENGTH: usize = 100;
+
+#[async_trait]
+pub trait WorkflowsDbo {
+  /// Workflow of the project, or of the personal tasks of `user` without one. Scopes that never set
+  /// a workflow get the built-in one.
+  async fn get_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError>;
+  /// Replaces the workflow of the scope (project owners only). Statuses still used by tasks cannot be dropped.
+  async fn set_workflow(&self, project_uuid: Option<String>, workflow: Workflow, user: String) -> Result<WorkflowDetail, DBError>;
+  /// Goes back to the built-in workflow, provided no task uses a custom status anymore.
+  async fn reset_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError>;
+}
+
+pub struct WorkflowsDboImpl {
+  db: PgPool,
+}
+
+impl WorkflowsDboImpl {
+  pub fn new(db: PgPool) -> Self {
+    Self {
+      db,
+    }
+  }
+
+  /// Checks that `user` may see the scope, and also change it when `manage` is set.
+  async fn check_access(&self, project_uuid: Option<Uuid>, user: &str, manage: bool) -> Result<(), DBError> {
+    let Some(project_uuid) = project_uuid else {
+      return Ok(());
+    };
+    let role = sqlx::query_scalar!("SELECT project_role($1, $2)", project_uuid, user)
+      .fetch_one(&self.db).await.map_err(DBError::from)?;
+
+    match role.as_deref() {
+      None => Err(DBError::NotFound(format!("Project {project_uuid} not found"))),
+      Some("owner") => Ok(()),
+      Some(_) if manage => Err(DBError::Forbidden("Only project owners can change the workflow".to_string())),
+      Some(_) => Ok(()),
+    }
+  }
+
+  /// Statuses of tasks in the scope that are not among `keys`.
+  async fn statuses_in_use(tx: &mut Transaction<'_, Postgres>, project_uuid: Option<Uuid>, user: &str, keys: &[String]) -> Result<(), DBError> {
+    let missing = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT status FROM tasks
+        WHERE CASE WHEN $1::uuid IS NULL THEN project_uuid IS NULL AND user_username = $2 ELSE project_uuid = $1 END
+          AND status <> ALL($3)
+        ORDER BY status
+        "#,
+        project_uuid,
+        user,
+        keys
+    ).fetch_all(&mut **tx).await.map_err(DBError::from)?;
+
+    if !missing.is_empty() {
+      return Err(DBError::Conflict(format!("Statuses still used by tasks: {}", missing.join(", "))));
+    }
+    Ok(())
+  }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, DBError> {
+  Uuid::parse_str(value).map_err(|e| DBError::InvalidInput(e.to_string()))
+}
+
+/// Checks the statuses and transitions of a workflow before it is stored.
+pub(crate) fn check_workflow(workflow: &Workflow) -> Result<(), DBError> {
+  let mut keys = HashSet::new();
+  for status in &workflow.statuses {
+    let name = status.name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+      return Err(DBError::Validation(format!("Status names need 1 to {MAX_NAME_LENGTH} characters")));
+    }
+    if !keys.insert(status.key.key()) {
+      return Err(DBError::Validation(format!("Duplicate status {}", status.key)));
+    }
+  }
+  for category in [StatusCategory::Todo, StatusCategory::Done] {
+    if !workflow.statuses.iter().any(|status| status.category == category) {
+      return Err(DBError::Validation(format!("A workflow needs a status in the {category} category")));
+    }
+  }
+  for transition in &workflow.transitions {
+    for key in [&transition.from, &transition.to] {
+      if !keys.contains(key.key()) {
+        return Err(DBError::Validation(format!("Transition uses unknown status {key}")));
+      }
+    }
+  }
+  Ok(())
+}
+
+#[async_trait]
+impl WorkflowsDbo for WorkflowsDboImpl {
+  async fn get_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    self.check_access(project, &user, false).await?;
+
+    let workflow_uuid = sqlx::query_scalar!("SELECT workflow_of($1, $2)", project, user)
+      .fetch_one(&self.db).await.map_err(DBError::from)?;
+    let Some(workflow_uuid) = workflow_uuid else {
+      return Ok(WorkflowDetail::builtin(project_uuid));
+    };
+
+    let statuses = sqlx::query!(
+        r#"
+        SELECT key, name, category FROM workflow_statuses WHERE workflow_uuid = $1 ORDER BY position
+        "#,
+        workflow_uuid
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+    let transitions = sqlx::query!(
+        r#"
+        SELECT t.from_key, t.to_key
+        FROM workflow_transitions t
+        JOIN workflow_statuses f ON f.workflow_uuid = t.workflow_uuid AND f.key = t.from_key
+        JOIN workflow_statuses s ON s.workflow_uuid = t.workflow_uuid AND s.key = t.to_key
+        WHERE t.workflow_uuid = $1
+        ORDER BY f.position, s.position
+        "#,
+        workflow_uuid
+    ).fetch_all(&self.db).await.map_err(DBError::from)?;
+
+    Ok(WorkflowDetail {
+      workflow_uuid: Some(workflow_uuid.to_string()),
+      project_uuid,
+      statuses: statuses.into_iter().map(|r| {
+        Ok(WorkflowStatus {
+          key: TaskStatus::from_str(&r.key)?,
+          name: r.name,
+          category: StatusCategory::from_str(&r.category)?,
+        })
+      }).collect::<Result<_, DBError>>()?,
+      transitions: transitions.into_iter().map(|r| {
+        Ok(WorkflowTransition {
+          from: TaskStatus::from_str(&r.from_key)?,
+          to: TaskStatus::from_str(&r.to_key)?,
+        })
+      }).collect::<Result<_, DBError>>()?,
+    })
+  }
+
+  async fn set_workflow(&self, project_uuid: Option<String>, workflow: Workflow, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    check_workflow(&workflow)?;
+    self.check_access(project, &user, true).await?;
+
+    let keys: Vec<String> = workflow.statuses.iter().map(|s| s.key.key().to_string()).collect();
+    let names: Vec<String> = workflow.statuses.iter().map(|s| s.name.trim().to_string()).collect();
+    let categories: Vec<String> = workflow.statuses.iter().map(|s| s.category.to_string()).collect();
+    let from_keys: Vec<String> = workflow.transitions.iter().map(|t| t.from.key().to_string()).collect();
+    let to_keys: Vec<String> = workflow.transitions.iter().map(|t| t.to.key().to_string()).collect();
+
+    let mut tx = self.db.begin().await.map_err(DBError::from)?;
+
+    // Lock the scope's workflow so concurrent updates replace it one after the other.
+    let existing = sqlx::query_scalar!(
+        r#"
+        SELECT workflow_uuid FROM workflows
+        WHERE CASE WHEN $1::uuid IS NULL THEN user_username = $2 ELSE project_uuid = $1 END
+        FOR UPDATE
+        "#,
+        project,
+        user
+    ).fetch_optional(&mut *tx).await.map_err(DBError::from)?;
+    let workflow_uuid = match existing {
+      Some(workflow_uuid) => workflow_uuid,
+      None => sqlx::query_scalar!(
+          r#"
+          INSERT INTO workflows (user_username, project_uuid, created_by)
+          VALUES (CASE WHEN $1::uuid IS NULL THEN $2 END, $1, $2)
+          RETURNING workflow_uuid
+          "#,
+          project,
+          user
+      ).fetch_one(&mut *tx).await.map_err(DBError::from)?,
+    };
+
+    Self::statuses_in_use(&mut tx, project, &user, &keys).await?;
+
+    sqlx::query!("DELETE FROM workflow_statuses WHERE workflow_uuid = $1", workflow_uuid)
+      .execute(&mut *tx).await.map_err(DBError::from)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO workflow_statuses (workflow_uuid, key, name, category, position)
+        SELECT $1, s.key, s.name, s.category, s.position - 1
+        FROM unnest($2::varchar[], $3::varchar[], $4::varchar[]) WITH ORDINALITY AS s(key, name, category, position)
+        "#,
+        workflow_uuid,
+        &keys,
+        &names,
+        &categories
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO workflow_transitions (workflow_uuid, from_key, to_key)
+        SELECT DISTINCT $1::uuid, t.from_key, t.to_key
+        FROM unnest($2::varchar[], $3::varchar[]) AS t(from_key, to_key)
+        "#,
+        workflow_uuid,
+        &from_keys,
+        &to_keys
+    ).execute(&mut *tx).await.map_err(DBError::from)?;
+
+    tx.commit().await.map_err(DBError::from)?;
+    self.get_workflow(project_uuid, user).await
+  }
+
+  async fn reset_workflow(&self, project_uuid: Option<String>, user: String) -> Result<WorkflowDetail, DBError> {
+    let project = project_uuid.as_deref().map(parse_uuid).transpose()?;
+    self.check_access(project, &user, true).await?;
+
+    let mut tx = self.db.begin().await.map_err(DBError::from)?;
+
+    let deleted = sqlx::query_scalar!(
+        r#"
+        DELETE FROM workflows
+        WHERE CASE WHEN $1::uuid IS NULL THEN user_username = $2 ELSE project_uuid = $1 END
+        RETURNING workflow_uuid
+        "#,
+        project,
+        user
+    ).fetch_optional(&mut *tx).await.map_err(DBError::from)?;
+
+    if deleted.is_some() {
+      let builtin: Vec<String> = WorkflowDetail::builtin(None).statuses.iter()
+        .map(|s| s.key.key().to_string())
+        .collect();
+      Self::statuses_in_use(&mut tx, project, &user, &builtin).await?;
+    }
+
+    tx.commit().await.map_err(DBError::from)?;
+    Ok(WorkflowDetail::builtin(project_uuid))
+  }
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, Weekday};
+use time::macros::format_description;
+use crate::models::DBError;
+
+/// Largest `INTERVAL` accepted, which keeps the search for the next weekday short.
+const MAX_INTERVAL: u32 = 1000;
+
+/// Frequency of a recurrence rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A subset of RFC 5545 RRULEs: `FREQ`, `INTERVAL`, `BYDAY` (weekly), `BYMONTHDAY`
+/// (monthly/yearly), `COUNT` and `UNTIL`. `daily`, `weekly`, `monthly` and `yearly` are
+/// accepted as shorthands. Days missing from a month are clamped to its last day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub by_month_day: Option<u8>,
+    /// Total number of occurrences in the series.
+    pub count: Option<u32>,
+    pub until: Option<OffsetDateTime>,
+}
+
+impl Recurrence {
+    pub fn parse(rule: &str) -> Result<Recurrence, DBError> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+        let shorthand = match rule.to_ascii_lowercase().as_str() {
+            "daily" => Some(Frequency::Daily),
+            "weekly" => Some(Frequency::Weekly),
+            "monthly" => Some(Frequency::Monthly),
+            "yearly" => Some(Frequency::Yearly),
+            _ => None,
+        };
+        if let Some(frequency) = shorthand {
+            return Ok(Recurrence::new(frequency));
+        }
+
+        let mut frequency = None;
+        let mut recurrence = Recurrence::new(Frequency::Daily);
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| invalid(rule, part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(rule, part)),
+                    });
+                }
+                "INTERVAL" => {
+                    recurrence.interval = value.parse().ok()
+                        .filter(|i| (1..=MAX_INTERVAL).contains(i))
+                        .ok_or_else(|| invalid(rule, part))?;
+                }
+                "BYDAY" => {
+                    recurrence.by_day = value.split(',')
+                        .map(|day| parse_weekday(day).ok_or_else(|| invalid(rule, part)))
+                        .collect::<Result<_, _>>()?;
+                }
+                "BYMONTHDAY" => {
+                    recurrence.by_month_day = Some(
+                        value.parse().ok().filter(|d| (1..=31).contains(d)).ok_or_else(|| invalid(rule, part))?
+                    );
+                }
+                "COUNT" => {
+                    recurrence.count = Some(value.parse().ok().filter(|c| *c > 0).ok_or_else(|| invalid(rule, part))?);
+                }
+                "UNTIL" => {
+                    recurrence.until = Some(parse_until(value).ok_or_else(|| invalid(rule, part))?);
+                }
+                _ => return Err(invalid(rule, part)),
+            }
+        }
+
+        recurrence.frequency = frequency.ok_or_else(|| {
+            DBError::Validation(format!("Invalid recurrence {rule}: FREQ is required"))
+        })?;
+        if !recurrence.by_day.is_empty() && recurrence.frequency != Frequency::Weekly {
+            return Err(DBError::Validation(format!("Invalid recurrence {rule}: BYDAY needs FREQ=WEEKLY")));
+        }
+        if recurrence.by_month_day.is_some() && !matches!(recurrence.frequency, Frequency::Monthly | Frequency::Yearly) {
+            return Err(DBError::Validation(format!("Invalid recurrence {rule}: BYMONTHDAY needs FREQ=MONTHLY or YEARLY")));
+        }
+        Ok(recurrence)
+    }
+
+    fn new(frequency: Frequency) -> Recurrence {
+        Recurrence {
+            frequency,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: None,
+            count: None,
+            until: None,
+        }
+    }
+
+    /// Pins the day of month to the one of `start`, so clamping in short months doesn't drift the series.
+    pub fn pinned(mut self, start: OffsetDateTime) -> Recurrence {
+        if matches!(self.frequency, Frequency::Monthly | Frequency::Yearly) && self.by_month_day.is_none() {
+            self.by_month_day = Some(start.day());
+        }
+        self
+    }
+
+    /// The occurrence after `prev`, which is occurrence number `occurrence` of the series. `None`
+    /// once the series ended or when the next date is out of range.
+    pub fn next_after(&self, prev: OffsetDateTime, occurrence: u32) -> Option<OffsetDateTime> {
+        if self.count.is_some_and(|count| occurrence >= count) {
+            return None;
+        }
+
+        let next = match self.frequency {
+            Frequency::Daily => prev.checked_add(Duration::days(self.interval as i64))?,
+            Frequency::Weekly if self.by_day.is_empty() => prev.checked_add(Duration::weeks(self.interval as i64))?,
+            Frequency::Weekly => self.next_weekday(prev)?,
+            Frequency::Monthly => self.add_months(prev, self.interval)?,
+            Frequency::Yearly => self.add_months(prev, self.interval.checked_mul(12)?)?,
+        };
+
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+
+    /// Up to `limit` occurrences following `start`, which is occurrence number `occurrence`.
+    pub fn preview(&self, start: OffsetDateTime, occurrence: u32, limit: usize) -> Vec<OffsetDateTime> {
+        let recurrence = self.clone().pinned(start);
+        let mut occurrences = Vec::with_capacity(limit);
+        let (mut prev, mut index) = (start, occurrence);
+        while occurrences.len() < limit {
+            match recurrence.next_after(prev, index) {
+                Some(next) => {
+                    occurrences.push(next);
+                    prev = next;
+                    index += 1;
+                }
+                None => break,
+            }
+        }
+        occurrences
+    }
+
+    /// Next listed weekday, skipping `interval - 1` weeks after the last weekday of `prev`'s week.
+    fn next_weekday(&self, prev: OffsetDateTime) -> Option<OffsetDateTime> {
+        let week_start = prev.date() - Duration::days(prev.weekday().number_days_from_monday() as i64);
+        (1..=7 * self.interval as i64 + 7)
+            .map_while(|days| prev.checked_add(Duration::days(days)))
+            .find(|candidate| {
+                let weeks = (candidate.date() - week_start).whole_weeks();
+                weeks % self.interval as i64 == 0 && self.by_day.contains(&candidate.weekday())
+            })
+            .or_else(|| prev.checked_add(Duration::weeks(self.interval as i64)))
+    }
+
+    fn add_months(&self, prev: OffsetDateTime, months: u32) -> Option<OffsetDateTime> {
+        let total = prev.year() * 12 + prev.month() as i32 - 1 + months as i32;
+        let (year, month) = (total.div_euclid(12), Month::try_from((total.rem_euclid(12) + 1) as u8).ok()?);
+        let day = self.by_month_day.unwrap_or(prev.day()).min(month.length(year));
+        let date = Date::from_calendar_date(year, month, day).ok()?;
+        Some(prev.replace_date(date))
+    }
+}
+
+impl fmt::Display for Recurrence {
+    /// Canonical RRULE form, as stored with the task.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter().map(|day| weekday_code(*day)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(day) = self.by_month_day {
+            write!(f, ";BYMONTHDAY={day}")?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self.until {
+            let until = until.to_offset(time::UtcOffset::UTC);
+            let until = until.format(format_description!("[year][month][day]T[hour][minute][second]Z")).map_err(|_| fmt::Error)?;
+            write!(f, ";UNTIL={until}")?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(rule: &str, part: &str) -> DBError {
+    DBError::Validation(format!("Invalid recurrence {rule}: unsupported {part}"))
+}
+
+fn parse_weekday(day: &str) -> Option<Weekday> {
+    match day.trim().to_ascii_uppercase().as_str() {
+        "MO" => Some(Weekday::Monday),
+        "TU" => Some(Weekday::Tuesday),
+        "WE" => Some(Weekday::Wednesday),
+        "TH" => Some(Weekday::Thursday),
+        "FR" => Some(Weekday::Friday),
+        "SA" => Some(Weekday::Saturday),
+        "SU" => Some(Weekday::Sunday),
+        _ => None,
+    }
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Monday => "MO",
+        Weekday::Tuesday => "TU",
+        Weekday::Wednesday => "WE",
+        Weekday::Thursday => "TH",
+        Weekday::Friday => "FR",
+        Weekday::Saturday => "SA",
+        Weekday::Sunday => "SU",
+    }
+}
+
+/// `UNTIL` as a date (`20250131`, inclusive) or a UTC date-time (`20250131T090000Z`).
+fn parse_until(value: &str) -> Option<OffsetDateTime> {
+    if let Ok(date_time) = PrimitiveDateTime::parse(value, format_description!("[year][month][day]T[hour][minute][second]Z")) {
+        return Some(date_time.assume_utc());
+    }
+    let date = Date::parse(value, format_description!("[year][month][day]")).ok()?;
+    Some(PrimitiveDateTime::new(date, Time::MAX).assume_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    fn next(rule: &str, prev: OffsetDateTime) -> Option<OffsetDateTime> {
+        Recurrence::parse(rule).unwrap().pinned(prev).next_after(prev, 1)
+    }
+
+    #[test]
+    fn parses_shorthands_and_rules() {
+        assert_eq!(Recurrence::parse("weekly").unwrap(), Recurrence::new(Frequency::Weekly));
+
+        let recurrence = Recurrence::parse("RRULE:freq=weekly;INTERVAL=2;BYDAY=mo,FR;COUNT=5;UNTIL=20250131").unwrap();
+        assert_eq!(recurrence.frequency, Frequency::Weekly);
+        assert_eq!(recurrence.interval, 2);
+        assert_eq!(recurrence.by_day, vec![Weekday::Monday, Weekday::Friday]);
+        assert_eq!(recurrence.count, Some(5));
+        assert_eq!(recurrence.until, Some(datetime!(2025-01-31 23:59:59.999999999 UTC)));
+        assert_eq!(recurrence.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=5;UNTIL=20250131T235959Z");
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=4000000",
+            "FREQ=DAILY;BYDAY=MO",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;COUNT=0",
+            "FREQ=DAILY;BYHOUR=9",
+        ] {
+            assert!(Recurrence::parse(rule).is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn steps_by_the_interval() {
+        let prev = datetime!(2025-01-01 09:00 UTC);
+        assert_eq!(next("FREQ=DAILY;INTERVAL=3", prev), Some(datetime!(2025-01-04 09:00 UTC)));
+        assert_eq!(next("FREQ=WEEKLY;INTERVAL=2", prev), Some(datetime!(2025-01-15 09:00 UTC)));
+        assert_eq!(next("yearly", prev), Some(datetime!(2026-01-01 09:00 UTC)));
+    }
+
+    #[test]
+    fn byday_skips_the_weeks_between_intervals() {
+        let recurrence = Recurrence::parse("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE").unwrap();
+        let monday = datetime!(2025-01-06 09:00 UTC);
+        let occurrences = recurrence.preview(monday, 1, 4);
+        assert_eq!(occurrences, vec![
+            datetime!(2025-01-08 09:00 UTC),
+            datetime!(2025-01-20 09:00 UTC),
+            datetime!(2025-01-22 09:00 UTC),
+            datetime!(2025-02-03 09:00 UTC),
+        ]);
+    }
+
+    #[test]
+    fn months_are_clamped_to_their_last_day() {
+        let recurrence = Recurrence::parse("monthly").unwrap();
+        assert_eq!(recurrence.preview(datetime!(2025-01-31 09:00 UTC), 1, 2), vec![
+            datetime!(2025-02-28 09:00 UTC),
+            datetime!(2025-03-31 09:00 UTC),
+        ]);
+        assert_eq!(recurrence.preview(datetime!(2024-01-31 09:00 UTC), 1, 1), vec![datetime!(2024-02-29 09:00 UTC)]);
+    }
+
+    #[test]
+    fn count_and_until_end_the_series() {
+        let counted = Recurrence::parse("FREQ=DAILY;COUNT=3").unwrap();
+        let prev = datetime!(2025-01-01 09:00 UTC);
+        assert_eq!(counted.next_after(prev, 2), Some(datetime!(2025-01-02 09:00 UTC)));
+        assert_eq!(counted.next_after(prev, 3), None);
+
+        let until = Recurrence::parse("FREQ=DAILY;UNTIL=20250110").unwrap();
+        assert_eq!(until.next_after(datetime!(2025-01-09 09:00 UTC), 1), Some(datetime!(2025-01-10 09:00 UTC)));
+        assert_eq!(until.next_after(datetime!(2025-01-10 09:00 UTC), 1), None);
+    }
+
+    #[test]
+    fn dates_out_of_range_end_the_series() {
+        let prev = datetime!(9999-06-01 09:00 UTC);
+        assert_eq!(next("FREQ=DAILY;INTERVAL=1000", prev), None);
+        assert_eq!(next("FREQ=WEEKLY;INTERVAL=1000", prev), None);
+        assert_eq!(next("FREQ=WEEKLY;INTERVAL=1000;BYDAY=MO", prev), None);
+        assert_eq!(next("FREQ=YEARLY;INTERVAL=1000", prev), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use crate::models::{DBError, ReminderKind, TaskReminder};
+use crate::persistence::tasks_dbo::TasksDbo;
+
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+
+/// Delivers reminders to users. Implement it to send e-mails, push notifications, ...
+#[async_trait]
+pub trait Notifier {
+    async fn notify(&self, reminder: &TaskReminder) -> Result<(), DBError>;
+}
+
+/// Default notifier, only writes reminders to the log.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, reminder: &TaskReminder) -> Result<(), DBError> {
+        let task = &reminder.task;
+        let recipient = task.assignee.as_deref().unwrap_or(&task.created_by);
+        match reminder.kind {
+            ReminderKind::DueSoon => println!(
+                "[reminder] {recipient}: task {} \"{}\" is due at {}",
+                task.task_uuid, task.title, task.due_at.as_deref().unwrap_or_default()
+            ),
+            ReminderKind::Overdue => println!(
+                "[reminder] {recipient}: task {} \"{}\" is overdue since {}",
+                task.task_uuid, task.title, task.due_at.as_deref().unwrap_or_default()
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Polling interval of the reminder job, from `REMINDER_INTERVAL_SECS`. Zero is refused, as the
+/// ticker needs a period.
+pub fn reminder_interval_from_env() -> Result<Duration, String> {
+    match std::env::var("REMINDER_INTERVAL_SECS") {
+        Ok(value) => match value.parse::<u64>() {
+            Ok(0) => Err("Invalid REMINDER_INTERVAL_SECS: must be at least 1".to_string()),
+            Ok(secs) => Ok(Duration::from_secs(secs)),
+            Err(e) => Err(format!("Invalid REMINDER_INTERVAL_SECS: {e}")),
+        },
+        Err(_) => Ok(Duration::from_secs(DEFAULT_INTERVAL_SECS)),
+    }
+}
+
+/// Spawns a task that periodically hands due and overdue tasks to `notifier`.
+pub fn spawn_reminder_job(
+    tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+    notifier: Arc<dyn Notifier + Send + Sync>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let reminders = match tasks_dbo.claim_due_reminders().await {
+                Ok(reminders) => reminders,
+                Err(e) => {
+                    println!("[reminder] Unable to load due tasks: {e}");
+                    continue;
+                }
+            };
+            for reminder in &reminders {
+                if let Err(e) = notifier.notify(reminder).await {
+                    println!("[reminder] Unable to notify about task {}: {e}", reminder.task.task_uuid);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,3 @@
+//! Flows shared by the REST handlers and the GraphQL resolvers: each one writes through the DBOs in
+//! a unit of work, records the change in the task history and publishes it once committed.
+pub mod tasks;
@@ -0,0 +1,301 @@
+use std::sync::Arc;
+use crate::events::TaskEvents;
+use crate::models::*;
+use crate::persistence::{
+    labels_dbo::LabelsDbo,
+    tasks_dbo::TasksDbo,
+    tracking_dbo::TrackingDbo,
+    unit_of_work::UnitOfWork
+};
+
+/// Task changes together with their tracking and the events announcing them.
+#[derive(Clone)]
+pub struct TaskService {
+    tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+    tracking_dbo: Arc<dyn TrackingDbo + Send + Sync>,
+    labels_dbo: Arc<dyn LabelsDbo + Send + Sync>,
+    task_events: TaskEvents,
+}
+
+impl TaskService {
+    pub fn new(
+        tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+        tracking_dbo: Arc<dyn TrackingDbo + Send + Sync>,
+        labels_dbo: Arc<dyn LabelsDbo + Send + Sync>,
+        task_events: TaskEvents,
+    ) -> Self {
+        Self {
+            tasks_dbo,
+            tracking_dbo,
+            labels_dbo,
+            task_events,
+        }
+    }
+
+    pub async fn create_task(&self, task: Task, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let task = self.tasks_dbo.create_task(&mut uow, task, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::Created, &user_name).task_changes(None, &task)
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Created, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Applies the fields of the patch if the task is still at `version`. Patches that change
+    /// nothing leave no trace in the history.
+    pub async fn update_task(&self, task: TaskUpdateReq, version: Option<i32>, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (previous, task) = self.tasks_dbo.update_task(&mut uow, task, version, user_name.clone()).await?;
+        let changes = Tracking::new(&task.task_uuid, TrackingEventType::Updated, &user_name).task_changes(Some(&previous), &task);
+        if changes.changes.is_empty() {
+            uow.commit().await?;
+            return Ok(task);
+        }
+        let tracking = self.tracking_dbo.create_tracking(&mut uow, changes).await?;
+        let next = self.next_occurrence(&mut uow, &previous, &task, &user_name).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        if let Some(next) = next {
+            self.task_events.publish(next);
+        }
+        Ok(task)
+    }
+
+    /// Changes the status if the task is still at `version`.
+    pub async fn update_status(&self, task_uuid: String, status: TaskStatus, version: Option<i32>, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (previous, task) = self.tasks_dbo.update_task_status(&mut uow, status, task_uuid, version, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::StatusChanged, &user_name).task_changes(Some(&previous), &task)
+        ).await?;
+        let next = self.next_occurrence(&mut uow, &previous, &task, &user_name).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::StatusUpdated, &task, tracking, user_name);
+        if let Some(next) = next {
+            self.task_events.publish(next);
+        }
+        Ok(task)
+    }
+
+    /// Assigns the task to `assignee`, or unassigns it with `None`.
+    pub async fn assign_task(&self, task_uuid: &str, assignee: Option<String>, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (previous, task) = self.tasks_dbo.assign_task(&mut uow, task_uuid, assignee, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::Assigned, &user_name).task_changes(Some(&previous), &task)
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Assigned, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Moves the task under another parent, or to the top level with `None`, at `position` among its siblings.
+    pub async fn set_parent(
+        &self,
+        task_uuid: &str,
+        parent_task_uuid: Option<String>,
+        position: Option<i32>,
+        user_name: String
+    ) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (previous, task) = self.tasks_dbo.move_to_parent(&mut uow, task_uuid, parent_task_uuid, position, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::ParentChanged, &user_name).task_changes(Some(&previous), &task)
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Moves the task to the `status` column, after `after_task_uuid` and/or before `before_task_uuid`,
+    /// or last without either.
+    pub async fn move_task(
+        &self,
+        task_uuid: &str,
+        status: TaskStatus,
+        after_task_uuid: Option<String>,
+        before_task_uuid: Option<String>,
+        user_name: String
+    ) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (previous, task) = self.tasks_dbo.move_task(&mut uow, task_uuid, status, after_task_uuid, before_task_uuid, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::Moved, &user_name).task_changes(Some(&previous), &task)
+        ).await?;
+        let next = self.next_occurrence(&mut uow, &previous, &task, &user_name).await?;
+        uow.commit().await?;
+
+        let kind = if previous.status != task.status { TaskEventKind::StatusUpdated } else { TaskEventKind::Updated };
+        self.publish(kind, &task, tracking, user_name);
+        if let Some(next) = next {
+            self.task_events.publish(next);
+        }
+        Ok(task)
+    }
+
+    /// Marks the task as blocked by `blocker_uuid`.
+    pub async fn add_dependency(&self, task_uuid: &str, blocker_uuid: &str, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let task = self.tasks_dbo.add_dependency(&mut uow, task_uuid, blocker_uuid, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::DependencyAdded, &user_name).change("blocker_uuid", None::<String>, blocker_uuid)
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    pub async fn remove_dependency(&self, task_uuid: &str, blocker_uuid: &str, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let task = self.tasks_dbo.remove_dependency(&mut uow, task_uuid, blocker_uuid, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::DependencyRemoved, &user_name).change("blocker_uuid", blocker_uuid, None::<String>)
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Attaches a label to the task and returns the task with it.
+    pub async fn attach_label(&self, task_uuid: &str, label_uuid: &str, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let label = self.labels_dbo.attach_label(&mut uow, task_uuid, label_uuid, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(task_uuid, TrackingEventType::LabelAdded, &user_name).change("label", None::<LabelDetail>, &label)
+        ).await?;
+        uow.commit().await?;
+        let task = self.tasks_dbo.get_task(task_uuid, user_name.clone()).await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    pub async fn detach_label(&self, task_uuid: &str, label_uuid: &str, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let label = self.labels_dbo.detach_label(&mut uow, task_uuid, label_uuid, user_name.clone()).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            &mut uow,
+            Tracking::new(task_uuid, TrackingEventType::LabelRemoved, &user_name).change("label", &label, None::<LabelDetail>)
+        ).await?;
+        uow.commit().await?;
+        let task = self.tasks_dbo.get_task(task_uuid, user_name.clone()).await?;
+
+        self.publish(TaskEventKind::Updated, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Moves the task to the trash, with its subtasks when `cascade` is set, and returns it.
+    pub async fn delete_task(&self, task_uuid: String, cascade: bool, user_name: String) -> Result<TaskDetail, DBError> {
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (task, subtasks) = self.tasks_dbo.delete_task(&mut uow, task_uuid, cascade, user_name.clone()).await?;
+        let deleted_at = task.deleted_at.clone();
+        let tracking = self.track_trash(&mut uow, TrackingEventType::Deleted, deleted_at.as_deref(), &task, &subtasks, &user_name).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Deleted, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    /// Takes the task out of the trash, together with the subtasks deleted along with it.
+    pub async fn restore_task(&self, task_uuid: &str, user_name: String) -> Result<TaskDetail, DBError> {
+        let trashed = self.tasks_dbo.get_trashed_task(task_uuid, user_name.clone()).await?;
+        let mut uow = self.tasks_dbo.begin().await?;
+        let (task, subtasks) = self.tasks_dbo.restore_task(&mut uow, task_uuid, user_name.clone()).await?;
+        let tracking = self.track_trash(
+            &mut uow, TrackingEventType::Restored, trashed.deleted_at.as_deref(), &task, &subtasks, &user_name
+        ).await?;
+        uow.commit().await?;
+
+        self.publish(TaskEventKind::Restored, &task, tracking, user_name);
+        Ok(task)
+    }
+
+    fn publish(&self, kind: TaskEventKind, task: &TaskDetail, tracking: TrackingDetail, user_name: String) {
+        self.task_events.publish(TaskEvent {
+            kind,
+            task: task.clone(),
+            tracking: Some(tracking),
+            user_name
+        });
+    }
+
+    /// Generates the next occurrence of a recurring task that was just completed, that is moved from
+    /// `previous` into a done status, and records it in the tracking of both tasks, as part of `uow`.
+    /// Returns the event announcing the new task, to publish once `uow` is committed.
+    async fn next_occurrence(
+        &self,
+        uow: &mut UnitOfWork,
+        previous: &TaskDetail,
+        task: &TaskDetail,
+        user_name: &str
+    ) -> Result<Option<TaskEvent>, DBError> {
+        if task.status_category != StatusCategory::Done || task.status == previous.status {
+            return Ok(None);
+        }
+        let Some(next) = self.tasks_dbo.schedule_next_occurrence(uow, &task.task_uuid, user_name.to_string()).await? else {
+            return Ok(None);
+        };
+
+        self.tracking_dbo.create_tracking(
+            uow,
+            Tracking::new(&task.task_uuid, TrackingEventType::OccurrenceScheduled, user_name)
+                .change("next_task_uuid", None::<String>, &next.task_uuid)
+                .change("next_due_at", None::<String>, &next.due_at)
+        ).await?;
+        let tracking = self.tracking_dbo.create_tracking(
+            uow,
+            Tracking::new(&next.task_uuid, TrackingEventType::Created, user_name).task_changes(None, &next)
+        ).await?;
+
+        Ok(Some(TaskEvent {
+            kind: TaskEventKind::Created,
+            task: next,
+            tracking: Some(tracking),
+            user_name: user_name.to_string()
+        }))
+    }
+
+    /// Records that `task` and `subtasks` went to the trash at `deleted_at` (`TrackingEventType::Deleted`)
+    /// or came back out of it (`TrackingEventType::Restored`), as part of `uow`. Returns the tracking of
+    /// `task`, the one the user acted on.
+    async fn track_trash(
+        &self,
+        uow: &mut UnitOfWork,
+        event_type: TrackingEventType,
+        deleted_at: Option<&str>,
+        task: &TaskDetail,
+        subtasks: &[TaskDetail],
+        user_name: &str
+    ) -> Result<TrackingDetail, DBError> {
+        let (before, after) = match event_type {
+            TrackingEventType::Restored => (deleted_at, None),
+            _ => (None, deleted_at),
+        };
+        let track = |task: &TaskDetail| {
+            Tracking::new(&task.task_uuid, event_type, user_name).change("deleted_at", before, after)
+        };
+
+        let tracking = self.tracking_dbo.create_tracking(uow, track(task)).await?;
+        for subtask in subtasks {
+            self.tracking_dbo.create_tracking(uow, track(subtask)).await?;
+        }
+        Ok(tracking)
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::time::Duration;
+use crate::persistence::tasks_dbo::TasksDbo;
+
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+const DEFAULT_PURGE_INTERVAL_SECS: u64 = 3600;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// How long deleted tasks stay in the trash, from `TRASH_RETENTION_DAYS`.
+pub fn trash_retention_from_env() -> Result<Duration, String> {
+    match std::env::var("TRASH_RETENTION_DAYS") {
+        Ok(value) => {
+            let days = value.parse::<u64>().map_err(|e| format!("Invalid TRASH_RETENTION_DAYS: {e}"))?;
+            days.checked_mul(SECS_PER_DAY)
+                .map(Duration::from_secs)
+                .ok_or_else(|| format!("Invalid TRASH_RETENTION_DAYS: {days} days is too long"))
+        }
+        Err(_) => Ok(Duration::from_secs(DEFAULT_RETENTION_DAYS * SECS_PER_DAY)),
+    }
+}
+
+/// How often the trash is checked for tasks to purge, from `TRASH_PURGE_INTERVAL_SECS`. Zero is
+/// refused, as the ticker needs a period.
+pub fn trash_purge_interval_from_env() -> Result<Duration, String> {
+    match std::env::var("TRASH_PURGE_INTERVAL_SECS") {
+        Ok(value) => match value.parse::<u64>() {
+            Ok(0) => Err("Invalid TRASH_PURGE_INTERVAL_SECS: must be at least 1".to_string()),
+            Ok(secs) => Ok(Duration::from_secs(secs)),
+            Err(e) => Err(format!("Invalid TRASH_PURGE_INTERVAL_SECS: {e}")),
+        },
+        Err(_) => Ok(Duration::from_secs(DEFAULT_PURGE_INTERVAL_SECS)),
+    }
+}
+
+/// Spawns a task that periodically deletes tasks that have been in the trash for longer than `retention`.
+pub fn spawn_trash_purge_job(
+    tasks_dbo: Arc<dyn TasksDbo + Send + Sync>,
+    retention: Duration,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match tasks_dbo.purge_trash(retention).await {
+                Ok(0) => {}
+                Ok(purged) => println!("[trash] Purged {purged} tasks"),
+                Err(e) => println!("[trash] Unable to purge the trash: {e}"),
+            }
+        }
+    })
+}
@@ -0,0 +1,598 @@
+//! End-to-end tests of the HTTP API, driving the router with `oneshot` against the in-memory store.
+use std::sync::Arc;
+use auth_lib::AuthConfig;
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use serde_json::{json, Value};
+use todo_app::app::{build_router, AppState};
+use tower::ServiceExt;
+
+const SECRET: &str = "test-secret";
+const PASSWORD: &str = "correct-horse-42";
+
+fn app() -> Router {
+    let auth_config = AuthConfig::from_secret("test", SECRET).expect("valid test JWT config");
+    build_router(AppState::in_memory(Arc::new(auth_config)))
+}
+
+struct Response {
+    status: StatusCode,
+    etag: Option<String>,
+    /// The JSON body, or the text of plain-text bodies as a string.
+    body: Value,
+}
+
+async fn send(app: &Router, request: Request<Body>) -> Response {
+    let response = app.clone().oneshot(request).await.expect("router is infallible");
+    let status = response.status();
+    let etag = response.headers().get(header::ETAG).map(|v| v.to_str().unwrap().to_string());
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    Response { status, etag, body }
+}
+
+fn request(method: &str, uri: &str, token: Option<&str>, body: Option<Value>) -> Request<Body> {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }.unwrap()
+}
+
+/// Checks the status of an error response and the `code` and `message` of its error envelope.
+fn assert_error(response: &Response, status: StatusCode, code: &str, message: &str) {
+    assert_eq!(response.status, status, "{}", response.body);
+    assert_eq!(response.body["error"]["code"], code, "{}", response.body);
+    assert_eq!(response.body["error"]["message"], message, "{}", response.body);
+    assert!(response.body["error"]["request_id"].is_string(), "{}", response.body);
+}
+
+async fn call(app: &Router, method: &str, uri: &str, token: Option<&str>, body: Option<Value>) -> Response {
+    send(app, request(method, uri, token, body)).await
+}
+
+/// Registers `username` with `PASSWORD` and returns the login response.
+async fn register_and_login(app: &Router, username: &str) -> Value {
+    let registered = call(app, "POST", "/register", None, Some(json!({
+        "username": username,
+        "email": format!("{username}@example.com"),
+        "password": PASSWORD
+    }))).await;
+    assert_eq!(registered.status, StatusCode::OK, "{}", registered.body);
+
+    let login = call(app, "POST", "/login", None, Some(json!({ "username": username, "password": PASSWORD }))).await;
+    assert_eq!(login.status, StatusCode::OK, "{}", login.body);
+    login.body
+}
+
+async fn token(app: &Router, username: &str) -> String {
+    register_and_login(app, username).await["token"].as_str().unwrap().to_string()
+}
+
+async fn create_task(app: &Router, token: &str, title: &str) -> Value {
+    let created = call(app, "POST", "/", Some(token), Some(json!({
+        "title": title,
+        "description": format!("{title} description"),
+        "status": "todo"
+    }))).await;
+    assert_eq!(created.status, StatusCode::OK, "{}", created.body);
+    created.body
+}
+
+#[tokio::test]
+async fn task_lifecycle() {
+    let app = app();
+    let login = register_and_login(&app, "alice").await;
+    assert!(login["refresh_token"].is_string());
+    let token = login["token"].as_str().unwrap();
+
+    let task = create_task(&app, token, "Write tests").await;
+    let id = task["task_uuid"].as_str().unwrap();
+    assert_eq!(task["title"], "Write tests");
+    assert_eq!(task["status"], "Todo");
+    assert_eq!(task["created_by"], "alice");
+    assert_eq!(task["version"], 1);
+
+    let fetched = call(&app, "GET", &format!("/{id}"), Some(token), None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+    assert_eq!(fetched.etag.as_deref(), Some("\"1\""));
+    assert_eq!(fetched.body["task"]["task_uuid"], id);
+
+    let listed = call(&app, "GET", "/", Some(token), None).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.body["total"], 1);
+    assert_eq!(listed.body["tasks"][0]["task_uuid"], id);
+
+    let updated = call(&app, "PATCH", "/", Some(token), Some(json!({
+        "task_uuid": id,
+        "title": "Write more tests",
+        "description": "Cover the API",
+        "status": "todo",
+        "version": 1
+    }))).await;
+    assert_eq!(updated.status, StatusCode::OK, "{}", updated.body);
+    assert_eq!(updated.body["title"], "Write more tests");
+    assert_eq!(updated.body["version"], 2);
+    assert_eq!(updated.etag.as_deref(), Some("\"2\""));
+
+    let mut status_change = request("PATCH", "/update-status", Some(token), Some(json!({
+        "task_uuid": id,
+        "status": "done"
+    })));
+    status_change.headers_mut().insert(header::IF_MATCH, "\"2\"".parse().unwrap());
+    let done = send(&app, status_change).await;
+    assert_eq!(done.status, StatusCode::OK, "{}", done.body);
+    assert_eq!(done.body["status"], "Done");
+    assert_eq!(done.body["status_category"], "done");
+    assert_eq!(done.body["version"], 3);
+
+    let history = call(&app, "GET", &format!("/{id}/history"), Some(token), None).await;
+    assert_eq!(history.status, StatusCode::OK);
+    let events: Vec<&str> = history.body.as_array().unwrap().iter()
+        .map(|entry| entry["event_type"].as_str().unwrap())
+        .collect();
+    assert_eq!(events, ["created", "updated", "status_changed"]);
+    assert_eq!(history.body[1]["actor"], "alice");
+    assert_eq!(history.body[1]["changes"], json!([
+        { "field": "title", "before": "Write tests", "after": "Write more tests" },
+        { "field": "description", "before": "Write tests description", "after": "Cover the API" }
+    ]));
+    assert_eq!(history.body[2]["changes"][0], json!({ "field": "status", "before": "Todo", "after": "Done" }));
+
+    let deleted = call(&app, "DELETE", "/", Some(token), Some(json!({ "task_uuid": id }))).await;
+    assert_eq!(deleted.status, StatusCode::OK, "{}", deleted.body);
+
+    let gone = call(&app, "GET", &format!("/{id}"), Some(token), None).await;
+    assert_error(&gone, StatusCode::NOT_FOUND, "not_found", &format!("Task {id} not found"));
+
+    let trash = call(&app, "GET", "/trash", Some(token), None).await;
+    assert_eq!(trash.status, StatusCode::OK);
+    assert_eq!(trash.body[0]["task_uuid"], id);
+
+    let restored = call(&app, "POST", &format!("/{id}/restore"), Some(token), None).await;
+    assert_eq!(restored.status, StatusCode::OK, "{}", restored.body);
+    assert_eq!(restored.body["deleted_at"], Value::Null);
+    let listed = call(&app, "GET", "/", Some(token), None).await;
+    assert_eq!(listed.body["total"], 1);
+}
+
+#[tokio::test]
+async fn tasks_are_private_to_their_owner() {
+    let app = app();
+    let alice = token(&app, "alice").await;
+    let bob = token(&app, "bob").await;
+    let task = create_task(&app, &alice, "Secret plans").await;
+    let id = task["task_uuid"].as_str().unwrap();
+
+    let listed = call(&app, "GET", "/", Some(&bob), None).await;
+    assert_eq!(listed.status, StatusCode::OK);
+    assert_eq!(listed.body["total"], 0);
+
+    let fetched = call(&app, "GET", &format!("/{id}"), Some(&bob), None).await;
+    assert_error(&fetched, StatusCode::NOT_FOUND, "not_found", &format!("Task {id} not found"));
+
+    let deleted = call(&app, "DELETE", "/", Some(&bob), Some(json!({ "task_uuid": id }))).await;
+    assert_error(&deleted, StatusCode::NOT_FOUND, "not_found", &format!("Task {id} not found"));
+    let fetched = call(&app, "GET", &format!("/{id}"), Some(&alice), None).await;
+    assert_eq!(fetched.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn boards_are_ordered_independently() {
+    let app = app();
+    let alice = token(&app, "alice").await;
+    let bob = token(&app, "bob").await;
+    let anchor = create_task(&app, &alice, "Anchor").await;
+    let moved = [create_task(&app, &alice, "First").await, create_task(&app, &alice, "Second").await];
+    let other = create_task(&app, &bob, "Elsewhere").await;
+    assert_eq!(other["position"], anchor["position"]);
+
+    // Moving the two tasks right after the anchor in turn halves the gap each time, until the column
+    // runs out of room and is renumbered.
+    for i in 0..60 {
+        let id = moved[i % 2]["task_uuid"].as_str().unwrap();
+        let response = call(&app, "PATCH", &format!("/{id}/move"), Some(&alice), Some(json!({
+            "status": "todo",
+            "after_task_uuid": anchor["task_uuid"]
+        }))).await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+    }
+
+    let other = call(&app, "GET", &format!("/{}", other["task_uuid"].as_str().unwrap()), Some(&bob), None).await;
+    assert_eq!(other.body["task"]["version"], 1);
+    assert_eq!(other.body["task"]["position"], anchor["position"]);
+}
+
+#[tokio::test]
+async fn stale_versions_are_rejected() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let task = create_task(&app, &token, "Contended").await;
+    let id = task["task_uuid"].as_str().unwrap();
+    let update = |version: i32| json!({
+        "task_uuid": id,
+        "title": format!("Version {version}"),
+        "description": "",
+        "status": "todo",
+        "version": version
+    });
+
+    let first = call(&app, "PATCH", "/", Some(&token), Some(update(1))).await;
+    assert_eq!(first.status, StatusCode::OK, "{}", first.body);
+
+    let stale = call(&app, "PATCH", "/", Some(&token), Some(update(1))).await;
+    assert_error(
+        &stale,
+        StatusCode::CONFLICT,
+        "conflict",
+        &format!("Task {id} was changed by someone else, it is at version 2 instead of 1")
+    );
+
+    let mut stale_if_match = request("PATCH", "/update-status", Some(&token), Some(json!({
+        "task_uuid": id,
+        "status": "done"
+    })));
+    stale_if_match.headers_mut().insert(header::IF_MATCH, "\"1\"".parse().unwrap());
+    let stale = send(&app, stale_if_match).await;
+    assert_eq!(stale.status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(stale.body["error"]["code"], "precondition_failed");
+
+    let history = call(&app, "GET", &format!("/{id}/history"), Some(&token), None).await;
+    assert_eq!(history.body.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn blocked_changes_stay_conflicts_under_if_match() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let blocker = create_task(&app, &token, "Blocker").await;
+    let task = create_task(&app, &token, "Blocked").await;
+    let id = task["task_uuid"].as_str().unwrap();
+    let added = call(&app, "POST", &format!("/{id}/dependencies"), Some(&token), Some(json!({
+        "blocker_uuid": blocker["task_uuid"]
+    }))).await;
+    assert_eq!(added.status, StatusCode::CREATED, "{}", added.body);
+    let current = call(&app, "GET", &format!("/{id}"), Some(&token), None).await;
+    let version = current.body["task"]["version"].as_i64().unwrap();
+
+    let mut done = request("PATCH", "/update-status", Some(&token), Some(json!({ "task_uuid": id, "status": "done" })));
+    done.headers_mut().insert(header::IF_MATCH, format!("\"{version}\"").parse().unwrap());
+    let refused = send(&app, done).await;
+    assert_error(
+        &refused,
+        StatusCode::CONFLICT,
+        "conflict",
+        &format!("Task {id} is blocked by unfinished tasks: {}", blocker["task_uuid"].as_str().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn patches_change_only_the_given_fields() {
+    let app = app();
+    let alice = token(&app, "alice").await;
+    register_and_login(&app, "bob").await;
+    let task = create_task(&app, &alice, "Patch me").await;
+    let id = task["task_uuid"].as_str().unwrap();
+    let patch = |body: Value| {
+        let mut request = request("PATCH", "/", Some(&alice), Some(body));
+        request.headers_mut().insert(header::IF_MATCH, "*".parse().unwrap());
+        send(&app, request)
+    };
+
+    let patched = patch(json!({
+        "task_uuid": id,
+        "title": "Patched",
+        "due_at": "2030-01-01T09:00:00Z",
+        "assignee": "alice"
+    })).await;
+    assert_eq!(patched.status, StatusCode::OK, "{}", patched.body);
+    assert_eq!(patched.body["title"], "Patched");
+    assert_eq!(patched.body["description"], "Patch me description");
+    assert_eq!(patched.body["status"], "Todo");
+    assert_eq!(patched.body["assignee"], "alice");
+    assert_eq!(patched.body["version"], 2);
+
+    let cleared = patch(json!({ "task_uuid": id, "due_at": null, "assignee": "bob" })).await;
+    assert_eq!(cleared.status, StatusCode::OK, "{}", cleared.body);
+    assert_eq!(cleared.body["title"], "Patched");
+    assert!(cleared.body["due_at"].is_null());
+    assert_eq!(cleared.body["assignee"], "bob");
+
+    // Sending the current values changes nothing, so neither the version nor the history moves.
+    let unchanged = patch(json!({ "task_uuid": id, "title": "Patched", "assignee": "bob" })).await;
+    assert_eq!(unchanged.status, StatusCode::OK, "{}", unchanged.body);
+    assert_eq!(unchanged.body["version"], 3);
+    assert_eq!(unchanged.etag.as_deref(), Some("\"3\""));
+
+    let history = call(&app, "GET", &format!("/{id}/history?event_type=updated"), Some(&alice), None).await;
+    let mut fields: Vec<Vec<String>> = history.body.as_array().unwrap().iter()
+        .map(|entry| entry["changes"].as_array().unwrap().iter().map(|c| c["field"].as_str().unwrap().to_string()).collect())
+        .collect();
+    fields.iter_mut().for_each(|fields| fields.sort());
+    fields.sort();
+    assert_eq!(fields, vec![vec!["assignee", "due_at"], vec!["assignee", "due_at", "title"]]);
+
+    let unknown = patch(json!({ "task_uuid": id, "assignee": "nobody" })).await;
+    assert_error(&unknown, StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", &format!("nobody is not a user who can be assigned task {id}"));
+}
+
+#[tokio::test]
+async fn completing_a_recurring_task_schedules_one_occurrence() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let created = call(&app, "POST", "/", Some(&token), Some(json!({
+        "title": "Water plants",
+        "description": "",
+        "status": "todo",
+        "due_at": "2030-01-01T09:00:00Z",
+        "recurrence": "daily"
+    }))).await;
+    assert_eq!(created.status, StatusCode::OK, "{}", created.body);
+    let id = created.body["task_uuid"].as_str().unwrap();
+
+    // Repeating the status change must not schedule a second occurrence.
+    for _ in 0..2 {
+        let mut done = request("PATCH", "/update-status", Some(&token), Some(json!({ "task_uuid": id, "status": "done" })));
+        done.headers_mut().insert(header::IF_MATCH, "*".parse().unwrap());
+        let done = send(&app, done).await;
+        assert_eq!(done.status, StatusCode::OK, "{}", done.body);
+    }
+
+    let listed = call(&app, "GET", "/", Some(&token), None).await;
+    assert_eq!(listed.body["total"], 2, "{}", listed.body);
+    let next = listed.body["tasks"].as_array().unwrap().iter().find(|task| task["task_uuid"] != id).unwrap();
+    assert_eq!(next["due_at"], "2030-01-02T09:00:00Z");
+    assert_eq!(next["status"], "Todo");
+}
+
+#[tokio::test]
+async fn trashed_blockers_do_not_block() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let trashed = create_task(&app, &token, "Trashed blocker").await;
+    let open = create_task(&app, &token, "Open blocker").await;
+    let task = create_task(&app, &token, "Blocked").await;
+    let id = task["task_uuid"].as_str().unwrap();
+    let (trashed, open) = (trashed["task_uuid"].as_str().unwrap(), open["task_uuid"].as_str().unwrap());
+    for blocker in [trashed, open] {
+        let added = call(&app, "POST", &format!("/{id}/dependencies"), Some(&token), Some(json!({ "blocker_uuid": blocker }))).await;
+        assert_eq!(added.status, StatusCode::CREATED, "{}", added.body);
+    }
+    let deleted = call(&app, "DELETE", "/", Some(&token), Some(json!({ "task_uuid": trashed }))).await;
+    assert_eq!(deleted.status, StatusCode::OK, "{}", deleted.body);
+    let done = || call(&app, "PATCH", "/update-status", Some(&token), Some(json!({ "task_uuid": id, "status": "done", "version": 1 })));
+
+    let refused = done().await;
+    assert_error(&refused, StatusCode::CONFLICT, "conflict", &format!("Task {id} is blocked by unfinished tasks: {open}"));
+
+    let deleted = call(&app, "DELETE", "/", Some(&token), Some(json!({ "task_uuid": open }))).await;
+    assert_eq!(deleted.status, StatusCode::OK, "{}", deleted.body);
+    let done = done().await;
+    assert_eq!(done.status, StatusCode::OK, "{}", done.body);
+}
+
+#[tokio::test]
+async fn repeated_tags_are_matched_once() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let tagged = create_task(&app, &token, "Tagged").await;
+    create_task(&app, &token, "Untagged").await;
+    let label = call(&app, "POST", "/labels", Some(&token), Some(json!({ "name": "Bug" }))).await;
+    assert_eq!(label.status, StatusCode::CREATED, "{}", label.body);
+    let attached = call(&app, "POST", &format!("/{}/labels", tagged["task_uuid"].as_str().unwrap()), Some(&token), Some(json!({
+        "label_uuid": label.body["label_uuid"]
+    }))).await;
+    assert!(attached.status.is_success(), "{}", attached.body);
+
+    let listed = call(&app, "GET", "/?tags=bug,BUG&tags_mode=all", Some(&token), None).await;
+    assert_eq!(listed.status, StatusCode::OK, "{}", listed.body);
+    let titles: Vec<&str> = listed.body["tasks"].as_array().unwrap().iter().map(|task| task["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Tagged"]);
+}
+
+#[tokio::test]
+async fn login_rejects_a_wrong_password() {
+    let app = app();
+    register_and_login(&app, "alice").await;
+
+    let login = call(&app, "POST", "/login", None, Some(json!({ "username": "alice", "password": "nope" }))).await;
+    assert_error(&login, StatusCode::UNAUTHORIZED, "unauthorized", "Invalid username or password");
+
+    let unknown = call(&app, "POST", "/login", None, Some(json!({ "username": "nobody", "password": PASSWORD }))).await;
+    assert_error(&unknown, StatusCode::UNAUTHORIZED, "unauthorized", "Invalid username or password");
+}
+
+#[tokio::test]
+async fn register_rejects_a_taken_username() {
+    let app = app();
+    register_and_login(&app, "alice").await;
+
+    let again = call(&app, "POST", "/register", None, Some(json!({
+        "username": "alice",
+        "email": "other@example.com",
+        "password": PASSWORD
+    }))).await;
+    assert_error(&again, StatusCode::CONFLICT, "conflict", "User alice already exists");
+}
+
+/// The `fields` of a validation error as `(field, code)` pairs.
+fn field_errors(response: &Response) -> Vec<(String, String)> {
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", response.body);
+    assert_eq!(response.body["error"]["code"], "validation_failed", "{}", response.body);
+    response.body["error"]["fields"].as_array().expect("fields").iter()
+        .map(|e| (e["field"].as_str().unwrap().to_string(), e["code"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+fn pairs(expected: &[(&str, &str)]) -> Vec<(String, String)> {
+    expected.iter().map(|(field, code)| (field.to_string(), code.to_string())).collect()
+}
+
+#[tokio::test]
+async fn register_validates_the_user() {
+    let app = app();
+
+    let invalid = call(&app, "POST", "/register", None, Some(json!({
+        "username": "al ice",
+        "email": "not-an-email",
+        "password": "short"
+    }))).await;
+    assert_eq!(field_errors(&invalid), pairs(&[("email", "email"), ("password", "length"), ("username", "charset")]));
+    assert_eq!(invalid.body["error"]["fields"][0]["message"], "must be a valid email address");
+
+    let weak = call(&app, "POST", "/register", None, Some(json!({
+        "username": "al",
+        "email": "al@example.com",
+        "password": "onlyletters"
+    }))).await;
+    assert_eq!(field_errors(&weak), pairs(&[("password", "strength"), ("username", "length")]));
+
+    // Nothing was created by the rejected requests.
+    let login = call(&app, "POST", "/login", None, Some(json!({ "username": "al", "password": "onlyletters" }))).await;
+    assert_eq!(login.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn login_validates_lengths() {
+    let app = app();
+
+    let empty = call(&app, "POST", "/login", None, Some(json!({ "username": "", "password": "" }))).await;
+    assert_eq!(field_errors(&empty), pairs(&[("password", "length"), ("username", "length")]));
+}
+
+#[tokio::test]
+async fn tasks_are_validated() {
+    let app = app();
+    let token = token(&app, "alice").await;
+
+    let created = call(&app, "POST", "/", Some(&token), Some(json!({
+        "title": "",
+        "description": "x".repeat(256),
+        "status": "todo"
+    }))).await;
+    assert_eq!(field_errors(&created), pairs(&[("description", "length"), ("title", "length")]));
+
+    let task = create_task(&app, &token, "Valid").await;
+    let updated = call(&app, "PATCH", "/", Some(&token), Some(json!({
+        "task_uuid": task["task_uuid"],
+        "title": "t".repeat(256),
+        "description": "",
+        "status": "todo",
+        "version": 1
+    }))).await;
+    assert_eq!(field_errors(&updated), pairs(&[("title", "length")]));
+    assert_eq!(updated.body["error"]["message"], "Invalid request: title must be between 1 and 255 characters");
+
+    // Bodies that do not match the type get the envelope too.
+    let missing = call(&app, "POST", "/", Some(&token), Some(json!({ "title": "No status" }))).await;
+    assert_eq!(missing.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", missing.body);
+    assert_eq!(missing.body["error"]["code"], "validation_failed");
+
+    let mut garbled = request("POST", "/", Some(&token), None);
+    *garbled.body_mut() = Body::from("{not json");
+    garbled.headers_mut().insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+    let garbled = send(&app, garbled).await;
+    assert_eq!(garbled.status, StatusCode::BAD_REQUEST, "{}", garbled.body);
+    assert_eq!(garbled.body["error"]["code"], "invalid_input");
+}
+
+#[tokio::test]
+async fn projects_are_validated() {
+    let app = app();
+    let token = token(&app, "alice").await;
+
+    let empty = call(&app, "POST", "/projects", Some(&token), Some(json!({ "name": "" }))).await;
+    assert_eq!(field_errors(&empty), pairs(&[("name", "length")]));
+    let long = call(&app, "POST", "/projects", Some(&token), Some(json!({ "name": "p".repeat(256) }))).await;
+    assert_eq!(field_errors(&long), pairs(&[("name", "length")]));
+
+    let project = call(&app, "POST", "/projects", Some(&token), Some(json!({ "name": "Launch" }))).await;
+    assert_eq!(project.status, StatusCode::CREATED, "{}", project.body);
+    let members = format!("/projects/{}/members", project.body["project_uuid"].as_str().unwrap());
+    let member = call(&app, "POST", &members, Some(&token), Some(json!({ "username": "", "role": "editor" }))).await;
+    assert_eq!(field_errors(&member), pairs(&[("username", "length")]));
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let app = app();
+    let mut request = request("GET", "/", None, None);
+    request.headers_mut().insert("x-request-id", "req-42".parse().unwrap());
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.headers()["x-request-id"], "req-42");
+    let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(body["error"]["request_id"], "req-42");
+
+    // Without one, the id is generated.
+    let response = call(&app, "GET", "/", None, None).await;
+    assert!(!response.body["error"]["request_id"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn auth_rejects_requests_without_a_token() {
+    let app = app();
+
+    let response = call(&app, "GET", "/", None, None).await;
+    assert_error(&response, StatusCode::UNAUTHORIZED, "unauthorized", "auth_token does not exist");
+}
+
+#[tokio::test]
+async fn auth_rejects_other_schemes() {
+    let app = app();
+    let mut basic = request("GET", "/", None, None);
+    basic.headers_mut().insert(header::AUTHORIZATION, "Basic YWxpY2U6cHc=".parse().unwrap());
+
+    let response = send(&app, basic).await;
+    assert_error(&response, StatusCode::UNAUTHORIZED, "unauthorized", "Authorization header must use the Bearer scheme");
+}
+
+#[tokio::test]
+async fn auth_rejects_invalid_tokens() {
+    let app = app();
+    let garbage = call(&app, "GET", "/", Some("not-a-jwt"), None).await;
+    assert_eq!(garbage.status, StatusCode::UNAUTHORIZED);
+    assert!(garbage.body["error"]["message"].as_str().unwrap().starts_with("auth_token is invalid"), "{}", garbage.body);
+
+    // A token of another deployment carries the same kid but a different signature.
+    let other = AuthConfig::from_secret("test", "other-secret").unwrap();
+    let forged = auth_lib::generate_token("alice", &other).unwrap();
+    let forged = call(&app, "GET", "/", Some(&forged), None).await;
+    assert_eq!(forged.status, StatusCode::UNAUTHORIZED);
+    assert!(forged.body["error"]["message"].as_str().unwrap().starts_with("auth_token is invalid"), "{}", forged.body);
+}
+
+#[tokio::test]
+async fn auth_accepts_the_legacy_header() {
+    let app = app();
+    let token = token(&app, "alice").await;
+    let mut legacy = request("GET", "/", None, None);
+    legacy.headers_mut().insert("auth_token", token.parse().unwrap());
+
+    let response = send(&app, legacy).await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+}
+
+#[tokio::test]
+async fn logout_revokes_the_session() {
+    let app = app();
+    let login = register_and_login(&app, "alice").await;
+    let token = login["token"].as_str().unwrap();
+    let refresh_token = login["refresh_token"].as_str().unwrap();
+
+    let logout = call(&app, "POST", "/logout", Some(token), Some(json!({ "refresh_token": refresh_token }))).await;
+    assert_eq!(logout.status, StatusCode::NO_CONTENT);
+
+    let response = call(&app, "GET", "/", Some(token), None).await;
+    assert_error(&response, StatusCode::UNAUTHORIZED, "unauthorized", "auth_token has been revoked");
+
+    let refresh = call(&app, "POST", "/token/refresh", None, Some(json!({ "refresh_token": refresh_token }))).await;
+    assert_error(&refresh, StatusCode::UNAUTHORIZED, "unauthorized", "refresh_token is invalid or expired");
+}